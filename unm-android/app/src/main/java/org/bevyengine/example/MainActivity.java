@@ -15,6 +15,7 @@ import android.view.SurfaceView;
 import android.view.View;
 import android.view.ViewGroup;
 import android.view.WindowManager;
+import android.view.inputmethod.InputMethodManager;
 import android.widget.FrameLayout;
 import android.widget.ImageView;
 
@@ -149,6 +150,47 @@ public class MainActivity extends GameActivity {
         return (display != null) ? display.getRefreshRate() : 60.0f;
     }
 
+    /**
+     * 由 Rust 端调用，锁定屏幕方向，参数为 ActivityInfo.SCREEN_ORIENTATION_* 常量
+     */
+    public void setOrientation(int orientation) {
+        runOnUiThread(() -> setRequestedOrientation(orientation));
+    }
+
+    /**
+     * 由 Rust 端调用，保持屏幕常亮。窗口标志只能在 UI 线程上修改
+     */
+    public void setKeepScreenOn(boolean keepOn) {
+        runOnUiThread(() -> {
+            if (keepOn) {
+                getWindow().addFlags(WindowManager.LayoutParams.FLAG_KEEP_SCREEN_ON);
+            } else {
+                getWindow().clearFlags(WindowManager.LayoutParams.FLAG_KEEP_SCREEN_ON);
+            }
+        });
+    }
+
+    /**
+     * 由 Rust 端调用，显示或隐藏软键盘。InputMethodManager 只能在 UI 线程上使用
+     */
+    public void showSoftKeyboard(boolean show) {
+        runOnUiThread(() -> {
+            InputMethodManager imm = (InputMethodManager) getSystemService(Context.INPUT_METHOD_SERVICE);
+            if (imm == null) return;
+
+            View view = getCurrentFocus();
+            if (view == null) {
+                view = getWindow().getDecorView();
+            }
+            if (show) {
+                view.requestFocus();
+                imm.showSoftInput(view, InputMethodManager.SHOW_IMPLICIT);
+            } else {
+                imm.hideSoftInputFromWindow(view.getWindowToken(), 0);
+            }
+        });
+    }
+
     public void setupDisplayCutoutHandling() {
         // 1. 设置窗口延伸至刘海区域
         WindowManager.LayoutParams attributes = getWindow().getAttributes();
@@ -4,7 +4,7 @@ pub mod oboe;
 #[cfg(not(target_os = "android"))]
 pub mod cpal;
 
-use crate::clip::SfxHandle;
+use crate::{atlas::RawSource, clip::{SfxCommand, SfxHandle}};
 
 pub trait AudioBackend {
     // 构建流
@@ -13,9 +13,18 @@ pub trait AudioBackend {
     // 检查流是否关闭/失效，如失效并且有音效则重建
     fn maintain_stream(&mut self);
 
-    // 初始化音效
+    // 解码并追加音效
     fn init_load_sound(&mut self, datas: Vec<Vec<u8>>) -> Option<Vec<SfxHandle>>;
 
-    // 尝试播放音效
-    fn play(&mut self, handle: SfxHandle);
+    // 预留一个音效句柄，之后由 add_sounds 填入
+    fn reserve_sound(&mut self) -> SfxHandle;
+
+    // 为预留的句柄填入已解码的音效。音频流已建立时把新的图集交给音频回调替换，正在播放的声音保持不变
+    fn add_sounds(&mut self, sounds: Vec<(SfxHandle, RawSource)>) -> bool;
+
+    // 向音频回调发送播放/停止指令，指令队列已满时返回 false
+    fn send(&mut self, command: SfxCommand) -> bool;
+
+    // 输出设备的采样率
+    fn sample_rate(&self) -> u32;
 }
\ No newline at end of file
@@ -5,25 +5,22 @@ use std::sync::{
 };
 
 // 第三方 crate 导入
-use ringbuf::{
-    HeapRb,
-    traits::{Consumer, Producer, Split}
-};
+use ringbuf::traits::Producer;
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use unm_tools::id_map::IdMap;
 
 // 当前 crate 内部模块导入
 use crate::atlas::{RawSource, SoundAtlas};
 use crate::backend::AudioBackend;
-use crate::clip::SfxHandle;
+use crate::clip::{SfxCommand, SfxHandle};
 use crate::decoder;
-use crate::mixer::Mixer;
+use crate::mixer::{self, CallbackQueues, GameQueues, Mixer};
 use crate::player::{GLOBAL_ATLAS, GLOBAL_MIXER};
 
 
 pub struct Player {
-    producer: ringbuf::HeapProd<SfxHandle>,
-    consumer: Option<ringbuf::HeapCons<SfxHandle>>,
+    queues: GameQueues,
+    callback_queues: Option<CallbackQueues>,
 
     stream: Option<cpal::Stream>,
 
@@ -34,37 +31,44 @@ pub struct Player {
 
 impl Player {
      pub(crate) fn new() -> Self {
-        let rb = HeapRb::<SfxHandle>::new(128);
-        let (prod, cons) = rb.split();
+        let (queues, callback_queues) = mixer::new_queues();
 
         Self {
             device_sample_rate: 48000,
             cached_sources: None,
             stream: None,
 
-            producer: prod,
-            consumer: Some(cons),
+            queues,
+            callback_queues: Some(callback_queues),
 
             device_lost: Arc::new(AtomicBool::new(false)),
         }
     }
 }
 
-impl AudioBackend for Player {
-    fn maintain_stream(&mut self) {
-        if self.device_lost.load(Ordering::Acquire) {
-            unsafe {
-                GLOBAL_MIXER = None;
-                GLOBAL_ATLAS = None;
-            }
+impl Player {
+    // 关闭音频流并清空混音器和图集，指令和图集队列随之重建
+    fn reset_stream(&mut self) {
+        // 先销毁音频流，保证回调不再访问全局的混音器和图集
+        self.stream = None;
+
+        unsafe {
+            GLOBAL_MIXER = None;
+            GLOBAL_ATLAS = None;
+        }
 
-            self.stream = None;
+        let (queues, callback_queues) = mixer::new_queues();
+        self.queues = queues;
+        self.callback_queues = Some(callback_queues);
+    }
+}
 
-            let rb = HeapRb::<SfxHandle>::new(128);
-            let (prod, cons) = rb.split();
-            self.producer = prod;
-            self.consumer = Some(cons);
+impl AudioBackend for Player {
+    fn maintain_stream(&mut self) {
+        self.queues.drop_retired();
 
+        if self.device_lost.load(Ordering::Acquire) {
+            self.reset_stream();
             self.device_lost.store(false, Ordering::Release);
         }
 
@@ -87,7 +91,7 @@ impl AudioBackend for Player {
         let channels = config.channels as usize;
         self.device_sample_rate = config.sample_rate;
 
-        let mut consumer = self.consumer.take().ok_or_else(|| {
+        let mut callback_queues = self.callback_queues.take().ok_or_else(|| {
             anyhow::anyhow!("Consumer handle lost - cannot rebuild stream without consumer")
         })?;
         let sources = self.cached_sources.as_ref().unwrap();
@@ -105,27 +109,8 @@ impl AudioBackend for Player {
 
         let stream = device.build_output_stream(
             &config,
-            move |data: &mut [f32], _| {
-                data.fill(0.0);
-
-                unsafe {
-                    if GLOBAL_MIXER.is_none() || GLOBAL_ATLAS.is_none() {
-                        return;
-                    }
-
-                    let mixer = GLOBAL_MIXER.as_mut().unwrap_unchecked();
-                    let atlas = GLOBAL_ATLAS.as_ref().unwrap_unchecked();
-
-                    // 1. 无锁消费指令
-                    while let Some(handle) = consumer.try_pop() {
-                        if let Some(map) = atlas.1.get(&handle) {
-                            mixer.add_sound(*map);
-                        }
-                    }
-
-                    // 2. 混音
-                    mixer.mix(channels, data);
-                }
+            move |data: &mut [f32], _| unsafe {
+                callback_queues.process(channels, data);
             },
             move |_| {
                 device_lost_trigger.store(true, Ordering::Release);
@@ -139,25 +124,51 @@ impl AudioBackend for Player {
     }
 
     fn init_load_sound(&mut self, datas: Vec<Vec<u8>>) -> Option<Vec<SfxHandle>> {
-        let mut sounds: IdMap<RawSource, SfxHandle> = IdMap::<RawSource, SfxHandle>::new();
+        let mut sources = Vec::with_capacity(datas.len());
         for data in datas {
-            let data = if let Ok(dasta) = decoder::decode(data) {
-                dasta
-            } else {
-                return None;
-            };
-            sounds.insert(data);
+            sources.push(decoder::decode(data).ok()?);
+        }
+
+        let sounds: Vec<_> = sources
+            .into_iter()
+            .map(|source| (self.reserve_sound(), source))
+            .collect();
+        let result = sounds.iter().map(|(handle, _)| *handle).collect();
+        self.add_sounds(sounds).then_some(result)
+    }
+
+    fn reserve_sound(&mut self) -> SfxHandle {
+        self.cached_sources.get_or_insert_with(IdMap::new).reserve()
+    }
+
+    fn add_sounds(&mut self, sounds: Vec<(SfxHandle, RawSource)>) -> bool {
+        let cached_sources = self.cached_sources.get_or_insert_with(IdMap::new);
+        for (handle, source) in sounds {
+            cached_sources.insert_reserved(handle, source);
+        }
+
+        if self.stream.is_none() {
+            self.reset_stream();
+            return self.build_stream().is_ok();
         }
 
-        let result = sounds.keys().collect();
-        self.cached_sources = Some(sounds);
-        match self.build_stream() {
-            Ok(_) => Some(result),
-            Err(_) => None,
+        // 在当前线程生成新图集，由音频回调换入，正在播放的声音保持不变
+        self.queues.drop_retired();
+        let atlas = SoundAtlas::build_from_sources(cached_sources, self.device_sample_rate);
+        if self.queues.atlases.try_push(atlas).is_ok() {
+            return true;
         }
+
+        // 音频回调长时间没有运行，只能重建音频流
+        self.reset_stream();
+        self.build_stream().is_ok()
+    }
+
+    fn send(&mut self, command: SfxCommand) -> bool {
+        self.queues.commands.try_push(command).is_ok()
     }
 
-    fn play(&mut self, handle: SfxHandle) {
-        let _ = self.producer.try_push(handle);
+    fn sample_rate(&self) -> u32 {
+        self.device_sample_rate
     }
 }
\ No newline at end of file
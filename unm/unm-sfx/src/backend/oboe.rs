@@ -5,21 +5,20 @@ use std::sync::{
 };
 
 // 第三方 crate 导入
-use ringbuf::HeapRb;
-use ringbuf::traits::{Consumer, Producer, Split};
+use ringbuf::traits::Producer;
 use unm_tools::id_map::IdMap;
 use oboe::{AudioOutputCallback, AudioStream, AudioStreamBuilder, DataCallbackResult, PerformanceMode, SharingMode, Usage, AudioStreamSafe, Stereo, AudioStreamBase, AudioStreamAsync, Output, AudioOutputStreamSafe, Error};
 
 // 当前 crate 内部模块导入
 use crate::atlas::{RawSource, SoundAtlas};
 use crate::backend::AudioBackend;
-use crate::clip::SfxHandle;
+use crate::clip::{SfxCommand, SfxHandle};
 use crate::decoder;
-use crate::mixer::Mixer;
+use crate::mixer::{self, CallbackQueues, GameQueues, Mixer};
 use crate::player::{GLOBAL_ATLAS, GLOBAL_MIXER};
 
 /// Oboe 音频回调结构体
-struct OboeCallback(ringbuf::HeapCons<SfxHandle>, Arc<AtomicBool>);
+struct OboeCallback(CallbackQueues, Arc<AtomicBool>);
 
 impl AudioOutputCallback for OboeCallback {
     type FrameType = (f32, Stereo);
@@ -29,35 +28,15 @@ impl AudioOutputCallback for OboeCallback {
         stream: &mut dyn AudioOutputStreamSafe,
         data: &mut [(f32, f32)],
     ) -> DataCallbackResult {
+        let data = unsafe {
+            std::slice::from_raw_parts_mut(
+                data.as_mut_ptr() as *mut f32,
+                data.len() * 2
+            )
+        };
+
         unsafe {
-            let data = unsafe {
-                std::slice::from_raw_parts_mut(
-                    data.as_mut_ptr() as *mut f32,
-                    data.len() * 2
-                )
-            };
-
-            // 1. 预填零（对应原代码 data.fill(0.0)）
-            data.fill(0.0);
-
-            // 2. 检查全局状态（保持原代码逻辑）
-            if GLOBAL_MIXER.is_none() || GLOBAL_ATLAS.is_none() {
-                return DataCallbackResult::Continue;
-            }
-
-            // 使用 unwrap_unchecked 以获得极致性能
-            let mixer = GLOBAL_MIXER.as_mut().unwrap_unchecked();
-            let atlas = GLOBAL_ATLAS.as_ref().unwrap_unchecked();
-
-            // 3. 无锁消费指令
-            while let Some(handle) = self.0.try_pop() {
-                if let Some(map) = atlas.1.get(&handle) {
-                    mixer.add_sound(*map);
-                }
-            }
-
-            // 4. 混音处理
-            mixer.mix(2, data);
+            self.0.process(2, data);
         }
 
         DataCallbackResult::Continue
@@ -73,8 +52,8 @@ impl AudioOutputCallback for OboeCallback {
 }
 
 pub struct Player {
-    producer: ringbuf::HeapProd<SfxHandle>,
-    consumer: Option<ringbuf::HeapCons<SfxHandle>>,
+    queues: GameQueues,
+    callback_queues: Option<CallbackQueues>,
 
     stream: Option<AudioStreamAsync<Output, OboeCallback>>,
 
@@ -85,40 +64,45 @@ pub struct Player {
 
 impl Player {
     pub(crate) fn new() -> Self {
-        let rb = HeapRb::<SfxHandle>::new(128);
-        let (prod, cons) = rb.split();
+        let (queues, callback_queues) = mixer::new_queues();
 
         Self {
             device_sample_rate: 48000, // Android 默认通常为 48k
             cached_sources: None,
             stream: None,
 
-            producer: prod,
-            consumer: Some(cons),
+            queues,
+            callback_queues: Some(callback_queues),
 
             device_lost: Arc::new(AtomicBool::new(false)),
         }
     }
 }
 
-impl AudioBackend for Player {
-    fn maintain_stream(&mut self) {
-        if self.device_lost.load(Ordering::Acquire) {
-            if let Some(mut s) = self.stream.take() {
-                let _ = s.stop(); // 确保回调停止执行
-            }
+impl Player {
+    // 关闭音频流并清空混音器和图集，指令和图集队列随之重建
+    fn reset_stream(&mut self) {
+        if let Some(mut s) = self.stream.take() {
+            let _ = s.stop(); // 确保回调停止执行
+        }
 
-            unsafe {
-                GLOBAL_MIXER = None;
-                GLOBAL_ATLAS = None;
-            }
-            self.stream = None;
+        unsafe {
+            GLOBAL_MIXER = None;
+            GLOBAL_ATLAS = None;
+        }
+
+        let (queues, callback_queues) = mixer::new_queues();
+        self.queues = queues;
+        self.callback_queues = Some(callback_queues);
+    }
+}
 
-            let rb = HeapRb::<SfxHandle>::new(128);
-            let (prod, cons) = rb.split();
-            self.producer = prod;
-            self.consumer = Some(cons);
+impl AudioBackend for Player {
+    fn maintain_stream(&mut self) {
+        self.queues.drop_retired();
 
+        if self.device_lost.load(Ordering::Acquire) {
+            self.reset_stream();
             self.device_lost.store(false, Ordering::Release);
         }
 
@@ -132,7 +116,7 @@ impl AudioBackend for Player {
             return Ok(());
         }
 
-        let mut consumer = self.consumer.take().ok_or_else(|| {
+        let callback_queues = self.callback_queues.take().ok_or_else(|| {
             anyhow::anyhow!("Consumer handle lost - cannot rebuild stream without consumer")
         })?;
 
@@ -168,7 +152,7 @@ impl AudioBackend for Player {
             .set_usage(Usage::Game)
             .set_channel_count::<Stereo>()
             .set_format::<f32>()
-            .set_callback(OboeCallback(consumer, device_lost_trigger))
+            .set_callback(OboeCallback(callback_queues, device_lost_trigger))
             .open_stream()?;
 
         stream.start()?;
@@ -177,25 +161,51 @@ impl AudioBackend for Player {
     }
 
     fn init_load_sound(&mut self, datas: Vec<Vec<u8>>) -> Option<Vec<SfxHandle>> {
-        let mut sounds: IdMap<RawSource, SfxHandle> = IdMap::<RawSource, SfxHandle>::new();
+        let mut sources = Vec::with_capacity(datas.len());
         for data in datas {
-            let data = if let Ok(dasta) = decoder::decode(data) {
-                dasta
-            } else {
-                return None;
-            };
-            sounds.insert(data);
+            sources.push(decoder::decode(data).ok()?);
+        }
+
+        let sounds: Vec<_> = sources
+            .into_iter()
+            .map(|source| (self.reserve_sound(), source))
+            .collect();
+        let result = sounds.iter().map(|(handle, _)| *handle).collect();
+        self.add_sounds(sounds).then_some(result)
+    }
+
+    fn reserve_sound(&mut self) -> SfxHandle {
+        self.cached_sources.get_or_insert_with(IdMap::new).reserve()
+    }
+
+    fn add_sounds(&mut self, sounds: Vec<(SfxHandle, RawSource)>) -> bool {
+        let cached_sources = self.cached_sources.get_or_insert_with(IdMap::new);
+        for (handle, source) in sounds {
+            cached_sources.insert_reserved(handle, source);
+        }
+
+        if self.stream.is_none() {
+            self.reset_stream();
+            return self.build_stream().is_ok();
         }
 
-        let result = sounds.keys().collect();
-        self.cached_sources = Some(sounds);
-        match self.build_stream() {
-            Ok(_) => Some(result),
-            Err(_) => None,
+        // 在当前线程生成新图集，由音频回调换入，正在播放的声音保持不变
+        self.queues.drop_retired();
+        let atlas = SoundAtlas::build_from_sources(cached_sources, self.device_sample_rate);
+        if self.queues.atlases.try_push(atlas).is_ok() {
+            return true;
         }
+
+        // 音频回调长时间没有运行，只能重建音频流
+        self.reset_stream();
+        self.build_stream().is_ok()
+    }
+
+    fn send(&mut self, command: SfxCommand) -> bool {
+        self.queues.commands.try_push(command).is_ok()
     }
 
-    fn play(&mut self, handle: SfxHandle) {
-        let _ = self.producer.try_push(handle);
+    fn sample_rate(&self) -> u32 {
+        self.device_sample_rate
     }
 }
\ No newline at end of file
@@ -0,0 +1,159 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Seek, SeekFrom, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicPtr, AtomicU16, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use ringbuf::{
+    traits::{Consumer, Producer, Split},
+    HeapCons, HeapProd, HeapRb,
+};
+
+// 音频回调写入的生产端，为空表示没有在录制
+static CAPTURE_PRODUCER: AtomicPtr<HeapProd<f32>> = AtomicPtr::new(std::ptr::null_mut());
+// 音频回调正在使用 CAPTURE_PRODUCER，停止录制时等待它变为 false 再释放生产端
+static CAPTURE_IN_USE: AtomicBool = AtomicBool::new(false);
+// 最近一次写入的声道数，写 WAV 文件头时使用
+static CAPTURE_CHANNELS: AtomicU16 = AtomicU16::new(2);
+// 缓冲区已满时丢弃的采样数，写入线程跟不上时非零
+static CAPTURE_DROPPED: AtomicU64 = AtomicU64::new(0);
+
+// 游戏线程持有的录制状态
+static ACTIVE_CAPTURE: Mutex<Option<Capture>> = Mutex::new(None);
+
+// 缓冲的时长，写入线程每 10 ms 取一次，留出足够余量
+const CAPTURE_BUFFER_SECONDS: usize = 2;
+const CAPTURE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+struct Capture {
+    stop: Arc<AtomicBool>,
+    writer: JoinHandle<anyhow::Result<u64>>,
+}
+
+/// 在音频回调中调用，把混音完成的一块复制到缓冲区，不会分配内存或阻塞
+pub(crate) fn push(channels: usize, out_data: &[f32]) {
+    CAPTURE_IN_USE.store(true, Ordering::SeqCst);
+    let producer = CAPTURE_PRODUCER.load(Ordering::SeqCst);
+    if !producer.is_null() {
+        CAPTURE_CHANNELS.store(channels as u16, Ordering::Relaxed);
+        let pushed = unsafe { (*producer).push_slice(out_data) };
+        if pushed < out_data.len() {
+            CAPTURE_DROPPED.fetch_add((out_data.len() - pushed) as u64, Ordering::Relaxed);
+        }
+    }
+    CAPTURE_IN_USE.store(false, Ordering::SeqCst);
+}
+
+pub(crate) fn start(path: &Path, sample_rate: u32) -> anyhow::Result<()> {
+    let mut active = ACTIVE_CAPTURE.lock().unwrap_or_else(|e| e.into_inner());
+    if active.is_some() {
+        anyhow::bail!("Audio capture is already running");
+    }
+
+    let mut file = BufWriter::new(File::create(path)?);
+    // 先写入占位的文件头，停止时再填入长度
+    write_wav_header(&mut file, 2, sample_rate, 0)?;
+
+    let capacity = sample_rate as usize * 2 * CAPTURE_BUFFER_SECONDS;
+    let (producer, consumer) = HeapRb::<f32>::new(capacity).split();
+    let stop = Arc::new(AtomicBool::new(false));
+    let writer_stop = stop.clone();
+    let writer = std::thread::Builder::new()
+        .name("unm-sfx-capture".to_owned())
+        .spawn(move || write_samples(file, consumer, sample_rate, writer_stop))?;
+
+    CAPTURE_DROPPED.store(0, Ordering::Relaxed);
+    CAPTURE_PRODUCER.store(Box::into_raw(Box::new(producer)), Ordering::SeqCst);
+    *active = Some(Capture { stop, writer });
+    Ok(())
+}
+
+/// 停止录制并等待文件写完，返回写入的采样数
+pub(crate) fn stop() -> anyhow::Result<u64> {
+    let Some(capture) = ACTIVE_CAPTURE.lock().unwrap_or_else(|e| e.into_inner()).take() else {
+        anyhow::bail!("Audio capture is not running");
+    };
+
+    // 先让音频回调看不到生产端，等它用完后在游戏线程中释放
+    let producer = CAPTURE_PRODUCER.swap(std::ptr::null_mut(), Ordering::SeqCst);
+    while CAPTURE_IN_USE.load(Ordering::SeqCst) {
+        std::hint::spin_loop();
+    }
+    if !producer.is_null() {
+        drop(unsafe { Box::from_raw(producer) });
+    }
+
+    capture.stop.store(true, Ordering::Release);
+    let written = capture
+        .writer
+        .join()
+        .map_err(|_| anyhow::anyhow!("Audio capture thread panicked"))??;
+
+    let dropped = CAPTURE_DROPPED.load(Ordering::Relaxed);
+    if dropped > 0 {
+        anyhow::bail!("Audio capture dropped {} samples, the file has gaps", dropped);
+    }
+    Ok(written)
+}
+
+// 写入线程：取出缓冲区中的采样写入文件，停止后补全文件头
+fn write_samples(
+    mut file: BufWriter<File>,
+    mut consumer: HeapCons<f32>,
+    sample_rate: u32,
+    stop: Arc<AtomicBool>,
+) -> anyhow::Result<u64> {
+    let mut block = vec![0.0f32; 4096];
+    let mut written: u64 = 0;
+    loop {
+        // 先读取停止标志，保证停止之前推入的采样都会被取出
+        let stopping = stop.load(Ordering::Acquire);
+        let count = consumer.pop_slice(&mut block);
+        for sample in &block[..count] {
+            file.write_all(&sample.to_le_bytes())?;
+        }
+        written += count as u64;
+
+        if count == 0 {
+            if stopping {
+                break;
+            }
+            std::thread::sleep(CAPTURE_POLL_INTERVAL);
+        }
+    }
+
+    let channels = CAPTURE_CHANNELS.load(Ordering::Relaxed);
+    file.seek(SeekFrom::Start(0))?;
+    write_wav_header(&mut file, channels, sample_rate, written * 4)?;
+    file.flush()?;
+    Ok(written)
+}
+
+// 32 位浮点 PCM 的 WAV 文件头
+fn write_wav_header(file: &mut impl Write, channels: u16, sample_rate: u32, data_bytes: u64) -> anyhow::Result<()> {
+    let data_bytes = data_bytes.min(u32::MAX as u64 - 36) as u32;
+    let block_align = channels * 4;
+
+    file.write_all(b"RIFF")?;
+    file.write_all(&(36 + data_bytes).to_le_bytes())?;
+    file.write_all(b"WAVE")?;
+
+    file.write_all(b"fmt ")?;
+    file.write_all(&16u32.to_le_bytes())?;
+    file.write_all(&3u16.to_le_bytes())?; // WAVE_FORMAT_IEEE_FLOAT
+    file.write_all(&channels.to_le_bytes())?;
+    file.write_all(&sample_rate.to_le_bytes())?;
+    file.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+    file.write_all(&block_align.to_le_bytes())?;
+    file.write_all(&32u16.to_le_bytes())?;
+
+    file.write_all(b"data")?;
+    file.write_all(&data_bytes.to_le_bytes())?;
+    Ok(())
+}
@@ -11,6 +11,33 @@ impl IdMapKey for SfxHandle {
     fn to(&self) -> u64 { self.0 }
 }
 
+/// `SfxManager::play` 返回的一次播放，用于查询它是否仍在播放
+#[derive(Default, Eq, PartialEq, Clone, Copy, Hash, Debug)]
+pub struct PlayingVoice(pub(crate) u64);
+
+impl PlayingVoice {
+    /// 没有对应任何播放，例如指令队列已满时返回
+    pub const NONE: Self = PlayingVoice(0);
+}
+
+/// 从游戏线程发送给音频回调的指令
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum SfxCommand {
+    Play(PlayCommand),
+    // 停止指定 id 的声音
+    Stop(u64),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct PlayCommand {
+    pub handle: SfxHandle,
+    pub voice: u64,
+    // 开始播放的采样时钟，None 表示尽快播放
+    pub start_frame: Option<u64>,
+    // 循环区间 [loop_start, loop_end)（帧），None 表示只播放一次
+    pub loop_region: Option<(u64, u64)>,
+}
+
 #[derive(Clone, Copy)]
 pub(crate) struct ClipMap {
     pub data_ptr: *const f32,
@@ -31,17 +31,17 @@ pub(crate) fn decode(data: Vec<u8>) -> anyhow::Result<RawSource> {
 
     let probed = symphonia::default::get_probe()
         .format(&Hint::new(), mss, &FormatOptions::default(), &MetadataOptions::default())
-        .expect("不支持的音频格式");
+        .map_err(|e| anyhow::anyhow!("Unsupported audio format: {}", e))?;
 
     let mut format = probed.format;
 
     let track = format.tracks().iter()
         .find(|t| t.codec_params.codec != CODEC_TYPE_NULL && t.codec_params.sample_rate.is_some())
-        .expect("未找到音频轨道");
+        .ok_or_else(|| anyhow::anyhow!("No audio track found"))?;
 
     let mut decoder = symphonia::default::get_codecs()
         .make(&track.codec_params, &DecoderOptions::default())
-        .expect("无法创建解码器");
+        .map_err(|e| anyhow::anyhow!("Failed to create audio decoder: {}", e))?;
 
     let sample_rate = track.codec_params.sample_rate.unwrap_or(48000);
     let track_id = track.id;
@@ -3,5 +3,8 @@ pub mod player;
 
 mod atlas;
 mod backend;
+#[cfg(feature = "capture")]
+mod capture;
 mod decoder;
-mod mixer;
\ No newline at end of file
+mod mixer;
+mod voice;
\ No newline at end of file
@@ -1,79 +1,267 @@
-use crate::clip::ClipMap;
+use std::{collections::HashMap, sync::atomic::Ordering};
+
+use ringbuf::{
+    traits::{Consumer, Producer, Split},
+    HeapCons, HeapProd, HeapRb,
+};
+
+use crate::{
+    clip::{ClipMap, PlayCommand, SfxCommand, SfxHandle},
+    player::{Atlas, VoiceStealPolicy, GLOBAL_ATLAS, GLOBAL_MIXER, MAX_VOICES, MAX_VOICES_LIMIT, SAMPLE_CLOCK},
+    voice,
+};
+
+// 指令队列的容量
+const COMMAND_QUEUE_LEN: usize = 128;
+// 等待换入以及换下等待释放的图集数量，游戏线程每次加入音效前都会释放换下的图集
+const ATLAS_QUEUE_LEN: usize = 4;
+
+/// 游戏线程一侧的队列
+pub(crate) struct GameQueues {
+    pub(crate) commands: HeapProd<SfxCommand>,
+    pub(crate) atlases: HeapProd<Atlas>,
+    pub(crate) retired: HeapCons<Atlas>,
+}
+
+impl GameQueues {
+    /// 释放音频回调换下的旧图集
+    pub(crate) fn drop_retired(&mut self) {
+        while self.retired.try_pop().is_some() {}
+    }
+}
+
+/// 音频回调一侧的队列：播放指令、新的图集，以及换下来交给游戏线程释放的旧图集
+pub(crate) struct CallbackQueues {
+    commands: HeapCons<SfxCommand>,
+    atlases: HeapCons<Atlas>,
+    retired: HeapProd<Atlas>,
+}
+
+/// 创建一组新的队列，音频流重建时随之重建
+pub(crate) fn new_queues() -> (GameQueues, CallbackQueues) {
+    let (command_prod, command_cons) = HeapRb::<SfxCommand>::new(COMMAND_QUEUE_LEN).split();
+    let (atlas_prod, atlas_cons) = HeapRb::<Atlas>::new(ATLAS_QUEUE_LEN).split();
+    let (retired_prod, retired_cons) = HeapRb::<Atlas>::new(ATLAS_QUEUE_LEN).split();
+    (
+        GameQueues {
+            commands: command_prod,
+            atlases: atlas_prod,
+            retired: retired_cons,
+        },
+        CallbackQueues {
+            commands: command_cons,
+            atlases: atlas_cons,
+            retired: retired_prod,
+        },
+    )
+}
+
+impl CallbackQueues {
+    /// 在音频回调中调用：换入新的图集、处理指令并混音到交错排列的 `data`
+    ///
+    /// # Safety
+    /// 只能在音频回调中调用，且调用期间游戏线程不能访问 `GLOBAL_MIXER`/`GLOBAL_ATLAS`
+    pub(crate) unsafe fn process(&mut self, channels: usize, data: &mut [f32]) {
+        data.fill(0.0);
+
+        unsafe {
+            if GLOBAL_MIXER.is_none() || GLOBAL_ATLAS.is_none() {
+                return;
+            }
+
+            let mixer = GLOBAL_MIXER.as_mut().unwrap_unchecked();
+            self.swap_atlas(mixer);
+
+            // 1. 无锁消费指令
+            while let Some(command) = self.commands.try_pop() {
+                // 指令可能引用刚加入的音效，在它之前发送的图集此时一定已经可见
+                self.swap_atlas(mixer);
+                mixer.apply(command, &GLOBAL_ATLAS.as_ref().unwrap_unchecked().1);
+            }
+
+            // 2. 混音
+            mixer.mix(channels, data);
+        }
+    }
+
+    // 换入游戏线程生成的新图集，正在播放的声音改为指向新图集中的同一音效
+    unsafe fn swap_atlas(&mut self, mixer: &mut Mixer) {
+        while let Some(atlas) = self.atlases.try_pop() {
+            mixer.remap_clips(&atlas.1);
+            let old = unsafe { std::ptr::replace(&raw mut GLOBAL_ATLAS, Some(atlas)) };
+            // 释放内存可能阻塞，交给游戏线程；队列已满时只能在这里释放
+            if let Some(old) = old {
+                let _ = self.retired.try_push(old);
+            }
+        }
+    }
+}
 
 struct SoundState {
+    handle: SfxHandle,
     clip: ClipMap,
+    voice: u64,
     cursor: usize,
+    // 开始播放时的输出采样时钟（帧）
+    start_frame: u64,
+    // 循环区间 [loop_start, loop_end)，播放到 loop_end 时跳回 loop_start
+    loop_region: Option<(usize, usize)>,
 }
 
-pub(crate) struct Mixer(Vec<SoundState>);
+pub(crate) struct Mixer {
+    sounds: Vec<SoundState>,
+    // 已输出的帧数，即当前音频块第一帧的采样时钟
+    clock: u64,
+    // 已经取出的最大声音 id
+    consumed_voice: u64,
+}
 
 impl Mixer {
     pub(crate) fn new() -> Self {
-        Self(Vec::with_capacity(128))
+        SAMPLE_CLOCK.store(0, Ordering::Release);
+        voice::reset();
+        Self {
+            sounds: Vec::with_capacity(MAX_VOICES_LIMIT),
+            clock: 0,
+            consumed_voice: 0,
+        }
+    }
+
+    /// 在音频回调中处理一条指令
+    pub(crate) fn apply(&mut self, command: SfxCommand, clips: &HashMap<SfxHandle, ClipMap>) {
+        match command {
+            SfxCommand::Play(play) => self.add_sound(play, clips.get(&play.handle).copied()),
+            SfxCommand::Stop(voice) => {
+                if let Some(i) = self.sounds.iter().position(|s| s.voice == voice) {
+                    self.sounds.swap_remove(i);
+                }
+            }
+        }
     }
 
-    pub(crate) fn add_sound(&mut self, clip: ClipMap) {
-        self.0.push(SoundState { clip, cursor: 0 });
+    /// 在音频回调中调用，不会分配内存：未达到上限时追加，否则按策略替换一个正在播放的声音。
+    /// `start_frame` 为开始播放的采样时钟，`None` 或已经过去时从当前音频块开头播放。
+    /// `clip` 为 None（音效不存在）时只记录该指令已被取出
+    fn add_sound(&mut self, command: PlayCommand, clip: Option<ClipMap>) {
+        self.consumed_voice = self.consumed_voice.max(command.voice);
+        let Some(clip) = clip else {
+            return;
+        };
+        let new_sound = SoundState {
+            handle: command.handle,
+            clip,
+            voice: command.voice,
+            cursor: 0,
+            start_frame: command.start_frame.unwrap_or(0).max(self.clock),
+            loop_region: command.loop_region.and_then(|(start, end)| {
+                let end = (end as usize).min(clip.frames_count);
+                let start = start as usize;
+                (start < end).then_some((start, end))
+            }),
+        };
+        let max_voices = MAX_VOICES.load(Ordering::Relaxed);
+        if self.sounds.len() < max_voices {
+            self.sounds.push(new_sound);
+            return;
+        }
+
+        // 上限调低后可能超出，先截断到上限
+        self.sounds.truncate(max_voices);
+
+        // 循环的声音 cursor 会跳回，按开始播放的时钟判断先后；它也不会自然结束，视为剩余时长无限
+        let victim = match VoiceStealPolicy::load() {
+            VoiceStealPolicy::Oldest => self.sounds.iter().enumerate().min_by_key(|(_, s)| s.start_frame),
+            VoiceStealPolicy::NearestEnd => self.sounds.iter().enumerate().min_by_key(|(_, s)| {
+                if s.loop_region.is_some() { usize::MAX } else { s.clip.frames_count - s.cursor }
+            }),
+            VoiceStealPolicy::Reject => None,
+        };
+        if let Some((i, _)) = victim {
+            self.sounds[i] = new_sound;
+        }
+    }
+
+    /// 图集替换后，把正在播放的声音指向新图集中的同一音效。
+    /// 采样率不变时音效的帧数相同，播放位置与循环区间保持不变
+    pub(crate) fn remap_clips(&mut self, clips: &HashMap<SfxHandle, ClipMap>) {
+        let mut i = 0;
+        while i < self.sounds.len() {
+            match clips.get(&self.sounds[i].handle) {
+                Some(&clip) if clip.frames_count == self.sounds[i].clip.frames_count => {
+                    self.sounds[i].clip = clip;
+                    i += 1;
+                }
+                _ => {
+                    self.sounds.swap_remove(i);
+                }
+            }
+        }
     }
 
     pub(crate) fn mix(&mut self, channels: usize, out_data: &mut [f32]) {
-        let sounds = &mut self.0;
+        let out_frames = out_data.len() / channels;
+        let block_start = self.clock;
+        self.clock += out_frames as u64;
+        SAMPLE_CLOCK.store(self.clock, Ordering::Release);
+
+        self.mix_sounds(channels, block_start, out_data);
+
+        #[cfg(feature = "capture")]
+        crate::capture::push(channels, out_data);
+
+        // 发布本块结束后仍在播放的声音，供游戏线程查询
+        voice::publish(self.consumed_voice, self.sounds.iter().map(|s| s.voice));
+    }
+
+    fn mix_sounds(&mut self, channels: usize, block_start: u64, out_data: &mut [f32]) {
+        let out_frames = out_data.len() / channels;
+        let sounds = &mut self.sounds;
         if sounds.is_empty() {
             return;
         }
 
-        let out_frames = out_data.len() / channels;
         let out_ptr = out_data.as_mut_ptr();
         let mut i = 0;
 
         while i < sounds.len() {
             let sound = unsafe { sounds.get_unchecked_mut(i) };
-            let mix_frames = out_frames.min(sound.clip.frames_count - sound.cursor);
 
-            if mix_frames == 0 {
-                sounds.swap_remove(i);
+            // 预约的声音在本块内开始时，从对应的帧开始写入，之前保持静音
+            let delay = sound.start_frame.saturating_sub(block_start);
+            if delay >= out_frames as u64 {
+                i += 1;
                 continue;
             }
 
-            unsafe {
-                // src_ptr 现在直接指向单声道数据
-                let src_ptr = sound.clip.data_ptr.add(sound.cursor);
+            // 循环区间内可能在一个块中多次跳回 loop_start，按段写入，跳转精确到采样
+            let mut written = delay as usize;
+            let mut finished = false;
+            while written < out_frames {
+                let end = sound.loop_region.map_or(sound.clip.frames_count, |(_, end)| end);
+                let mix_frames = (out_frames - written).min(end - sound.cursor);
+                unsafe {
+                    mix_segment(
+                        channels,
+                        out_ptr.add(written * channels),
+                        sound.clip.data_ptr.add(sound.cursor),
+                        mix_frames,
+                    );
+                }
+                written += mix_frames;
+                sound.cursor += mix_frames;
 
-                // 使用 match 优化常见的 channels 数量，兼顾缓存命中率
-                match channels {
-                    1 => {
-                        // 输出单声道：直接将源单声道数据拷贝到目标单声道缓冲区
-                        for j in 0..mix_frames {
-                            *out_ptr.add(j) += *src_ptr.add(j);
-                        }
-                    }
-                    2 => {
-                        // 输出双声道：将源单声道数据拷贝到左右两个声道
-                        // 这样访问 out_ptr 是连续的 (L, R, L, R...)
-                        for j in 0..mix_frames {
-                            let mono_sample = *src_ptr.add(j);
-                            let out_base_idx = j * 2;
-                            *out_ptr.add(out_base_idx) += mono_sample;     // 左声道
-                            *out_ptr.add(out_base_idx + 1) += mono_sample; // 右声道
-                        }
-                    }
-                    // 默认情况：通用处理，可能会有缓存损失，但适用于所有其他声道数
-                    _ => {
-                        for j in 0..mix_frames {
-                            let mono_sample = *src_ptr.add(j);
-                            // 确保内层循环是连续访问 out_ptr
-                            let out_frame_base_idx = j * channels;
-                            for c in 0..channels {
-                                *out_ptr.add(out_frame_base_idx + c) += mono_sample;
-                            }
+                if sound.cursor >= end {
+                    match sound.loop_region {
+                        Some((start, _)) => sound.cursor = start,
+                        None => {
+                            finished = true;
+                            break;
                         }
                     }
                 }
             }
 
-            sound.cursor += mix_frames;
-
-            if sound.cursor >= sound.clip.frames_count {
+            if finished {
                 sounds.swap_remove(i);
             } else {
                 i += 1;
@@ -84,4 +272,87 @@ impl Mixer {
             *sample = sample.clamp(-1.0, 1.0);
         }
     }
-}
\ No newline at end of file
+}
+
+// 把单声道源数据叠加到交错排列的输出缓冲区
+unsafe fn mix_segment(channels: usize, out_ptr: *mut f32, src_ptr: *const f32, frames: usize) {
+    unsafe {
+        // 使用 match 优化常见的 channels 数量，兼顾缓存命中率
+        match channels {
+            1 => {
+                // 输出单声道：直接将源单声道数据拷贝到目标单声道缓冲区
+                for j in 0..frames {
+                    *out_ptr.add(j) += *src_ptr.add(j);
+                }
+            }
+            2 => {
+                // 输出双声道：将源单声道数据拷贝到左右两个声道
+                // 这样访问 out_ptr 是连续的 (L, R, L, R...)
+                for j in 0..frames {
+                    let mono_sample = *src_ptr.add(j);
+                    let out_base_idx = j * 2;
+                    *out_ptr.add(out_base_idx) += mono_sample;     // 左声道
+                    *out_ptr.add(out_base_idx + 1) += mono_sample; // 右声道
+                }
+            }
+            // 默认情况：通用处理，可能会有缓存损失，但适用于所有其他声道数
+            _ => {
+                for j in 0..frames {
+                    let mono_sample = *src_ptr.add(j);
+                    // 确保内层循环是连续访问 out_ptr
+                    let out_frame_base_idx = j * channels;
+                    for c in 0..channels {
+                        *out_ptr.add(out_frame_base_idx + c) += mono_sample;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Mixer {
+    fn drop(&mut self) {
+        voice::reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use unm_tools::id_map::IdMap;
+
+    use super::*;
+    use crate::atlas::{RawSource, SoundAtlas};
+
+    fn source(frames_count: usize) -> RawSource {
+        RawSource {
+            data: vec![0.5; frames_count].into(),
+            sample_rate: 1,
+            frames_count,
+        }
+    }
+
+    #[test]
+    fn remap_clips_keeps_playing_sounds() {
+        let mut sources = IdMap::new();
+        let music = sources.insert(source(64));
+        let (_atlas, clips) = SoundAtlas::build_from_sources(&sources, 1);
+
+        let mut mixer = Mixer::new();
+        let play = PlayCommand { handle: music, voice: 1, start_frame: None, loop_region: None };
+        mixer.apply(SfxCommand::Play(play), &clips);
+        mixer.mix(1, &mut [0.0; 16]);
+        assert_eq!(mixer.sounds[0].cursor, 16);
+
+        // 追加音效后重新生成图集，正在播放的声音继续从原位置播放
+        sources.insert(source(8));
+        let (_new_atlas, new_clips) = SoundAtlas::build_from_sources(&sources, 1);
+        mixer.remap_clips(&new_clips);
+        assert_eq!(mixer.sounds.len(), 1);
+        assert_eq!(mixer.sounds[0].cursor, 16);
+        assert_eq!(mixer.sounds[0].clip.data_ptr, new_clips[&music].data_ptr);
+
+        // 音效不在新图集中时停止播放
+        mixer.remap_clips(&HashMap::new());
+        assert!(mixer.sounds.is_empty());
+    }
+}
@@ -1,7 +1,54 @@
-use crate::{atlas::SoundAtlas, backend::AudioBackend, clip::{ClipMap, SfxHandle}, mixer::Mixer};
+use std::sync::atomic::{AtomicU64, AtomicU8, AtomicUsize, Ordering};
+
+use crate::{atlas::{RawSource, SoundAtlas}, backend::AudioBackend, clip::{ClipMap, PlayCommand, PlayingVoice, SfxCommand, SfxHandle}, mixer::Mixer, voice};
+
+/// 音频图集与其中每个音效的位置
+pub(crate) type Atlas = (SoundAtlas, std::collections::HashMap<SfxHandle, ClipMap>);
 
 pub(crate) static mut GLOBAL_MIXER: Option<Mixer> = None;
-pub(crate) static mut GLOBAL_ATLAS: Option<(SoundAtlas, std::collections::HashMap<SfxHandle, ClipMap>)> = None;
+pub(crate) static mut GLOBAL_ATLAS: Option<Atlas> = None;
+
+/// 默认同时播放的最大声音数
+pub const DEFAULT_MAX_VOICES: usize = 128;
+/// 最大声音数的上限，混音器按这个数量预分配，音频回调中不会再分配内存
+pub const MAX_VOICES_LIMIT: usize = 256;
+
+// 音频回调线程读取的混音配置
+pub(crate) static MAX_VOICES: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_VOICES);
+// 混音器已输出的帧数，音频流重建时归零
+pub(crate) static SAMPLE_CLOCK: AtomicU64 = AtomicU64::new(0);
+pub(crate) static VOICE_STEAL_POLICY: AtomicU8 = AtomicU8::new(VoiceStealPolicy::Oldest as u8);
+
+/// 同时播放的声音达到上限时如何处理新的声音
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum VoiceStealPolicy {
+    /// 停止最早开始播放的声音
+    #[default]
+    Oldest,
+    /// 停止剩余时长最短的声音，它通常已经处于衰减的尾音
+    NearestEnd,
+    /// 丢弃新的声音
+    Reject,
+}
+
+impl VoiceStealPolicy {
+    pub(crate) fn load() -> Self {
+        match VOICE_STEAL_POLICY.load(Ordering::Relaxed) {
+            1 => Self::NearestEnd,
+            2 => Self::Reject,
+            _ => Self::Oldest,
+        }
+    }
+}
+
+/// 已经解码、尚未加入音频流的音效，由 `decode_sound` 生成，可在任意线程中解码
+pub struct DecodedSound(pub(crate) RawSource);
+
+/// 解码音效数据，不涉及音频设备，适合放到后台线程中执行
+pub fn decode_sound(data: Vec<u8>) -> anyhow::Result<DecodedSound> {
+    crate::decoder::decode(data).map(DecodedSound)
+}
 
 pub struct SfxManager(Box<dyn AudioBackend>);
 
@@ -22,11 +69,118 @@ impl SfxManager {
         self.0.maintain_stream()
     }
 
+    /// 解码并加入一批音效，可多次调用；任意一个解码失败时不加入任何音效并返回 None。
+    /// 新的图集在当前线程中生成后交给音频回调替换，正在播放的声音不受影响
     pub fn init_load_sound(&mut self, datas: Vec<Vec<u8>>) -> Option<Vec<SfxHandle>> {
         self.0.init_load_sound(datas)
     }
 
-    pub fn play(&mut self, handle: SfxHandle) {
-        self.0.play(handle);
+    /// 预留一个音效句柄，解码完成后用 `add_sounds` 填入。填入之前播放该句柄不会发出声音
+    pub fn reserve_sound(&mut self) -> SfxHandle {
+        self.0.reserve_sound()
+    }
+
+    /// 为 `reserve_sound` 预留的句柄填入已解码的音效。
+    /// 每次加入都会在当前线程中按全部音效重新生成图集，应尽量一次加入一批；
+    /// 音频流不会重建，正在播放的声音不受影响
+    pub fn add_sounds(&mut self, sounds: Vec<(SfxHandle, DecodedSound)>) -> bool {
+        let sounds = sounds.into_iter().map(|(handle, sound)| (handle, sound.0)).collect();
+        self.0.add_sounds(sounds)
+    }
+
+    /// 播放音效，返回的 `PlayingVoice` 可用于 `is_playing` 查询
+    pub fn play(&mut self, handle: SfxHandle) -> PlayingVoice {
+        self.send_play(handle, None, None)
+    }
+
+    /// 播放音效，播放到 `loop_end` 时无缝跳回 `loop_start` 并一直循环，直到 `stop`。
+    /// 适合“前奏只播放一次，之后循环主体”的音乐。
+    /// 区间以帧为单位（输出采样率，见 `get_sample_rate`），`loop_end` 超出音效长度时截断到末尾；
+    /// 区间为空时只播放一次
+    pub fn play_looping_region(&mut self, handle: SfxHandle, loop_start: u64, loop_end: u64) -> PlayingVoice {
+        self.send_play(handle, None, Some((loop_start, loop_end)))
+    }
+
+    /// 停止正在播放（或预约）的声音，已经结束时不做任何事
+    pub fn stop(&mut self, voice: PlayingVoice) {
+        if voice != PlayingVoice::NONE {
+            self.0.send(SfxCommand::Stop(voice.0));
+        }
+    }
+
+    fn send_play(&mut self, handle: SfxHandle, start_frame: Option<u64>, loop_region: Option<(u64, u64)>) -> PlayingVoice {
+        let id = voice::next_voice_id();
+        let command = PlayCommand { handle, voice: id, start_frame, loop_region };
+        if self.0.send(SfxCommand::Play(command)) { PlayingVoice(id) } else { PlayingVoice::NONE }
+    }
+
+    /// 在输出采样时钟到达 `sample_offset`（帧）时开始播放，精确到采样，适合节奏游戏。
+    /// 用 `playback_samples() + 秒数 * get_sample_rate()` 计算目标时钟；时钟已经过去时立即播放。
+    /// 音频设备重建后时钟从 0 重新开始
+    pub fn play_at(&mut self, handle: SfxHandle, sample_offset: u64) -> PlayingVoice {
+        self.send_play(handle, Some(sample_offset), None)
+    }
+
+    /// 声音是否仍在播放，不会阻塞音频线程。
+    /// 刚调用 `play` 还未被音频线程取出的声音，以及 `play_at` 预约但尚未开始的声音都算作正在播放；
+    /// 被抢占、被 `VoiceStealPolicy::Reject` 丢弃或音频设备重建后返回 false
+    pub fn is_playing(&self, voice: PlayingVoice) -> bool {
+        voice::is_playing(voice.0)
+    }
+
+    /// 混音器当前持有的声音数（含预约尚未开始的），在每个音频块结束时更新
+    pub fn active_voice_count(&self) -> usize {
+        voice::active_voice_count()
+    }
+
+    /// 当前的输出采样时钟，即混音器已经输出的帧数，可在游戏线程中读取，用于让画面跟随音乐。
+    ///
+    /// 时钟在每个音频块混音完成时更新，因此按块跳变（块大小通常为几毫秒）；
+    /// 且它表示“已经交给设备”的位置，实际听到的声音还要晚一个输出延迟
+    /// （设备缓冲区加上系统混音，桌面上约 10~50 ms，蓝牙耳机可能超过 150 ms）。
+    /// 对齐判定时应减去一个可由玩家校准的偏移量。音频设备重建后从 0 重新开始
+    pub fn playback_samples(&self) -> u64 {
+        SAMPLE_CLOCK.load(Ordering::Acquire)
+    }
+
+    /// `playback_samples` 换算成秒，延迟注意事项相同
+    pub fn playback_seconds(&self) -> f64 {
+        self.playback_samples() as f64 / self.0.sample_rate() as f64
+    }
+
+    /// 输出设备的采样率，音频流建立之前为默认值 48000
+    pub fn get_sample_rate(&self) -> u32 {
+        self.0.sample_rate()
+    }
+
+    /// 开始把混音输出录制为 32 位浮点 WAV 文件，用于离线复现爆音、断音等问题。
+    /// 音频回调只把每块输出复制到预分配的缓冲区，由后台线程写入文件，不会增加延迟。
+    /// 录制期间音频设备重建导致采样率变化时，文件头仍使用开始录制时的采样率
+    #[cfg(feature = "capture")]
+    pub fn start_capture(&mut self, path: impl AsRef<std::path::Path>) -> anyhow::Result<()> {
+        crate::capture::start(path.as_ref(), self.0.sample_rate())
+    }
+
+    /// 停止录制并补全文件头，返回写入的采样数。写入线程跟不上导致丢失采样时返回错误，文件仍然保留
+    #[cfg(feature = "capture")]
+    pub fn stop_capture(&mut self) -> anyhow::Result<u64> {
+        crate::capture::stop()
+    }
+
+    /// 设置同时播放的最大声音数，范围 [1, MAX_VOICES_LIMIT]，达到上限后按 `set_voice_steal_policy` 处理新的声音
+    pub fn set_max_voices(&mut self, max_voices: usize) {
+        MAX_VOICES.store(max_voices.clamp(1, MAX_VOICES_LIMIT), Ordering::Relaxed);
+    }
+
+    pub fn get_max_voices(&self) -> usize {
+        MAX_VOICES.load(Ordering::Relaxed)
+    }
+
+    pub fn set_voice_steal_policy(&mut self, policy: VoiceStealPolicy) {
+        VOICE_STEAL_POLICY.store(policy as u8, Ordering::Relaxed);
+    }
+
+    pub fn get_voice_steal_policy(&self) -> VoiceStealPolicy {
+        VoiceStealPolicy::load()
     }
 }
\ No newline at end of file
@@ -0,0 +1,66 @@
+use std::sync::atomic::{fence, AtomicU64, AtomicUsize, Ordering};
+
+use crate::player::MAX_VOICES_LIMIT;
+
+// 下一个分配给 play 的声音 id，0 保留给 PlayingVoice::NONE
+static NEXT_VOICE_ID: AtomicU64 = AtomicU64::new(1);
+
+// 混音器在每个音频块结束时发布的快照，用顺序锁保证游戏线程读到一致的数据，音频回调从不等待
+// 版本号为奇数时表示正在写入
+static SNAPSHOT_VERSION: AtomicU64 = AtomicU64::new(0);
+// 音频回调已经取出的最大声音 id，更大的 id 仍在指令队列中
+static CONSUMED_VOICE: AtomicU64 = AtomicU64::new(0);
+static ACTIVE_VOICE_COUNT: AtomicUsize = AtomicUsize::new(0);
+static ACTIVE_VOICES: [AtomicU64; MAX_VOICES_LIMIT] = [const { AtomicU64::new(0) }; MAX_VOICES_LIMIT];
+
+pub(crate) fn next_voice_id() -> u64 {
+    NEXT_VOICE_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// 在音频回调中调用，发布当前的声音列表
+pub(crate) fn publish(consumed: u64, voices: impl ExactSizeIterator<Item = u64>) {
+    let version = SNAPSHOT_VERSION.load(Ordering::Relaxed);
+    SNAPSHOT_VERSION.store(version + 1, Ordering::Relaxed);
+    fence(Ordering::Release);
+
+    CONSUMED_VOICE.store(consumed, Ordering::Relaxed);
+    ACTIVE_VOICE_COUNT.store(voices.len(), Ordering::Relaxed);
+    for (slot, id) in ACTIVE_VOICES.iter().zip(voices) {
+        slot.store(id, Ordering::Relaxed);
+    }
+
+    SNAPSHOT_VERSION.store(version + 2, Ordering::Release);
+}
+
+/// 混音器创建或销毁时调用。此时指令队列已经重建，尚未取出的声音不会再播放
+pub(crate) fn reset() {
+    let consumed = NEXT_VOICE_ID.load(Ordering::Relaxed) - 1;
+    publish(consumed, std::iter::empty());
+}
+
+pub(crate) fn is_playing(id: u64) -> bool {
+    if id == 0 {
+        return false;
+    }
+
+    loop {
+        let version = SNAPSHOT_VERSION.load(Ordering::Acquire);
+        if version % 2 == 1 {
+            std::hint::spin_loop();
+            continue;
+        }
+
+        let count = ACTIVE_VOICE_COUNT.load(Ordering::Relaxed).min(MAX_VOICES_LIMIT);
+        let playing = id > CONSUMED_VOICE.load(Ordering::Relaxed)
+            || ACTIVE_VOICES[..count].iter().any(|slot| slot.load(Ordering::Relaxed) == id);
+
+        fence(Ordering::Acquire);
+        if SNAPSHOT_VERSION.load(Ordering::Relaxed) == version {
+            return playing;
+        }
+    }
+}
+
+pub(crate) fn active_voice_count() -> usize {
+    ACTIVE_VOICE_COUNT.load(Ordering::Relaxed)
+}
@@ -1,83 +1,323 @@
-use std::collections::HashMap;
+use std::fmt;
 
 pub trait IdMapKey: Sized {
     fn from(id: u64) -> Self;
     fn to(&self) -> u64;
 }
 
+/// `IdMap::try_get` 失败的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GetError {
+    /// 句柄不是由本 IdMap 分配的（包括默认的 0 句柄）
+    NeverExisted,
+    /// 句柄已经通过 `reserve` 预留，但还没有用 `insert_reserved` 填入值
+    Reserved,
+    /// 句柄对应的值已经被移除，槽位还没有被重新使用
+    Freed,
+    /// 句柄对应的值已经被移除，槽位已经分配给了新的值
+    WrongGeneration,
+}
+
+impl fmt::Display for GetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            GetError::NeverExisted => "handle was never allocated by this map",
+            GetError::Reserved => "handle is reserved but has no value yet",
+            GetError::Freed => "handle refers to a removed value",
+            GetError::WrongGeneration => "handle refers to a removed value whose slot has been reused",
+        };
+        f.write_str(message)
+    }
+}
+
+impl std::error::Error for GetError {}
+
+#[derive(Clone)]
+enum SlotState<V> {
+    Vacant,
+    Reserved,
+    Occupied(V),
+}
+
+#[derive(Clone)]
+struct Slot<V> {
+    // 当前（或最后一个）占用者的代数，从 1 开始，因此任何有效句柄都不为 0
+    generation: u32,
+    state: SlotState<V>,
+}
+
+// 句柄的低 32 位为槽位序号，高 32 位为代数
+fn pack(index: u32, generation: u32) -> u64 {
+    ((generation as u64) << 32) | index as u64
+}
+
+fn unpack(id: u64) -> (u32, u32) {
+    (id as u32, (id >> 32) as u32)
+}
+
+/// 使用代数句柄的槽位表。
+///
+/// 移除值后槽位会被重新使用，但代数加一，旧句柄不会误取到新的值，`try_get` 可以区分句柄失效的原因。
+/// 代数用尽（u32::MAX）的槽位不再回收
 #[derive(Clone)]
 pub struct IdMap<V, H: IdMapKey> {
-    data: HashMap<u64, V>,
-    next_id: u64,
+    slots: Vec<Slot<V>>,
+    // 可以重新使用的空槽位
+    free: Vec<u32>,
+    len: usize,
     _phantom: std::marker::PhantomData<H>,
 }
 
 impl<V, H: IdMapKey> IdMap<V, H> {
     pub fn new() -> Self {
         IdMap {
-            data: HashMap::new(),
-            next_id: 1, // 从 1 开始，0 往往可以作为无效句柄的保留值
+            slots: Vec::new(),
+            free: Vec::new(),
+            len: 0,
             _phantom: std::marker::PhantomData,
         }
     }
 
-    /// 插入新值，生成一个全局唯一的句柄
-    pub fn insert(&mut self, value: V) -> H {
-        let current_id = self.next_id;
+    // 取一个空槽位并把代数加一（新槽位的代数为 1），状态设置为 `state`
+    fn allocate(&mut self, state: SlotState<V>) -> u64 {
+        if let Some(index) = self.free.pop() {
+            let slot = &mut self.slots[index as usize];
+            slot.generation += 1;
+            slot.state = state;
+            return pack(index, slot.generation);
+        }
+
+        // 检查槽位序号溢出情况
+        let Ok(index) = u32::try_from(self.slots.len()) else {
+            panic!("IdMap 槽位已耗尽！无法生成更多唯一的 ID。");
+        };
+        self.slots.push(Slot { generation: 1, state });
+        pack(index, 1)
+    }
 
-        // 检查 ID 溢出情况
-        if current_id == u64::MAX {
-            panic!("IdMap ID 空间已耗尽！无法生成更多唯一的 ID。");
+    // 查找句柄对应的槽位，代数不匹配时返回对应的错误
+    fn slot(&self, handle: &H) -> Result<&Slot<V>, GetError> {
+        let (index, generation) = unpack(handle.to());
+        let slot = self.slots.get(index as usize).ok_or(GetError::NeverExisted)?;
+        match generation {
+            0 => Err(GetError::NeverExisted),
+            g if g > slot.generation => Err(GetError::NeverExisted),
+            g if g < slot.generation => Err(GetError::WrongGeneration),
+            _ => Ok(slot),
         }
+    }
 
-        // 核心逻辑：直接自增，不检查 free_ids，不回收任何 ID
-        self.next_id += 1;
+    fn slot_mut(&mut self, handle: &H) -> Option<&mut Slot<V>> {
+        let (index, generation) = unpack(handle.to());
+        self.slots
+            .get_mut(index as usize)
+            .filter(|slot| generation != 0 && slot.generation == generation)
+    }
 
-        self.data.insert(current_id, value);
-        H::from(current_id)
+    /// 插入新值，生成一个句柄
+    pub fn insert(&mut self, value: V) -> H {
+        self.len += 1;
+        H::from(self.allocate(SlotState::Occupied(value)))
     }
 
-    /// 移除值，其对应的句柄将永远变为失效状态
+    /// 预留一个句柄但暂不插入值，用于异步加载：先把句柄交给调用者，加载完成后再用 `insert_reserved` 填入。
+    /// 填入之前 `get` 返回 None，`try_get` 返回 `GetError::Reserved`
+    pub fn reserve(&mut self) -> H {
+        H::from(self.allocate(SlotState::Reserved))
+    }
+
+    /// 为 `reserve` 预留的句柄填入值。句柄不是由本 IdMap 预留的、已经有值或已经被 `remove` 时返回 false
+    pub fn insert_reserved(&mut self, handle: H, value: V) -> bool {
+        let Some(slot) = self.slot_mut(&handle) else {
+            return false;
+        };
+        if !matches!(slot.state, SlotState::Reserved) {
+            return false;
+        }
+        slot.state = SlotState::Occupied(value);
+        self.len += 1;
+        true
+    }
+
+    /// 移除值并回收槽位，之后该句柄的 `try_get` 返回 `Freed` 或 `WrongGeneration`。
+    /// 也可以用于取消一个还没有填入值的预留句柄
     pub fn remove(&mut self, handle: H) -> Option<V> {
-        let id_value = handle.to();
-        // 直接从 map 中移除，不再将 id 放入 free_ids
-        self.data.remove(&id_value)
+        let (index, _) = unpack(handle.to());
+        let slot = self.slot_mut(&handle)?;
+        let recyclable = slot.generation < u32::MAX;
+        let value = match std::mem::replace(&mut slot.state, SlotState::Vacant) {
+            // 已经移除过，槽位已经在空闲列表中
+            SlotState::Vacant => return None,
+            SlotState::Reserved => None,
+            SlotState::Occupied(value) => {
+                self.len -= 1;
+                Some(value)
+            }
+        };
+        if recyclable {
+            self.free.push(index);
+        }
+        value
     }
 
     pub fn get(&self, handle: H) -> Option<&V> {
-        self.data.get(&handle.to())
+        self.try_get(handle).ok()
+    }
+
+    /// 与 `get` 相同，但在句柄无效时返回原因，便于发现使用已移除资源的句柄
+    pub fn try_get(&self, handle: H) -> Result<&V, GetError> {
+        match &self.slot(&handle)?.state {
+            SlotState::Occupied(value) => Ok(value),
+            SlotState::Reserved => Err(GetError::Reserved),
+            SlotState::Vacant => Err(GetError::Freed),
+        }
     }
 
     pub fn get_mut(&mut self, handle: H) -> Option<&mut V> {
-        self.data.get_mut(&handle.to())
+        match &mut self.slot_mut(&handle)?.state {
+            SlotState::Occupied(value) => Some(value),
+            _ => None,
+        }
     }
 
     pub fn keys(&self) -> impl Iterator<Item = H> + '_ {
-        self.data.keys().map(|&id| H::from(id))
+        self.iter().map(|(handle, _)| handle)
     }
 
     pub fn values(&self) -> impl Iterator<Item = &V> {
-        self.data.values()
+        self.iter().map(|(_, v)| v)
     }
 
+    /// 按槽位顺序遍历所有有值的句柄
     pub fn iter(&self) -> impl Iterator<Item = (H, &V)> {
-        self.data.iter().map(|(&id, v)| (H::from(id), v))
+        self.slots.iter().enumerate().filter_map(|(index, slot)| match &slot.state {
+            SlotState::Occupied(v) => Some((H::from(pack(index as u32, slot.generation)), v)),
+            _ => None,
+        })
     }
 
     pub fn iter_mut(&mut self) -> impl Iterator<Item = (H, &mut V)> {
-        self.data.iter_mut().map(|(&id, v)| (H::from(id), v))
+        self.slots.iter_mut().enumerate().filter_map(|(index, slot)| match &mut slot.state {
+            SlotState::Occupied(v) => Some((H::from(pack(index as u32, slot.generation)), v)),
+            _ => None,
+        })
     }
 
     pub fn len(&self) -> usize {
-        self.data.len()
+        self.len
     }
 
     pub fn is_empty(&self) -> bool {
-        self.data.is_empty()
+        self.len == 0
     }
 
     /// 获取下一个即将分配的 ID（用于调试或统计）
     pub fn peek_next_id(&self) -> u64 {
-        self.next_id
+        match self.free.last() {
+            Some(&index) => pack(index, self.slots[index as usize].generation + 1),
+            None => pack(self.slots.len() as u32, 1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    struct Handle(u64);
+
+    impl IdMapKey for Handle {
+        fn from(id: u64) -> Self {
+            Handle(id)
+        }
+        fn to(&self) -> u64 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn stale_handle_does_not_see_reused_slot() {
+        let mut map = IdMap::<&str, Handle>::new();
+        let old = map.insert("old");
+        assert_eq!(map.remove(old), Some("old"));
+
+        // 槽位被重新使用，序号相同但代数不同
+        let new = map.insert("new");
+        assert_eq!(unpack(old.0).0, unpack(new.0).0);
+        assert_ne!(old, new);
+
+        assert_eq!(map.try_get(old), Err(GetError::WrongGeneration));
+        assert_eq!(map.get(old), None);
+        assert_eq!(map.get_mut(old), None);
+        assert_eq!(map.remove(old), None);
+        assert_eq!(map.get(new), Some(&"new"));
+        assert_eq!(map.len(), 1);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn try_get_reports_each_error() {
+        let mut map = IdMap::<u32, Handle>::new();
+        let value = map.insert(1);
+        assert_eq!(map.try_get(value), Ok(&1));
+
+        // 默认的 0 句柄、越界的序号、未来的代数
+        assert_eq!(map.try_get(Handle::default()), Err(GetError::NeverExisted));
+        assert_eq!(map.try_get(Handle(pack(5, 1))), Err(GetError::NeverExisted));
+        assert_eq!(map.try_get(Handle(pack(0, 2))), Err(GetError::NeverExisted));
+
+        let reserved = map.reserve();
+        assert_eq!(map.try_get(reserved), Err(GetError::Reserved));
+        assert!(map.insert_reserved(reserved, 2));
+        assert_eq!(map.try_get(reserved), Ok(&2));
+        assert!(!map.insert_reserved(reserved, 3));
+
+        assert_eq!(map.remove(value), Some(1));
+        assert_eq!(map.try_get(value), Err(GetError::Freed));
+
+        map.insert(4);
+        assert_eq!(map.try_get(value), Err(GetError::WrongGeneration));
+    }
+
+    #[test]
+    fn removed_reservation_cannot_be_filled() {
+        let mut map = IdMap::<u32, Handle>::new();
+        let reserved = map.reserve();
+        assert_eq!(map.remove(reserved), None);
+        assert_eq!(map.try_get(reserved), Err(GetError::Freed));
+        assert!(!map.insert_reserved(reserved, 1));
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn exhausted_generation_is_not_recycled() {
+        let mut map = IdMap::<u32, Handle>::new();
+        let first = map.insert(1);
+        map.remove(first);
+        // 直接把槽位推到代数上限前一代，下一次分配得到最后一代
+        map.slots[0].generation = u32::MAX - 1;
+        let last = map.insert(2);
+        assert_eq!(unpack(last.0), (0, u32::MAX));
+
+        // 最后一代移除后槽位不再进入空闲列表，新值使用新的槽位
+        assert_eq!(map.remove(last), Some(2));
+        assert_eq!(map.try_get(last), Err(GetError::Freed));
+        assert_eq!(map.peek_next_id(), pack(1, 1));
+        let next = map.insert(3);
+        assert_eq!(unpack(next.0), (1, 1));
+        assert_eq!(map.try_get(last), Err(GetError::Freed));
+    }
+
+    #[test]
+    fn iteration_skips_empty_slots() {
+        let mut map = IdMap::<u32, Handle>::new();
+        let a = map.insert(1);
+        let b = map.insert(2);
+        map.reserve();
+        let c = map.insert(3);
+        map.remove(b);
+        assert_eq!(map.keys().collect::<Vec<_>>(), [a, c]);
+        assert_eq!(map.values().copied().collect::<Vec<_>>(), [1, 3]);
+        assert_eq!(map.len(), 2);
+    }
+}
@@ -1,6 +1,5 @@
-use crossbeam_queue::ArrayQueue;
 use log::*;
-use std::{mem::ManuallyDrop, sync::{Arc, mpsc::{self, Sender, channel}}, time::Duration};
+use std::{collections::HashMap, mem::ManuallyDrop, sync::mpsc::{self, Receiver, Sender, channel}, time::{Duration, Instant}};
 use tokio::{
     runtime::Runtime,
     task::JoinHandle,
@@ -10,33 +9,63 @@ use unm_sfx::player::SfxManager;
 use winit::{
     application::ApplicationHandler,
     dpi::PhysicalSize,
-    event::WindowEvent,
+    event::{DeviceEvent, DeviceId, Touch, WindowEvent},
     event_loop::{ActiveEventLoop, ControlFlow, EventLoop, EventLoopProxy},
-    window::{Fullscreen, Icon, Window, WindowAttributes, WindowId},
+    window::{CursorGrabMode, CustomCursorSource, Icon, Window, WindowAttributes, WindowId, WindowLevel},
 };
 
 use crate::{
+    assets::Assets,
     game_loop::GameLoop,
-    game_settings::GameSettings,
-    get_context, get_quad_context,
-    input::{InputEvent, MouseButtonState, MouseInput, TouchInput},
+    plugin::Plugin,
+    game_settings::{decode_window_icon, FrameRate, GameSettings, Orientation},
+    msaa::Msaa,
+    input::{InputEvent, KeyInput, MouseButtonState, MouseInput, TouchInput},
+    monitor::{collect_monitors, resolve_fullscreen, FullscreenMode, MonitorInfo},
     resolution::Resolution,
     tools::*,
-    WgpuState, CONTEXT,
+    WgpuState,
 };
 
+/// 退出时等待渲染任务结束的最长时间，`GameLoop::on_exit` 需要在这段时间内完成
+const RENDER_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+/// 进入后台时等待渲染线程释放 Surface 的最长时间
+const SURFACE_RELEASE_TIMEOUT: Duration = Duration::from_secs(2);
+
 /// 渲染线程可以发送给主线程的命令，用于控制窗口行为。
 #[derive(Debug)]
 pub enum WindowCommand {
-    /// 设置窗口全屏模式。`None` 表示退出全屏，`Some(Fullscreen)` 表示设置特定全屏模式。
-    SetFullscreen(bool),
+    /// 设置窗口全屏模式，可以指定显示器和独占全屏的显示模式。
+    SetFullscreen(FullscreenMode),
+    /// 在 winit 线程上枚举显示器，通过附带的发送端返回结果。
+    GetMonitors(Sender<Vec<MonitorInfo>>),
     /// 设置窗口图标。
     SetWindowIcon(Icon),
     /// 设置窗口标题。
     SetTitle(String),
     /// 请求重新设置窗口分辨率。这会触发 `WindowEvent::Resized`。
     SetResolution(Resolution),
-    // 还可以添加更多命令，例如 SetCursorIcon, SetDecorations 等。
+    /// 显示/隐藏光标。
+    SetCursorVisible(bool),
+    /// 锁定光标（优先锁定在原地，不支持时限制在窗口内），锁定期间转发原始鼠标位移。
+    SetCursorGrab(bool),
+    /// 使用自定义光标图片。
+    SetCursor(CustomCursorSource),
+    /// 显示/隐藏软键盘，桌面平台上对应开启/关闭输入法。
+    ShowSoftKeyboard(bool),
+    /// 锁定屏幕方向，只在 Android 上生效。
+    SetOrientation(Orientation),
+    /// 保持屏幕常亮，只在 Android 上生效。
+    SetKeepScreenOn(bool),
+    /// 是否允许用户调整窗口大小。
+    SetResizable(bool),
+    /// 是否显示标题栏和边框。
+    SetDecorations(bool),
+    /// 设置窗口的最小内部尺寸，`None` 表示不限制。
+    SetMinInnerSize(Option<Resolution>),
+    /// 设置窗口的最大内部尺寸，`None` 表示不限制。
+    SetMaxInnerSize(Option<Resolution>),
+    // 还可以添加更多命令，例如 SetDecorations 等。
     Quit,
 }
 
@@ -47,9 +76,199 @@ enum WgpuStateCommand {
     /// 关闭渲染线程。
     Close,
 
+    /// 应用回到前台，重新创建 Surface。
     Resume,
+    /// 应用进入后台，销毁 Surface 后通过附带的发送端通知主线程。
+    Suspended(Sender<()>),
+
+    /// 窗口获得/失去焦点。
+    Focused(bool),
+    /// 窗口被完全遮挡/取消遮挡。
+    Occluded(bool),
+    /// 窗口的 DPI 缩放系数改变。
+    ScaleFactorChanged(f64),
+    /// 窗口所在显示器的刷新率改变，例如窗口被拖到另一块显示器上。
+    RefreshRateChanged(f64),
+    /// 用户请求关闭窗口，由游戏决定是否退出。
+    CloseRequested,
+}
+
+/// 渲染线程所使用的 Tokio 运行时配置。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeConfig {
+    /// 多线程运行时，`worker_threads` 为工作线程数（至少为 1），多出的线程可用于异步加载资源。
+    MultiThread { worker_threads: usize },
+    /// 单线程运行时，运行在一个独立的渲染线程上，适合 wasm 或核心数较少的设备。
+    CurrentThread,
+}
 
-    Suspended,
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self::MultiThread { worker_threads: 2 }
+    }
+}
+
+/// 创建窗口时使用的配置，由 [`App::new`] 应用到 `WindowAttributes`。
+#[derive(Debug, Clone)]
+pub struct AppConfig {
+    title: String,
+    size: Option<Resolution>,
+    resizable: bool,
+    decorations: bool,
+    transparent: bool,
+    always_on_top: bool,
+    min_size: Option<Resolution>,
+    max_size: Option<Resolution>,
+    icon: Option<Icon>,
+    // 其他字段之外的窗口属性（平台相关属性等），为 None 时从默认值开始
+    base_attributes: Option<WindowAttributes>,
+    // 应用到初始 GameSettings，None 时使用 GameSettings 的默认值
+    msaa: Option<Msaa>,
+    target_fps: Option<FrameRate>,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            title: "unm".to_owned(),
+            size: None,
+            resizable: true,
+            decorations: true,
+            transparent: false,
+            always_on_top: false,
+            min_size: None,
+            max_size: None,
+            icon: None,
+            base_attributes: None,
+            msaa: None,
+            target_fps: None,
+        }
+    }
+}
+
+impl AppConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    /// 初始窗口大小，不设置时由平台决定
+    pub fn with_size(mut self, size: Resolution) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    pub fn with_resizable(mut self, resizable: bool) -> Self {
+        self.resizable = resizable;
+        self
+    }
+
+    /// 是否显示标题栏和边框
+    pub fn with_decorations(mut self, decorations: bool) -> Self {
+        self.decorations = decorations;
+        self
+    }
+
+    /// 窗口背景是否透明，需要 Surface 支持带 alpha 的合成模式才有效果
+    pub fn with_transparent(mut self, transparent: bool) -> Self {
+        self.transparent = transparent;
+        self
+    }
+
+    pub fn with_always_on_top(mut self, always_on_top: bool) -> Self {
+        self.always_on_top = always_on_top;
+        self
+    }
+
+    pub fn with_min_size(mut self, min_size: Resolution) -> Self {
+        self.min_size = Some(min_size);
+        self
+    }
+
+    pub fn with_max_size(mut self, max_size: Resolution) -> Self {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    /// 窗口图标，PNG/JPEG 图片数据，见 `GameSettings::set_window_icon_from_bytes`。
+    /// 解码失败或尺寸不符合要求时打印错误并使用默认图标
+    pub fn with_icon(mut self, bytes: &[u8]) -> Self {
+        self.icon = decode_window_icon(bytes);
+        self
+    }
+
+    /// 以 `attributes` 为基础创建窗口，用于设置 AppConfig 没有提供的属性（例如平台相关的属性）。
+    /// 标题、大小、图标等仍以 AppConfig 中的设置为准，覆盖 `attributes` 中的同名属性
+    pub fn with_window_attributes(mut self, attributes: WindowAttributes) -> Self {
+        self.base_attributes = Some(attributes);
+        self
+    }
+
+    /// 初始 MSAA，在第一帧之前生效，避免启动后再重建渲染目标
+    pub fn with_msaa(mut self, msaa: Msaa) -> Self {
+        self.msaa = Some(msaa);
+        self
+    }
+
+    /// 初始目标帧率，含义同 `GameSettings::set_target_fps`
+    pub fn with_target_fps(mut self, target_fps: FrameRate) -> Self {
+        self.target_fps = Some(target_fps);
+        self
+    }
+
+    fn window_attributes(&self) -> WindowAttributes {
+        let mut attributes = self
+            .base_attributes
+            .clone()
+            .unwrap_or_default()
+            .with_title(self.title.clone())
+            .with_resizable(self.resizable)
+            .with_decorations(self.decorations)
+            .with_transparent(self.transparent)
+            .with_window_icon(self.icon.clone());
+
+        if self.always_on_top {
+            attributes = attributes.with_window_level(WindowLevel::AlwaysOnTop);
+        }
+        if let Some(mut size) = self.size {
+            attributes = attributes.with_inner_size(size.ensure_non_zero());
+        }
+        if let Some(mut min_size) = self.min_size {
+            attributes = attributes.with_min_inner_size(min_size.ensure_non_zero());
+        }
+        if let Some(mut max_size) = self.max_size {
+            attributes = attributes.with_max_inner_size(max_size.ensure_non_zero());
+        }
+        attributes
+    }
+
+    // 在 GameLoop::start 之前应用到 GameSettings
+    fn apply_to_settings(&self, game_settings: &mut GameSettings) {
+        if let Some(msaa) = self.msaa {
+            game_settings.set_msaa(msaa);
+        }
+        if let Some(target_fps) = self.target_fps {
+            game_settings.set_target_fps(target_fps);
+        }
+    }
+
+    // 把请求的尺寸限制在最小/最大尺寸之间
+    fn clamp_size(&self, size: PhysicalSize<u32>, scale_factor: f64) -> PhysicalSize<u32> {
+        let mut size = size;
+        if let Some(min) = self.min_size.map(|s| s.to_physical(scale_factor)) {
+            size.width = size.width.max(min.width);
+            size.height = size.height.max(min.height);
+        }
+        if let Some(max) = self.max_size.map(|s| s.to_physical(scale_factor)) {
+            size.width = size.width.min(max.width);
+            size.height = size.height.min(max.height);
+        }
+        size
+    }
 }
 
 /// 应用程序的主结构，管理 winit 窗口、WGPU 状态和渲染线程。
@@ -69,6 +288,11 @@ pub struct App {
 
     /// 应用程序的 Tokio 运行时。
     runtime: Option<Runtime>,
+    runtime_config: RuntimeConfig,
+    /// 单线程运行时模式下驱动运行时的系统线程。
+    render_os_thread: Option<std::thread::JoinHandle<()>>,
+    /// 单线程运行时模式下，渲染线程结束时发送一次，用于带超时地等待它退出。
+    render_os_thread_exited: Option<Receiver<()>>,
 
     event_loop: Option<EventLoop<WindowCommand>>,
 
@@ -76,12 +300,39 @@ pub struct App {
 
     /// 游戏的实例
     game: Option<Box<dyn GameLoop>>,
+    /// 按注册顺序调用的插件，渲染线程启动时移交给渲染循环。
+    plugins: Vec<Box<dyn Plugin>>,
+
+    /// 输入事件队列（无界），接收端在渲染线程启动时移交给渲染循环。
+    input_event_sender: Sender<InputEvent>,
+    input_event_receiver: Option<Receiver<InputEvent>>,
+    /// 尚未发送的触控移动事件，同一触控点只保留最新的一次，在事件批次结束时发送。
+    pending_touch_moves: HashMap<u64, Touch>,
+    /// 尚未发送的原始鼠标位移累计值。
+    pending_mouse_motion: Option<(f64, f64)>,
+
+    /// 光标是否已被锁定，锁定时才转发 `DeviceEvent::MouseMotion`。
+    cursor_grabbed: bool,
+
+    /// 窗口配置，运行时修改的最小/最大尺寸也会记录在这里。
+    config: AppConfig,
+    /// 最近一次以逻辑像素请求的窗口大小，缩放系数改变时按新的系数重新换算。
+    logical_size: Option<Resolution>,
+    /// 最近一次发送给渲染线程的刷新率，只在变化时发送。
+    refresh_rate: Option<f64>,
+}
 
-    input_event_sender: Arc<ArrayQueue<InputEvent>>,
+/// 检测窗口所在显示器的刷新率，与上一次发送的值不同时通知渲染线程。
+fn notify_refresh_rate(last: &mut Option<f64>, window: &Window, sender: &Sender<WgpuStateCommand>) {
+    let refresh_rate = query_refresh_rate(window);
+    if *last != Some(refresh_rate) {
+        *last = Some(refresh_rate);
+        let _ = sender.send(WgpuStateCommand::RefreshRateChanged(refresh_rate));
+    }
 }
 
 impl App {
-    pub fn new(game: impl GameLoop + 'static) -> Self {
+    pub fn new(game: impl GameLoop + 'static, config: AppConfig) -> Self {
         let mut event_loop_builder = EventLoop::<WindowCommand>::with_user_event();
         platform_specific::configure_event_loop_builder(&mut event_loop_builder);
 
@@ -92,6 +343,8 @@ impl App {
         let event_loop_proxy: EventLoopProxy<WindowCommand> = event_loop.create_proxy();
         event_loop.set_control_flow(ControlFlow::Poll);
 
+        let (input_event_sender, input_event_receiver) = channel();
+
         Self {
             window_ref: None,
             window_box: None,
@@ -99,13 +352,24 @@ impl App {
             event_proxy: event_loop_proxy,
             render_thread_handle: None,
             runtime: None,
+            runtime_config: RuntimeConfig::default(),
+            render_os_thread: None,
+            render_os_thread_exited: None,
 
             event_loop: Some(event_loop),
             max_level: LevelFilter::Info,
 
             game: Some(Box::new(game)),
-
-            input_event_sender: Arc::new(ArrayQueue::new(128)), // 初始化队列，大小可调整
+            plugins: Vec::new(),
+
+            input_event_sender,
+            input_event_receiver: Some(input_event_receiver),
+            pending_touch_moves: HashMap::new(),
+            pending_mouse_motion: None,
+            cursor_grabbed: false,
+            logical_size: config.size.filter(|size| matches!(size, Resolution::Logical(..))),
+            refresh_rate: None,
+            config,
         }
     }
 
@@ -114,6 +378,24 @@ impl App {
         self
     }
 
+    pub fn set_runtime(mut self, runtime_config: RuntimeConfig) -> Self {
+        self.runtime_config = runtime_config;
+        self
+    }
+
+    /// 注册插件，插件按注册顺序在游戏的 `update` 前后调用，见 `Plugin`
+    pub fn add_plugin(mut self, plugin: Box<dyn Plugin>) -> Self {
+        self.plugins.push(plugin);
+        self
+    }
+
+    // 将输入事件发送给渲染线程，队列无界，只有渲染线程已经退出时才会失败
+    fn send_input_event(&self, event: InputEvent) {
+        if let Err(e) = self.input_event_sender.send(event) {
+            warn!("Failed to send input event to render thread: {:?}", e.0);
+        }
+    }
+
     pub fn run(&mut self) {
         platform_specific::init_logger(self.max_level);
         if let Some(event_loop) = self.event_loop.take() {
@@ -131,7 +413,7 @@ impl App {
 
         info!("Initializing window and WGPU state...");
 
-        match event_loop.create_window(WindowAttributes::default()) {
+        match event_loop.create_window(self.config.window_attributes()) {
             Ok(window) => match self.setup_window_and_render_thread(window) {
                 Err(e) => {
                     error!("Failed to create render: {:?}", e);
@@ -158,147 +440,328 @@ impl App {
             Box::from_raw(window_ref as *const _ as *mut _)
         }));
 
-        let wgpu_state_initial = pollster::block_on(WgpuState::new(window_ref))?;
-        unsafe { CONTEXT = Some(wgpu_state_initial) };
+        let mut wgpu_state = pollster::block_on(WgpuState::new(window_ref))?;
 
         // 创建渲染命令频道
         let (render_command_sender, render_command_receiver) = channel();
 
         self.render_command_sender = Some(render_command_sender);
 
-        let mouse_event_queue = Arc::clone(&self.input_event_sender);
-
-        // 初始化 Tokio 运行时（如果尚未初始化）
-        self.runtime = Some(
-            tokio::runtime::Builder::new_multi_thread()
-                .worker_threads(2) // 根据需要调整线程数
-                .enable_all()
-                .build()
-                .expect("Failed to create Tokio runtime"),
-        );
-        let runtime_handle = self.runtime.as_ref().unwrap().handle().clone();
+        let input_event_receiver = self
+            .input_event_receiver
+            .take()
+            .expect("Input event receiver should be present when starting render thread");
 
         let event_proxy = self.event_proxy.clone();
         let game = self
             .game
             .take()
             .expect("Game loop instance should be present when starting render thread"); // 获取 game 实例
+        let plugins = std::mem::take(&mut self.plugins);
+        let config = self.config.clone();
 
-        // 在 Tokio 运行时上启动渲染任务
-        let render_thread_handle = runtime_handle.spawn(async move {
+        let render_future = async move {
+            // WgpuState 归渲染循环所有，循环结束时随之释放
             Self::render_loop(
                 render_command_receiver,
                 event_proxy.clone(),
-                mouse_event_queue, // 传递鼠标事件队列
+                input_event_receiver, // 传递输入事件队列
                 window_ref,        // 传递 &'static Window
+                &config,
+                &mut wgpu_state,
                 game,              // 传递游戏实例
+                plugins,
             )
             .await;
-        });
-        self.render_thread_handle = Some(render_thread_handle);
+        };
+
+        match self.runtime_config {
+            RuntimeConfig::MultiThread { worker_threads } => {
+                // 初始化 Tokio 运行时（如果尚未初始化）
+                self.runtime = Some(
+                    tokio::runtime::Builder::new_multi_thread()
+                        .worker_threads(worker_threads.max(1)) // 根据需要调整线程数
+                        .enable_all()
+                        .build()
+                        .expect("Failed to create Tokio runtime"),
+                );
+                let runtime_handle = self.runtime.as_ref().unwrap().handle().clone();
+
+                // 在 Tokio 运行时上启动渲染任务
+                self.render_thread_handle = Some(runtime_handle.spawn(render_future));
+            }
+            RuntimeConfig::CurrentThread => {
+                let runtime = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .expect("Failed to create Tokio runtime");
+
+                // 单线程运行时只有在 block_on 时才会推进任务，因此由独立的渲染线程驱动，
+                // 渲染循环结束后运行时随线程一起销毁
+                let (exited_sender, exited_receiver) = channel();
+                let render_os_thread = std::thread::Builder::new()
+                    .name("unm-render".to_owned())
+                    .spawn(move || {
+                        runtime.block_on(render_future);
+                        drop(runtime);
+                        let _ = exited_sender.send(());
+                    })?;
+                self.render_os_thread = Some(render_os_thread);
+                self.render_os_thread_exited = Some(exited_receiver);
+            }
+        }
         Ok(())
     }
 
     /// 渲染线程的主循环逻辑。
+    #[allow(clippy::too_many_arguments)]
     async fn render_loop(
         wgpu_state_receiver: mpsc::Receiver<WgpuStateCommand>,
         event_proxy: EventLoopProxy<WindowCommand>,
-        input_event_receiver: Arc<ArrayQueue<InputEvent>>, // 接收鼠标事件队列
+        input_event_receiver: Receiver<InputEvent>, // 接收输入事件队列
         window_ref: &'static Window,
+        config: &AppConfig,
+        wgpu_state: &mut WgpuState,
         mut game: Box<dyn GameLoop>,
+        mut plugins: Vec<Box<dyn Plugin>>,
     ) {
         let mut sfx_manager = SfxManager::new();
+        let mut assets = Assets::new();
         let mut mouse_input = MouseInput::new();
         let mut touch_input = TouchInput::new();
+        let mut key_input = KeyInput::new();
 
-        let wgpu_state = get_quad_context();
         wgpu_state.create_default_resources().await;
 
         let mut game_settings = GameSettings::new(event_proxy);
-        game.start(&mut game_settings, &mut sfx_manager).await;
+        game_settings.scale_factor = window_ref.scale_factor();
+        game_settings.refresh_rate = query_refresh_rate(window_ref);
+        game_settings.supported_msaa = wgpu_state.supported_sample_counts();
+        config.apply_to_settings(&mut game_settings);
+        wgpu_state.apply_batch_limits(&game_settings);
+        if let Err(e) = game.start(&mut game_settings, wgpu_state, &mut sfx_manager, &mut assets).await {
+            error!("Game failed to start: {:#}", e);
+            game_settings.quit();
+            return;
+        }
 
         wgpu_state.end_frame(&mut game_settings);
 
         // 移动端优化：当应用到后台时降低主循环更新频率
         let sleep_rate_limit: Duration = Duration::from_secs(1);
-        let mut time_manager = TimeManager::new();
+        // 窗口失去焦点时按 background_fps 降帧，记录上一个后台帧的开始时间
+        let mut last_background_frame = Instant::now();
+        // 上一次检测刷新率的时间，窗口没有移动时每秒重新检测一次
+        let mut refresh_rate_queried = Instant::now();
+        let mut time_manager =
+            TimeManager::with_frame_history_len(game_settings.get_frame_history_len());
         let mut first_loop = true;
+        // 应用在后台，Surface 已销毁
+        let mut suspended = false;
+        // 尚未应用到 Surface 的窗口大小。暂停渲染（最小化、被遮挡）期间收到的 Resize 保留到恢复渲染时再处理
+        let mut pending_size: Option<PhysicalSize<u32>> = None;
 
         loop {
             let mut game_ready = false;
-            let mut new_size: Option<PhysicalSize<u32>> = None;
+            let mut focus_lost = false;
             while let Ok(command) = wgpu_state_receiver.try_recv() {
                 match command {
                     WgpuStateCommand::Resize(size) => {
-                        new_size = Some(size);
+                        pending_size = Some(size);
                         game_settings.current_window_size = size;
                     }
                     WgpuStateCommand::Close => {
                         info!("Render thread received close command. Exiting render loop.");
+                        game.on_exit().await;
                         return;
                     }
                     WgpuStateCommand::Resume => {
-                        let size = get_context().resume(window_ref);
-                        game_settings.current_window_size = size;
-                        game_ready = true;
+                        match wgpu_state.resume(window_ref) {
+                            Some(size) => {
+                                // resume 已经按当前窗口大小重新配置了 Surface
+                                game_settings.current_window_size = size;
+                                pending_size = None;
+                                suspended = false;
+                                game_ready = true;
+                                game.on_resume();
+                            }
+                            None => error!("Failed to resume rendering."),
+                        }
                     }
-                    WgpuStateCommand::Suspended => {
+                    WgpuStateCommand::Suspended(released) => {
+                        // Surface 必须在 suspended() 返回之前释放，之后原生窗口会被销毁
+                        wgpu_state.suspend();
+                        let _ = released.send(());
                         game_settings.current_window_size = PhysicalSize::new(1, 1);
+                        suspended = true;
+                        game.on_suspend();
                     },
+                    WgpuStateCommand::Focused(focused) => {
+                        game_settings.is_focused = focused;
+                        focus_lost |= !focused;
+                        game.on_focus_changed(focused);
+                    }
+                    WgpuStateCommand::Occluded(occluded) => {
+                        game_settings.is_occluded = occluded;
+                    }
+                    WgpuStateCommand::ScaleFactorChanged(scale_factor) => {
+                        game_settings.scale_factor = scale_factor;
+                        game.on_scale_factor_changed(scale_factor);
+                    }
+                    WgpuStateCommand::RefreshRateChanged(refresh_rate) => {
+                        game_settings.refresh_rate = refresh_rate;
+                        refresh_rate_queried = Instant::now();
+                    }
+                    WgpuStateCommand::CloseRequested => {
+                        if game.on_quit_requested() {
+                            info!("Close request accepted by game. Exiting render loop.");
+                            // 先让游戏保存数据，再通知主线程退出事件循环
+                            game.on_exit().await;
+                            game_settings.quit();
+                            return;
+                        }
+                        info!("Close request cancelled by game.");
+                    }
                 }
             }
 
+            let input_scope = ProfileScope::new("input");
             mouse_input.begin_frame();
             touch_input.begin_frame();
+            key_input.begin_frame();
 
-            // 处理鼠标事件队列
-            while let Some(event) = input_event_receiver.pop() {
+            // 处理输入事件队列
+            while let Ok(event) = input_event_receiver.try_recv() {
                 match event {
                     InputEvent::MouseButton { button, state } => {
                         mouse_input.update_button_state(button, state);
                     }
+                    InputEvent::MouseMotion { dx, dy } => {
+                        mouse_input.add_motion(dx, dy);
+                    }
                     InputEvent::Touch(touch) => {
                         touch_input.update_touch_event(&touch);
                     }
+                    InputEvent::Text(text) => {
+                        key_input.push_text(&text);
+                    }
+                    InputEvent::Ime(ime) => {
+                        key_input.update_ime(ime);
+                    }
                 }
             }
 
+            // 失去焦点后窗口收不到抬起事件，释放所有按下的按钮和触控，避免按键卡住
+            if focus_lost {
+                mouse_input.release_all();
+                touch_input.cancel_all();
+            }
+            drop(input_scope);
+
             let current_window_size = game_settings.get_window_size();
 
-            // 如果处于后台运行模式且窗口过小，则暂停渲染
-            if !game_settings.get_background_run_mode()
-                && (current_window_size.width <= 1 || current_window_size.height <= 1)
+            // 没有 Surface 时无法渲染；未处于后台运行模式且窗口过小或被完全遮挡时也暂停渲染
+            if suspended
+                || (!game_settings.get_background_run_mode()
+                    && (current_window_size.width <= 1
+                        || current_window_size.height <= 1
+                        || game_settings.get_is_occluded()))
             {
                 sleep(sleep_rate_limit).await;
                 tokio::task::yield_now().await;
                 continue;
             }
 
-            if let Some(new_size) = new_size {
+            if let Some(new_size) = pending_size.take() {
                 wgpu_state.resize(new_size);
                 window_ref.request_redraw();
+                game.on_resize(new_size);
             }
 
             // 更新时间管理器并打印时间数据
+            time_manager.set_frame_history_len(game_settings.get_frame_history_len());
+            time_manager.set_max_delta_time(game_settings.get_max_delta_time());
             time_manager.update();
             // time_manager.print_time_data();
 
             // 渲染前操作
-            wgpu_state.prepare_for_new_frame();
+            wgpu_state.prepare_for_new_frame(game_settings.get_clear_color());
+
+            // 上传后台解码完成的资源
+            assets.process(wgpu_state, &mut sfx_manager);
+
+            // 记录各阶段耗时，供帧预算警告定位卡顿
+            let update_start = Instant::now();
+            let update_scope = ProfileScope::new("update");
+
+            // 固定步长逻辑，卡顿后最多补跑 max_fixed_steps 次
+            if let Some(hz) = game_settings.get_fixed_timestep() {
+                let step = Duration::from_secs_f32(1.0 / hz);
+                let steps = time_manager.advance_fixed_steps(step, game_settings.get_max_fixed_steps());
+                for _ in 0..steps {
+                    if let Err(e) = game.fixed_update(&mut game_settings, step.as_secs_f32()).await {
+                        error!("Game fixed update failed: {:#}", e);
+                        if !game.on_error(&e) {
+                            game.on_exit().await;
+                            game_settings.quit();
+                            return;
+                        }
+                    }
+                }
+            }
+
+            for plugin in plugins.iter_mut() {
+                plugin.update(&mut game_settings, wgpu_state, &time_manager, &mouse_input, &touch_input, &key_input);
+            }
 
             {
-                // 游戏逻辑
-                game.update(
-                    &mut game_settings,
-                    &time_manager,
-                    &mut sfx_manager,
-                    &mouse_input,
-                    &touch_input,
-                )
-                .await;
+                // 游戏逻辑，后台降帧期间改为调用 background_update
+                let result = if game_settings.is_background_throttled() {
+                    game.background_update(
+                        &mut game_settings,
+                        wgpu_state,
+                        &time_manager,
+                        &mut sfx_manager,
+                        &mut assets,
+                        &mouse_input,
+                        &touch_input,
+                        &mut key_input,
+                    )
+                    .await
+                } else {
+                    game.update(
+                        &mut game_settings,
+                        wgpu_state,
+                        &time_manager,
+                        &mut sfx_manager,
+                        &mut assets,
+                        &mouse_input,
+                        &touch_input,
+                        &mut key_input,
+                    )
+                    .await
+                };
+                if let Err(e) = result {
+                    error!("Game update failed: {:#}", e);
+                    if !game.on_error(&e) {
+                        game.on_exit().await;
+                        game_settings.quit();
+                        return;
+                    }
+                }
+            }
+
+            drop(update_scope);
+            let draw_start = Instant::now();
+            for plugin in plugins.iter_mut() {
+                plugin.draw(wgpu_state);
             }
 
             wgpu_state.draw();
+            wgpu_state.apply_post_process();
+            wgpu_state.draw_debug();
+            let present_start = Instant::now();
+            let present_scope = ProfileScope::new("present");
             // 执行 WGPU 渲染
             match wgpu_state.render() {
                 Ok(_) => {}
@@ -319,6 +782,13 @@ impl App {
                 }
             }
             wgpu_state.end_frame(&mut game_settings);
+            drop(present_scope);
+            swap_frame_profile(&mut time_manager.profile_scopes);
+            time_manager.phase_times = FramePhaseTimes {
+                update: draw_start - update_start,
+                draw: present_start - draw_start,
+                present: present_start.elapsed(),
+            };
             sfx_manager.maintain_stream();
 
             #[cfg(target_os = "android")]
@@ -327,7 +797,25 @@ impl App {
                 first_loop = false;
             }
 
-            framerate_limiter(window_ref, &mut time_manager, &game_settings); //.await;
+            // 游戏调用了 GameSettings::quit，主线程会退出事件循环，渲染循环也在这里结束
+            if game_settings.quit_requested {
+                info!("Quit requested by game. Exiting render loop.");
+                game.on_exit().await;
+                return;
+            }
+
+            if refresh_rate_queried.elapsed() >= REFRESH_RATE_POLL_INTERVAL {
+                game_settings.refresh_rate = query_refresh_rate(window_ref);
+                refresh_rate_queried = Instant::now();
+            }
+            framerate_limiter(&mut time_manager, &game_settings); //.await;
+
+            // 失去焦点且未开启后台运行模式时降到 background_fps，节省电量
+            if game_settings.is_background_throttled() {
+                let interval = Duration::from_secs_f64(1.0 / game_settings.get_background_fps() as f64);
+                sleep(interval.saturating_sub(last_background_frame.elapsed())).await;
+            }
+            last_background_frame = Instant::now();
         }
     }
 }
@@ -341,26 +829,54 @@ impl Drop for App {
             let _ = sender.send(WgpuStateCommand::Close);
         }
 
-        // 等待渲染线程结束（如果它还在运行）
-        if let Some(_) = self.render_thread_handle.take() {
-            // 如果渲染线程是 Tokio 任务，这里无法同步等待其完成，
-            // 除非使用 `block_on` (不推荐在 drop 中使用) 或在 `shutdown_background` 中处理。
-            // `shutdown_background` 会尝试优雅关闭所有任务。
+        // 等待渲染任务结束（如果它还在运行）。渲染任务会访问窗口和全局渲染状态，
+        // 必须在回收窗口之前退出，否则 shutdown_background 可能在任务执行到一半时销毁运行时
+        let mut render_task_exited = true;
+        if let (Some(handle), Some(runtime)) =
+            (self.render_thread_handle.take(), self.runtime.as_ref())
+        {
+            // 当前线程不在运行时内部，可以安全地 block_on
+            let result = runtime.block_on(tokio::time::timeout(RENDER_SHUTDOWN_TIMEOUT, handle));
+            if result.is_err() {
+                warn!("Render task did not exit within {:?}, leaking the window.", RENDER_SHUTDOWN_TIMEOUT);
+                render_task_exited = false;
+            }
+        }
+
+        // 单线程运行时模式：渲染线程收到关闭命令后会退出循环，等待它结束。
+        // 超时未退出时不 join，避免卡住退出流程
+        if let Some(render_os_thread) = self.render_os_thread.take() {
+            let exited = self
+                .render_os_thread_exited
+                .take()
+                // 线程 panic 时发送端被释放，同样说明它已经结束
+                .is_some_and(|receiver| {
+                    !matches!(receiver.recv_timeout(RENDER_SHUTDOWN_TIMEOUT), Err(mpsc::RecvTimeoutError::Timeout))
+                });
+            if exited {
+                let _ = render_os_thread.join();
+            } else {
+                warn!("Render thread did not exit within {:?}, leaking the window.", RENDER_SHUTDOWN_TIMEOUT);
+                render_task_exited = false;
+            }
         }
 
-        // 停止 Tokio 运行时。这将尝试优雅地关闭所有在运行时上创建的异步任务。
+        // 停止 Tokio 运行时。渲染任务已经结束，这里只会清理剩余的后台任务（如异步加载）。
         if let Some(runtime) = self.runtime.take() {
             info!("Dropping App: Shutting down Tokio runtime.");
-            runtime.shutdown_background();
+            runtime.shutdown_timeout(RENDER_SHUTDOWN_TIMEOUT);
         }
 
-        // 回收 Box::leak 的内存
+        // 回收 Box::leak 的内存。渲染任务超时未退出时仍可能持有 `&'static Window`，
+        // 此时不释放窗口（ManuallyDrop 不调用析构），由进程退出时回收
         if let Some(mut boxed_window) = self.window_box.take() {
-            unsafe {
-                ManuallyDrop::drop(&mut boxed_window);
+            if render_task_exited {
+                unsafe {
+                    ManuallyDrop::drop(&mut boxed_window);
+                }
             }
         }
-        println!("Application about to close, cleaning up resources.");
+        info!("Application about to close, cleaning up resources.");
     }
 }
 
@@ -373,14 +889,13 @@ impl ApplicationHandler<WindowCommand> for App {
             .expect("Window should be initialized before processing user events");
 
         match event {
-            WindowCommand::SetFullscreen(fullscreen) => {
-                let mode = if fullscreen {
-                    Some(Fullscreen::Borderless(None))
-                } else {
-                    None
-                };
-
-                window.set_fullscreen(mode);
+            WindowCommand::SetFullscreen(mode) => {
+                if let Ok(fullscreen) = resolve_fullscreen(_event_loop, mode) {
+                    window.set_fullscreen(fullscreen);
+                }
+            }
+            WindowCommand::GetMonitors(response) => {
+                let _ = response.send(collect_monitors(_event_loop));
             }
             WindowCommand::SetWindowIcon(icon) => {
                 window.set_window_icon(Some(icon));
@@ -389,7 +904,74 @@ impl ApplicationHandler<WindowCommand> for App {
                 window.set_title(&title);
             }
             WindowCommand::SetResolution(mut new_size) => {
-                let _ = window.request_inner_size(new_size.ensure_non_zero());
+                // 使用窗口当前的缩放系数换算逻辑像素，缩放系数之后改变时会重新换算
+                self.logical_size = Some(new_size).filter(|size| matches!(size, Resolution::Logical(..)));
+                let scale_factor = window.scale_factor();
+                let new_size = self
+                    .config
+                    .clamp_size(new_size.ensure_non_zero().to_physical(scale_factor), scale_factor);
+                let _ = window.request_inner_size(new_size);
+            }
+            WindowCommand::SetResizable(resizable) => {
+                window.set_resizable(resizable);
+                self.config.resizable = resizable;
+            }
+            WindowCommand::SetDecorations(decorations) => {
+                window.set_decorations(decorations);
+                self.config.decorations = decorations;
+            }
+            WindowCommand::SetMinInnerSize(min_size) => {
+                let min_size = min_size.map(|mut s| s.ensure_non_zero());
+                window.set_min_inner_size(min_size);
+                self.config.min_size = min_size;
+            }
+            WindowCommand::SetMaxInnerSize(max_size) => {
+                let max_size = max_size.map(|mut s| s.ensure_non_zero());
+                window.set_max_inner_size(max_size);
+                self.config.max_size = max_size;
+            }
+            WindowCommand::SetCursorVisible(visible) => {
+                window.set_cursor_visible(visible);
+            }
+            WindowCommand::SetCursorGrab(grab) => {
+                if !grab {
+                    if let Err(e) = window.set_cursor_grab(CursorGrabMode::None) {
+                        warn!("Failed to release cursor: {}", e);
+                    }
+                    self.cursor_grabbed = false;
+                    return;
+                }
+
+                // 部分平台只支持其中一种模式（如 macOS 只支持 Locked，Windows 只支持 Confined）
+                let result = window
+                    .set_cursor_grab(CursorGrabMode::Locked)
+                    .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined));
+                match result {
+                    Ok(()) => self.cursor_grabbed = true,
+                    Err(e) => warn!("Failed to grab cursor: {}", e),
+                }
+            }
+            WindowCommand::SetCursor(source) => {
+                let cursor = _event_loop.create_custom_cursor(source);
+                window.set_cursor(cursor);
+            }
+            WindowCommand::ShowSoftKeyboard(show) => {
+                // 开启后才会收到 WindowEvent::Ime
+                window.set_ime_allowed(show);
+                #[cfg(target_os = "android")]
+                crate::tools::show_soft_keyboard(show);
+            }
+            WindowCommand::SetOrientation(orientation) => {
+                #[cfg(target_os = "android")]
+                crate::tools::set_orientation(orientation);
+                #[cfg(not(target_os = "android"))]
+                debug!("Screen orientation {:?} is ignored on this platform", orientation);
+            }
+            WindowCommand::SetKeepScreenOn(keep_on) => {
+                #[cfg(target_os = "android")]
+                crate::tools::set_keep_screen_on(keep_on);
+                #[cfg(not(target_os = "android"))]
+                debug!("Keep screen on ({}) is ignored on this platform", keep_on);
             }
             WindowCommand::Quit => {
                 _event_loop.exit();
@@ -413,15 +995,21 @@ impl ApplicationHandler<WindowCommand> for App {
         }
     }
 
+    /// 应用进入后台时调用。由渲染线程在帧之间销毁 Surface，这里等待它完成，
+    /// 避免在渲染线程使用 Surface 的同时销毁它。
     fn suspended(&mut self, _: &ActiveEventLoop) {
-        get_context().destroy_surface();
-
         let sender = self
             .render_command_sender
             .as_ref()
             .expect("Render command sender should be initialized for window events");
 
-        let _ = sender.send(WgpuStateCommand::Suspended);
+        let (released_sender, released_receiver) = channel();
+        let released = sender.send(WgpuStateCommand::Suspended(released_sender)).is_ok()
+            && released_receiver.recv_timeout(SURFACE_RELEASE_TIMEOUT).is_ok();
+        if !released {
+            // 渲染线程退出时 Surface 已随 WgpuState 释放；卡住时 WgpuState 归渲染线程所有，这里无法安全地销毁它
+            warn!("Render thread did not release the surface in time.");
+        }
     }
 
     /// 处理窗口事件。
@@ -439,7 +1027,6 @@ impl ApplicationHandler<WindowCommand> for App {
             .as_ref()
             .expect("Render command sender should be initialized for window events");
 
-        let input_event_sender = self.input_event_sender.as_ref();
         if window_id != window.id() {
             return;
         }
@@ -451,11 +1038,37 @@ impl ApplicationHandler<WindowCommand> for App {
                 // 向渲染线程发送调整大小命令
                 let _ = sender.send(WgpuStateCommand::Resize(PhysicalSize::new(width, height)));
             }
+            WindowEvent::Focused(focused) => {
+                // 通知渲染线程焦点变化
+                let _ = sender.send(WgpuStateCommand::Focused(focused));
+            }
+            WindowEvent::ScaleFactorChanged { scale_factor, mut inner_size_writer } => {
+                // 以逻辑像素指定的窗口大小在新的缩放系数下保持逻辑尺寸不变
+                if let Some(mut logical_size) = self.logical_size {
+                    let size = self
+                        .config
+                        .clamp_size(logical_size.ensure_non_zero().to_physical(scale_factor), scale_factor);
+                    if let Err(e) = inner_size_writer.request_inner_size(size) {
+                        warn!("Failed to keep logical window size: {}", e);
+                    }
+                }
+                let _ = sender.send(WgpuStateCommand::ScaleFactorChanged(scale_factor));
+                notify_refresh_rate(&mut self.refresh_rate, window, sender);
+            }
+            WindowEvent::Moved(_) => {
+                // 窗口可能被拖到了刷新率不同的显示器上
+                notify_refresh_rate(&mut self.refresh_rate, window, sender);
+            }
+            WindowEvent::Occluded(occluded) => {
+                let _ = sender.send(WgpuStateCommand::Occluded(occluded));
+            }
             WindowEvent::CloseRequested => {
-                info!("Window close requested. Exiting application.");
-                // 通知渲染线程关闭
-                let _ = sender.send(WgpuStateCommand::Close);
-                _event_loop.exit();
+                info!("Window close requested.");
+                // 交给渲染线程中的游戏决定是否退出，游戏同意后会发送 WindowCommand::Quit；
+                // 渲染线程已经结束时直接退出
+                if sender.send(WgpuStateCommand::CloseRequested).is_err() {
+                    _event_loop.exit();
+                }
             }
             WindowEvent::MouseInput { state, button, .. } => {
                 let button_state = match state {
@@ -463,23 +1076,60 @@ impl ApplicationHandler<WindowCommand> for App {
                     winit::event::ElementState::Released => MouseButtonState::Released,
                 };
                 // 将鼠标事件发送给渲染线程
-                if let Err(e) = input_event_sender.push(InputEvent::MouseButton {
+                self.send_input_event(InputEvent::MouseButton {
                     button,
                     state: button_state,
-                }) {
-                    warn!("Failed to send mouse event to render thread: {:?}", e);
+                });
+            }
+            WindowEvent::KeyboardInput { event, .. } => {
+                // 只转发按下时产生的文字，按键本身不在这里处理
+                if let Some(text) = event.text.filter(|_| event.state.is_pressed()) {
+                    self.send_input_event(InputEvent::Text(text.to_string()));
                 }
             }
+            WindowEvent::Ime(ime) => {
+                self.send_input_event(InputEvent::Ime(ime));
+            }
             WindowEvent::Touch(touch) => {
-                // 直接发送原始的Touch事件到渲染线程
-                if let Err(e) = input_event_sender.push(InputEvent::Touch(touch)) {
-                    warn!("Failed to send touch event to render thread: {:?}", e);
+                // 移动事件合并到批次结束时发送；其他阶段先发送该触控点挂起的移动，保持先后顺序
+                if touch.phase == winit::event::TouchPhase::Moved {
+                    self.pending_touch_moves.insert(touch.id, touch);
+                } else {
+                    if let Some(moved) = self.pending_touch_moves.remove(&touch.id) {
+                        self.send_input_event(InputEvent::Touch(moved));
+                    }
+                    self.send_input_event(InputEvent::Touch(touch));
                 }
             }
             _ => {}
         }
     }
 
+    /// 处理设备事件。光标被锁定后 `CursorMoved` 不再变化，改为转发原始鼠标位移。
+    fn device_event(&mut self, _event_loop: &ActiveEventLoop, _device_id: DeviceId, event: DeviceEvent) {
+        if !self.cursor_grabbed {
+            return;
+        }
+
+        // 累加到批次结束时一起发送
+        if let DeviceEvent::MouseMotion { delta: (dx, dy) } = event {
+            let motion = self.pending_mouse_motion.get_or_insert((0.0, 0.0));
+            motion.0 += dx;
+            motion.1 += dy;
+        }
+    }
+
+    /// 一批事件处理完毕，发送合并后的移动事件。
+    fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {
+        if let Some((dx, dy)) = self.pending_mouse_motion.take() {
+            self.send_input_event(InputEvent::MouseMotion { dx, dy });
+        }
+        let moves: Vec<Touch> = self.pending_touch_moves.drain().map(|(_, touch)| touch).collect();
+        for touch in moves {
+            self.send_input_event(InputEvent::Touch(touch));
+        }
+    }
+
     /// 当应用程序即将退出时调用。
     fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
         info!("Application exiting. Sending close command to render thread.");
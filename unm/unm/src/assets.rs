@@ -0,0 +1,169 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::mpsc::{channel, Receiver, Sender},
+};
+
+use log::{error, warn};
+use unm_sfx::{
+    clip::SfxHandle,
+    player::{decode_sound, DecodedSound, SfxManager},
+};
+
+use crate::{graphics::WgpuState, texture::Texture2DHandle};
+
+/// 单个资源的加载状态
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssetState {
+    Loading,
+    Loaded,
+    /// 读取或解码失败，附带错误信息
+    Failed(String),
+}
+
+// 后台线程解码完成后发回渲染循环的结果
+enum LoadResult {
+    Texture(Texture2DHandle, anyhow::Result<image::RgbaImage>),
+    Sound(SfxHandle, anyhow::Result<DecodedSound>),
+}
+
+/// 异步资源加载，由渲染循环持有并传给 `GameLoop`。
+///
+/// `load_*` 立即返回句柄，解码在 tokio 的阻塞线程池中执行，
+/// 渲染循环每帧开始时取回结果，在渲染线程上传纹理、把音效加入音频图集。
+/// 加载完成之前绘制纹理句柄会使用白色纹理，播放音效句柄不会发出声音。
+/// 每个句柄的结果可用 `texture_state`/`sound_state` 查询，一个资源失败不影响其他资源
+pub struct Assets {
+    sender: Sender<LoadResult>,
+    receiver: Receiver<LoadResult>,
+    textures: HashMap<Texture2DHandle, AssetState>,
+    sounds: HashMap<SfxHandle, AssetState>,
+    // 当前这一批的请求数和已完成数，全部完成后再次加载时重新计数
+    requested: usize,
+    finished: usize,
+}
+
+impl Assets {
+    pub(crate) fn new() -> Self {
+        let (sender, receiver) = channel();
+        Self {
+            sender,
+            receiver,
+            textures: HashMap::new(),
+            sounds: HashMap::new(),
+            requested: 0,
+            finished: 0,
+        }
+    }
+
+    /// 从文件加载纹理
+    pub fn load_texture(&mut self, graphics: &mut WgpuState, path: impl Into<PathBuf>) -> Texture2DHandle {
+        let path = path.into();
+        self.spawn_texture(graphics, move || Ok(std::fs::read(path)?))
+    }
+
+    /// 从内存中的图片数据（PNG、JPEG 等）加载纹理
+    pub fn load_texture_from_memory(&mut self, graphics: &mut WgpuState, bytes: Vec<u8>) -> Texture2DHandle {
+        self.spawn_texture(graphics, move || Ok(bytes))
+    }
+
+    fn spawn_texture(
+        &mut self,
+        graphics: &mut WgpuState,
+        read: impl FnOnce() -> anyhow::Result<Vec<u8>> + Send + 'static,
+    ) -> Texture2DHandle {
+        let handle = graphics.reserve_texture();
+        self.begin_request();
+        self.textures.insert(handle, AssetState::Loading);
+
+        let sender = self.sender.clone();
+        tokio::task::spawn_blocking(move || {
+            let image = read().and_then(|bytes| Ok(image::load_from_memory(&bytes)?.to_rgba8()));
+            let _ = sender.send(LoadResult::Texture(handle, image));
+        });
+        handle
+    }
+
+    /// 加载音效（WAV、OGG 等）。
+    /// 解码完成后会按全部音效重新生成音频图集，正在播放的声音不受影响；生成图集的开销随音效总量增长，应尽量集中加载
+    pub fn load_sound(&mut self, sfx_manager: &mut SfxManager, bytes: Vec<u8>) -> SfxHandle {
+        let handle = sfx_manager.reserve_sound();
+        self.begin_request();
+        self.sounds.insert(handle, AssetState::Loading);
+
+        let sender = self.sender.clone();
+        tokio::task::spawn_blocking(move || {
+            let _ = sender.send(LoadResult::Sound(handle, decode_sound(bytes)));
+        });
+        handle
+    }
+
+    fn begin_request(&mut self) {
+        if self.finished == self.requested {
+            self.requested = 0;
+            self.finished = 0;
+        }
+        self.requested += 1;
+    }
+
+    /// 当前这一批资源的加载进度，范围 [0, 1]，没有资源在加载时为 1。
+    /// 失败的资源也算作已完成
+    pub fn progress(&self) -> f32 {
+        if self.requested == 0 {
+            1.0
+        } else {
+            self.finished as f32 / self.requested as f32
+        }
+    }
+
+    /// 所有请求的资源是否都已完成（成功或失败）
+    pub fn all_loaded(&self) -> bool {
+        self.finished == self.requested
+    }
+
+    /// 纹理的加载状态，句柄不是由 `Assets` 加载的时返回 None
+    pub fn texture_state(&self, handle: Texture2DHandle) -> Option<&AssetState> {
+        self.textures.get(&handle)
+    }
+
+    /// 音效的加载状态，句柄不是由 `Assets` 加载的时返回 None
+    pub fn sound_state(&self, handle: SfxHandle) -> Option<&AssetState> {
+        self.sounds.get(&handle)
+    }
+
+    // 在渲染循环中每帧调用，上传已经解码完成的资源。同一帧完成的音效一起加入，只重建一次音频流
+    pub(crate) fn process(&mut self, graphics: &mut WgpuState, sfx_manager: &mut SfxManager) {
+        let mut decoded_sounds = Vec::new();
+
+        for result in self.receiver.try_iter() {
+            self.finished += 1;
+            match result {
+                LoadResult::Texture(handle, Ok(image)) => {
+                    graphics.upload_reserved_texture(handle, &image);
+                    self.textures.insert(handle, AssetState::Loaded);
+                }
+                LoadResult::Texture(handle, Err(e)) => {
+                    error!("Failed to load texture {:?}: {:#}", handle, e);
+                    self.textures.insert(handle, AssetState::Failed(format!("{:#}", e)));
+                }
+                LoadResult::Sound(handle, Ok(sound)) => decoded_sounds.push((handle, sound)),
+                LoadResult::Sound(handle, Err(e)) => {
+                    error!("Failed to load sound {:?}: {:#}", handle, e);
+                    self.sounds.insert(handle, AssetState::Failed(format!("{:#}", e)));
+                }
+            }
+        }
+
+        if decoded_sounds.is_empty() {
+            return;
+        }
+        let handles: Vec<_> = decoded_sounds.iter().map(|(handle, _)| *handle).collect();
+        // 音效已经保存在音频后端中，音频流建立失败时会在 maintain_stream 中重试
+        if !sfx_manager.add_sounds(decoded_sounds) {
+            warn!("Audio stream is not available, {} new sounds will play once it is rebuilt.", handles.len());
+        }
+        for handle in handles {
+            self.sounds.insert(handle, AssetState::Loaded);
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, CommandEncoder, Device, FilterMode,
+    PipelineLayout, RenderPassTimestampWrites, RenderPipeline, Sampler, SamplerBindingType,
+    SamplerDescriptor, ShaderModule, ShaderStages, TextureFormat, TextureSampleType, TextureView,
+    TextureViewDimension,
+};
+
+/// HDR 默认渲染目标呈现到 LDR Surface 时使用的色调映射曲线
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Tonemapper {
+    /// `c / (c + 1)`，简单、不改变色相，但高光偏灰
+    Reinhard,
+    /// ACES 电影曲线拟合，对比度更高，高光过渡更自然
+    #[default]
+    Aces,
+}
+
+impl Tonemapper {
+    fn entry_point(&self) -> &'static str {
+        match self {
+            Tonemapper::Reinhard => "fs_reinhard",
+            Tonemapper::Aces => "fs_aces",
+        }
+    }
+}
+
+/// 把一张纹理拉伸绘制到另一张纹理上的全屏 Pass。
+/// 与 `copy_texture_to_texture` 不同，源和目标的尺寸、格式都可以不一致。
+pub(crate) struct Blitter {
+    shader: ShaderModule,
+    bind_group_layout: BindGroupLayout,
+    pipeline_layout: PipelineLayout,
+    sampler: Sampler,
+    // 按 (目标格式, 色调映射, 采样数) 缓存管线
+    pipelines: HashMap<(TextureFormat, Option<Tonemapper>, u32), RenderPipeline>,
+}
+
+impl Blitter {
+    pub(crate) fn new(device: &Device) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Blit Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/Blit.wgsl").into()),
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Blit Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Blit Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            ..Default::default()
+        });
+
+        // 尺寸不一致时线性过滤，缩放后不会出现明显锯齿
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("Blit Sampler"),
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            shader,
+            bind_group_layout,
+            pipeline_layout,
+            sampler,
+            pipelines: HashMap::new(),
+        }
+    }
+
+    /// 获取（必要时创建）写入 `format` 目标的管线，`sample_count` 为目标颜色附件的采样数
+    pub(crate) fn pipeline(
+        &mut self,
+        device: &Device,
+        format: TextureFormat,
+        tonemapper: Option<Tonemapper>,
+        sample_count: u32,
+    ) -> &RenderPipeline {
+        self.pipelines.entry((format, tonemapper, sample_count)).or_insert_with(|| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Blit Pipeline"),
+                layout: Some(&self.pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &self.shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &self.shader,
+                    entry_point: Some(tonemapper.map_or("fs_main", |t| t.entry_point())),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..Default::default()
+                },
+                cache: None,
+                multiview_mask: None,
+            })
+        })
+    }
+
+    /// 后处理材质第 0 组的绑定布局（源纹理 + 采样器）
+    pub(crate) fn bind_group_layout(&self) -> &BindGroupLayout {
+        &self.bind_group_layout
+    }
+
+    /// 为源纹理创建第 0 组绑定
+    pub(crate) fn source_bind_group(&self, device: &Device, source: &TextureView) -> BindGroup {
+        device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Blit Bind Group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(source),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        })
+    }
+
+    /// 将 `source` 拉伸绘制到 `target`，`target_format` 为 `target` 视图的格式。
+    /// `tonemapper` 不为 `None` 时在复制的同时做色调映射，`timestamp_writes` 用于 GPU 计时。
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn blit(
+        &mut self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        source: &TextureView,
+        target: &TextureView,
+        target_format: TextureFormat,
+        tonemapper: Option<Tonemapper>,
+        timestamp_writes: Option<RenderPassTimestampWrites<'_>>,
+    ) {
+        let bind_group = self.source_bind_group(device, source);
+
+        let pipeline = self.pipeline(device, target_format, tonemapper, 1);
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Blit Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            timestamp_writes,
+            ..Default::default()
+        });
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
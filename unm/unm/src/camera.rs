@@ -21,6 +21,11 @@ pub trait Camera: Send + Sync + Debug {
     fn set_render_target(&mut self, new_rt: Option<RenderTargetHandle>);
 
     fn get_forward(&self) -> Vec3;
+
+    /// 世界空间 XY 平面上的可见范围，用于 2D 剔除。无法简单表示（如透视相机）时返回 `None`，不做剔除
+    fn visible_rect(&self) -> Option<Rect> {
+        None
+    }
 }
 
 #[derive(Debug)]
@@ -165,6 +170,16 @@ pub struct Rect {
     pub h: f32,
 }
 
+impl Rect {
+    /// 两个矩形是否相交（边界接触也算相交）
+    pub fn overlaps(&self, other: &Rect) -> bool {
+        self.x <= other.x + other.w
+            && other.x <= self.x + self.w
+            && self.y <= other.y + other.h
+            && other.y <= self.y + self.h
+    }
+}
+
 
 #[derive(Debug)]
 pub struct Camera2D {
@@ -191,9 +206,28 @@ impl Camera2D {
         camera2d.resize(size); // 在初始化时调用 resize 设置 rect
         camera2d
     }
+
+    /// 世界空间中的可见矩形（`(x, y)` 为左下角），相机绕 Z 轴旋转时为旋转后视口的包围盒
+    pub fn visible_rect(&self) -> Rect {
+        // rect 的字段依次是 left, right, bottom, top
+        let (left, right, bottom, top) = (self.rect.x, self.rect.y, self.rect.w, self.rect.h);
+        let center = self.base.pos.truncate();
+
+        let (mut min, mut max) = (glam::Vec2::splat(f32::MAX), glam::Vec2::splat(f32::MIN));
+        for corner in [(left, bottom), (right, bottom), (left, top), (right, top)] {
+            let world = center + (self.base.rot * Vec3::new(corner.0, corner.1, 0.0)).truncate();
+            min = min.min(world);
+            max = max.max(world);
+        }
+        Rect { x: min.x, y: min.y, w: max.x - min.x, h: max.y - min.y }
+    }
 }
 
 impl Camera for Camera2D {
+    fn visible_rect(&self) -> Option<Rect> {
+        Some(Camera2D::visible_rect(self))
+    }
+
     fn matrix(&self) -> Mat4 {
         let base = &self.base;
         let up = base.rot * Vec3::Y; // Y轴仍然是上方向
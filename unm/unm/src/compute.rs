@@ -0,0 +1,135 @@
+use glam::UVec3;
+use unm_tools::id_map::IdMapKey;
+use wgpu::{naga, BindGroup, BindGroupLayout, ComputePipeline};
+
+use crate::render_context::RenderContext;
+
+#[derive(Default, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub struct ComputeMaterialHandle(u64);
+
+impl IdMapKey for ComputeMaterialHandle {
+    fn from(id: u64) -> Self {
+        ComputeMaterialHandle(id)
+    }
+    fn to(&self) -> u64 {
+        self.0
+    }
+}
+
+#[derive(Default, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub struct StorageBufferHandle(u64);
+
+impl IdMapKey for StorageBufferHandle {
+    fn from(id: u64) -> Self {
+        StorageBufferHandle(id)
+    }
+    fn to(&self) -> u64 {
+        self.0
+    }
+}
+
+/// 入口函数实际使用的一个 `@group(0)` 存储缓冲区绑定
+pub(crate) struct StorageBinding {
+    pub(crate) binding: u32,
+    // 绑定的缓冲区至少需要的字节数，运行时数组按一个元素计算
+    pub(crate) min_size: u64,
+}
+
+/// 计算着色器管线。
+///
+/// 绑定布局由着色器反射得到：`@group(0)` 中的 `@binding(i)` 依次对应
+/// `WgpuState::dispatch_compute` 传入的第 i 个存储缓冲区。
+pub(crate) struct ComputeMaterial {
+    pub(crate) name: String,
+    pub(crate) pipeline: ComputePipeline,
+    pub(crate) bind_group_layout: BindGroupLayout,
+    // 按 binding 升序排列，调度前据此检查 bind_data，不需要等待 wgpu 的错误作用域
+    pub(crate) bindings: Vec<StorageBinding>,
+}
+
+/// 已通过检查、等待录制到下一次绘制的 encoder 中的计算调度
+pub(crate) struct ComputeDispatch {
+    pub(crate) material: ComputeMaterialHandle,
+    pub(crate) bind_group: BindGroup,
+    pub(crate) workgroups: UVec3,
+}
+
+impl ComputeMaterial {
+    pub(crate) async fn new(
+        context: &RenderContext,
+        name: String,
+        shader_str: String,
+        entry_point: &str,
+    ) -> Result<ComputeMaterial, wgpu::Error> {
+        let error_scope = context.device.push_error_scope(wgpu::ErrorFilter::Validation);
+
+        let bindings = reflect_storage_bindings(&shader_str, entry_point);
+        let shader = context.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(&format!("{0} Compute Shader", name)),
+            source: wgpu::ShaderSource::Wgsl(shader_str.into()),
+        });
+
+        // 不指定管线布局，由 wgpu 根据着色器中实际使用的绑定自动生成
+        let pipeline = context.device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some(&format!("{0} Compute Pipeline", name)),
+            layout: None,
+            module: &shader,
+            entry_point: Some(entry_point),
+            compilation_options: Default::default(),
+            cache: context.pipeline_cache.as_ref(),
+        });
+        let bind_group_layout = pipeline.get_bind_group_layout(0);
+
+        if let Some(err) = error_scope.pop().await {
+            Err(err)
+        } else {
+            Ok(ComputeMaterial {
+                name,
+                pipeline,
+                bind_group_layout,
+                bindings,
+            })
+        }
+    }
+}
+
+// 找出入口函数实际用到的 `@group(0)` 存储缓冲区。自动生成的绑定布局只包含这些绑定，
+// 着色器无法解析时返回空列表，错误由之后创建着色器模块时的错误作用域报告
+fn reflect_storage_bindings(shader_str: &str, entry_point: &str) -> Vec<StorageBinding> {
+    let Ok(module) = naga::front::wgsl::parse_str(shader_str) else {
+        return Vec::new();
+    };
+    let Ok(info) = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module) else {
+        return Vec::new();
+    };
+    let Some(index) = module
+        .entry_points
+        .iter()
+        .position(|ep| ep.stage == naga::ShaderStage::Compute && ep.name == entry_point)
+    else {
+        return Vec::new();
+    };
+    let entry_info = info.get_entry_point(index);
+
+    let mut bindings: Vec<StorageBinding> = module
+        .global_variables
+        .iter()
+        .filter(|(handle, var)| {
+            matches!(var.space, naga::AddressSpace::Storage { .. })
+                && var.binding.as_ref().is_some_and(|b| b.group == 0)
+                && !entry_info[*handle].is_empty()
+        })
+        .filter_map(|(_, var)| {
+            Some(StorageBinding {
+                binding: var.binding.as_ref()?.binding,
+                min_size: module.types[var.ty].inner.size(module.to_ctx()) as u64,
+            })
+        })
+        .collect();
+    bindings.sort_by_key(|b| b.binding);
+    bindings
+}
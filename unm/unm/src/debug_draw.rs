@@ -0,0 +1,61 @@
+use std::f32::consts::TAU;
+
+use glam::{vec2, Vec2};
+
+use crate::{camera::Rect, vertex::Vertex};
+
+// 圆周分段数
+const CIRCLE_SEGMENTS: u32 = 32;
+
+/// 本帧收集的调试线段（线段列表，每 2 个索引一条），在所有绘制之后单独提交，`prepare_for_new_frame` 时清空
+#[derive(Debug, Default)]
+pub(crate) struct DebugDraw {
+    pub(crate) vertices: Vec<Vertex>,
+    pub(crate) indices: Vec<u32>,
+}
+
+impl DebugDraw {
+    pub(crate) fn clear(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    pub(crate) fn line(&mut self, from: Vec2, to: Vec2, color: wgpu::Color) {
+        let base = self.vertices.len() as u32;
+        self.vertices.push(Vertex::new(from.extend(0.0), Vec2::ZERO, color));
+        self.vertices.push(Vertex::new(to.extend(0.0), Vec2::ZERO, color));
+        self.indices.extend([base, base + 1]);
+    }
+
+    // 首尾相连的折线
+    fn polygon(&mut self, points: impl Iterator<Item = Vec2>, color: wgpu::Color) {
+        let base = self.vertices.len() as u32;
+        self.vertices.extend(points.map(|p| Vertex::new(p.extend(0.0), Vec2::ZERO, color)));
+        let count = self.vertices.len() as u32 - base;
+        for i in 0..count {
+            self.indices.extend([base + i, base + (i + 1) % count]);
+        }
+    }
+
+    pub(crate) fn rect(&mut self, rect: Rect, color: wgpu::Color) {
+        let corners = [
+            vec2(rect.x, rect.y),
+            vec2(rect.x + rect.w, rect.y),
+            vec2(rect.x + rect.w, rect.y + rect.h),
+            vec2(rect.x, rect.y + rect.h),
+        ];
+        self.polygon(corners.into_iter(), color);
+    }
+
+    pub(crate) fn circle(&mut self, center: Vec2, radius: f32, color: wgpu::Color) {
+        let points = (0..CIRCLE_SEGMENTS).map(|i| {
+            let angle = i as f32 / CIRCLE_SEGMENTS as f32 * TAU;
+            center + vec2(angle.cos(), angle.sin()) * radius
+        });
+        self.polygon(points, color);
+    }
+}
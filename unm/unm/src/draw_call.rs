@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use crate::{get_context, get_quad_context, material::MaterialHandle, render_command::RenderCommand, render_target::{RenderTarget, RenderTargetHandle}, uniform::Uniform};
+use crate::{material::MaterialHandle, render_command::RenderCommand, render_target::{RenderTarget, RenderTargetHandle}, texture::Texture2DHandle, uniform::Uniform};
 
 #[derive(Default)]
 pub struct DrawCall {
@@ -8,9 +8,12 @@ pub struct DrawCall {
     pub indices_count: usize,
     pub vertices_start: usize,
     pub indices_start: usize,
+    // 为 false 时用 `pass.draw` 按顶点顺序绘制，不占用索引缓冲
+    pub indexed: bool,
 
     pub mat_handle: MaterialHandle,
     pub uniforms: Option<HashMap<String, Uniform>>,
+    pub texture: Option<Texture2DHandle>,
 
     pub render_target: RenderTargetHandle
 }
@@ -22,9 +25,10 @@ impl DrawCall {
             indices_start: 0,
             vertices_count: 0,
             indices_count: 0,
+            indexed: command.indexed,
             // viewport: None,
             // clip: None,
-            // texture,
+            texture: command.texture,
             // model,
             // draw_mode,
             mat_handle: command.mat_handle,
@@ -0,0 +1,32 @@
+use std::time::Duration;
+
+/// 单帧渲染统计数据，用于观察合批效果和性能回归测试。
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameStats {
+    /// 本帧提交的 RenderCommand 数量
+    pub render_commands: usize,
+    /// 合批后实际执行的 DrawCall 数量
+    pub draw_calls: usize,
+    /// 上传到 GPU 的顶点数量
+    pub vertices: usize,
+    /// 上传到 GPU 的索引数量
+    pub indices: usize,
+    /// RenderPass 因渲染目标改变而切换的次数
+    pub render_target_switches: usize,
+    /// 顶点/索引缓冲区上传的总字节数
+    pub buffer_uploads_bytes: usize,
+    /// CPU 写入顶点/索引缓冲区所花的时间
+    pub buffer_upload_time: Duration,
+    /// 轮换缓冲区时因 GPU 仍在读取而等待的次数，正常情况下应为 0
+    pub buffer_ring_stalls: usize,
+    /// 本帧提交的计算调度次数
+    pub compute_dispatches: usize,
+    /// 开启剔除后因在相机可见范围之外而跳过的 RenderCommand 数量
+    pub culled_commands: usize,
+}
+
+impl FrameStats {
+    pub(crate) fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
@@ -1,21 +1,87 @@
 use async_trait::async_trait;
 use unm_sfx::player::SfxManager;
-use crate::{game_settings::GameSettings, graphics::WgpuState, input::{MouseInput, TouchInput}, tools::TimeManager};
+use winit::dpi::PhysicalSize;
+use crate::{assets::Assets, game_settings::GameSettings, graphics::WgpuState, input::{KeyInput, MouseInput, TouchInput}, tools::TimeManager};
 
 #[async_trait]
 pub trait GameLoop: Send {
+    /// 渲染循环开始前调用一次，用于加载资源。返回错误时记录日志并退出程序。
+    /// `graphics` 由渲染循环持有，只在回调期间借给游戏，用于创建材质、渲染目标等。
+    /// 通过 `assets` 加载的资源在之后的帧中陆续完成，可在 `update` 中根据 `progress` 显示加载界面
     async fn start(
         &mut self,
         game_settings: &mut GameSettings,
-        sfx_manager: &mut SfxManager
-    );
+        graphics: &mut WgpuState,
+        sfx_manager: &mut SfxManager,
+        assets: &mut Assets,
+    ) -> anyhow::Result<()>;
 
+    /// 每帧调用一次。返回错误时交给 `on_error` 决定是否继续运行
+    #[allow(clippy::too_many_arguments)]
     async fn update(
         &mut self,
         game_settings: &mut GameSettings,
+        graphics: &mut WgpuState,
         time_manager: &TimeManager,
         sfx_manager: &mut SfxManager,
+        assets: &mut Assets,
         mouse_input: &MouseInput,
         touch_input: &TouchInput,
-    );
+        key_input: &mut KeyInput,
+    ) -> anyhow::Result<()>;
+
+    /// 窗口失去焦点且未开启后台运行模式时代替 `update` 调用，帧率降到 `GameSettings::set_background_fps`。
+    /// 默认直接调用 `update`；需要在后台暂停游戏逻辑（如不再触发音效）的游戏可以只记录绘制命令。
+    /// 重新获得焦点后第一帧的增量时间受 `GameSettings::set_max_delta_time` 限制
+    #[allow(clippy::too_many_arguments)]
+    async fn background_update(
+        &mut self,
+        game_settings: &mut GameSettings,
+        graphics: &mut WgpuState,
+        time_manager: &TimeManager,
+        sfx_manager: &mut SfxManager,
+        assets: &mut Assets,
+        mouse_input: &MouseInput,
+        touch_input: &TouchInput,
+        key_input: &mut KeyInput,
+    ) -> anyhow::Result<()> {
+        self.update(game_settings, graphics, time_manager, sfx_manager, assets, mouse_input, touch_input, key_input)
+            .await
+    }
+
+    /// 以 `GameSettings::set_fixed_timestep` 设置的固定频率调用，每帧在 `update` 之前执行零次或多次，
+    /// 适合物理等需要确定性的逻辑。返回错误时与 `update` 一样交给 `on_error` 处理
+    async fn fixed_update(&mut self, _game_settings: &mut GameSettings, _fixed_dt: f32) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// `update` 或 `fixed_update` 返回错误时调用，返回 true 继续运行，默认退出程序
+    fn on_error(&mut self, _error: &anyhow::Error) -> bool {
+        false
+    }
+
+    /// 窗口大小改变且默认渲染目标已重建后调用
+    fn on_resize(&mut self, _new_size: PhysicalSize<u32>) {}
+
+    /// 窗口的 DPI 缩放系数改变时调用，例如窗口被拖到另一块显示器上
+    fn on_scale_factor_changed(&mut self, _scale_factor: f64) {}
+
+    /// 窗口获得/失去焦点时调用
+    fn on_focus_changed(&mut self, _focused: bool) {}
+
+    /// 应用进入后台（Android 上 Surface 被销毁）时调用
+    fn on_suspend(&mut self) {}
+
+    /// 应用从后台恢复且 Surface 重建后调用，首次启动不会调用
+    fn on_resume(&mut self) {}
+
+    /// 用户请求关闭窗口时调用，返回 false 可取消关闭
+    fn on_quit_requested(&mut self) -> bool {
+        true
+    }
+
+    /// 渲染循环结束前调用（`GameSettings::quit`、关闭窗口、`update` 出错或应用退出），可以在这里保存设置等。
+    /// `start` 失败时不会调用。
+    /// 主线程最多等待 2 秒，超时后运行时会被关闭
+    async fn on_exit(&mut self) {}
 }
\ No newline at end of file
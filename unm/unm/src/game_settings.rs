@@ -1,73 +1,513 @@
-use winit::{dpi::PhysicalSize, event_loop::EventLoopProxy, window::Icon};
+use std::{sync::mpsc, time::Duration};
 
-use crate::{app::WindowCommand, msaa::Msaa, resolution::Resolution};
+use log::{error, warn};
+use wgpu::PresentMode;
+use winit::{dpi::PhysicalSize, event_loop::EventLoopProxy, window::{CustomCursorSource, Icon}};
+
+use crate::{app::WindowCommand, blit::Tonemapper, monitor::{FullscreenMode, MonitorInfo}, msaa::Msaa, resolution::Resolution, tools::{SleepStrategy, DEFAULT_FRAME_HISTORY_LEN, DEFAULT_MAX_DELTA_TIME}};
+
+/// 单个 DrawCall 默认的顶点上限
+pub const DEFAULT_MAX_BATCH_VERTICES: usize = 1024 * 1024;
+/// 单个 DrawCall 默认的索引上限
+pub const DEFAULT_MAX_BATCH_INDICES: usize = 1024 * 1024;
+
+/// 默认允许 CPU 领先 GPU 的最大帧数
+pub const DEFAULT_MAX_FRAME_LATENCY: u32 = 2;
+/// 最大帧延迟的有效范围
+pub const MIN_MAX_FRAME_LATENCY: u32 = 1;
+pub const MAX_MAX_FRAME_LATENCY: u32 = 3;
+
+/// set_max_delta_time 允许的最小值
+pub const MIN_MAX_DELTA_TIME: Duration = Duration::from_millis(1);
+
+/// 默认的固定步长频率（Hz）
+pub const DEFAULT_FIXED_TIMESTEP_HZ: f32 = 60.0;
+/// 默认每帧最多补跑的固定步数
+pub const DEFAULT_MAX_FIXED_STEPS: u32 = 5;
+
+/// 失去焦点且未开启后台运行模式时默认的帧率
+pub const DEFAULT_BACKGROUND_FPS: u32 = 10;
+
+/// 移动平台的屏幕方向，见 `GameSettings::set_orientation`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Orientation {
+    /// 跟随系统设置和传感器
+    #[default]
+    Auto,
+    /// 横屏，可随传感器在正反横屏之间旋转
+    Landscape,
+    /// 竖屏，可随传感器在正反竖屏之间旋转
+    Portrait,
+}
+
+/// 目标帧率，见 `GameSettings::set_target_fps`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FrameRate {
+    /// 垂直同步：Fifo 呈现模式等待显示器刷新，CPU 不再额外限帧
+    #[default]
+    Vsync,
+    /// 不限帧率（用于性能测试）：Immediate/Mailbox 呈现模式，CPU 不等待
+    Unlimited,
+    /// 在 CPU 上限制到指定帧率，同时关闭垂直同步，避免两者叠加导致帧率减半。`Cap(0)` 等同于 `Unlimited`
+    Cap(u32),
+}
 
 pub struct GameSettings {
-    event_loop: EventLoopProxy<WindowCommand>,
-    target_fps: i32,
+    // 无窗口模式下为 None，窗口相关的设置被忽略
+    event_loop: Option<EventLoopProxy<WindowCommand>>,
+    target_fps: FrameRate,
+    sleep_strategy: SleepStrategy,
     background_run_mode: bool,
+    background_fps: u32,
+    pub(crate) is_focused: bool,
+    pub(crate) is_occluded: bool,
+    pub(crate) quit_requested: bool,
     pub(crate) current_window_size: PhysicalSize<u32>,
+    pub(crate) scale_factor: f64,
+    // 当前显示器的刷新率（Hz），由渲染循环更新
+    pub(crate) refresh_rate: f64,
+    logical_pixel_projection: bool,
+    culling: bool,
     pub(crate) msaa: Msaa,
     pub(crate) new_msaa: Option<Msaa>,
+    // 设备支持的 MSAA 采样数，由渲染线程在初始化时填入
+    pub(crate) supported_msaa: Vec<Msaa>,
+    max_batch_vertices: usize,
+    max_batch_indices: usize,
+    pub(crate) max_frame_latency: u32,
+    pub(crate) new_max_frame_latency: Option<u32>,
+    clear_color: wgpu::Color,
+    frame_budget_factor: Option<f32>,
+    frame_history_len: usize,
+    max_delta_time: Duration,
+    fixed_timestep_hz: Option<f32>,
+    max_fixed_steps: u32,
+    pub(crate) present_mode: PresentMode,
+    pub(crate) new_present_mode: Option<PresentMode>,
+    pub(crate) hdr: bool,
+    pub(crate) new_hdr: Option<bool>,
+    tonemapper: Tonemapper,
 }
 
 #[allow(dead_code)]
 impl GameSettings {
     pub fn new(event_loop: EventLoopProxy<WindowCommand>) -> Self {
+        Self::with_event_loop(Some(event_loop))
+    }
+
+    // 无窗口模式使用的设置
+    pub(crate) fn new_headless() -> Self {
+        Self::with_event_loop(None)
+    }
+
+    fn with_event_loop(event_loop: Option<EventLoopProxy<WindowCommand>>) -> Self {
         Self { 
-            target_fps: 0,
-            event_loop: event_loop,
+            target_fps: FrameRate::Vsync,
+            sleep_strategy: SleepStrategy::default(),
+            event_loop,
             background_run_mode: false,
+            background_fps: DEFAULT_BACKGROUND_FPS,
+            is_focused: true,
+            is_occluded: false,
+            quit_requested: false,
             current_window_size: PhysicalSize::new(1, 1),
+            scale_factor: 1.0,
+            refresh_rate: 60.0,
+            logical_pixel_projection: false,
+            culling: false,
             msaa: Msaa::Sample4,
-            new_msaa: Some(Msaa::Sample4)
+            new_msaa: Some(Msaa::Sample4),
+            supported_msaa: Vec::new(),
+            max_batch_vertices: DEFAULT_MAX_BATCH_VERTICES,
+            max_batch_indices: DEFAULT_MAX_BATCH_INDICES,
+            max_frame_latency: DEFAULT_MAX_FRAME_LATENCY,
+            new_max_frame_latency: None,
+            clear_color: wgpu::Color::BLACK,
+            frame_budget_factor: None,
+            frame_history_len: DEFAULT_FRAME_HISTORY_LEN,
+            max_delta_time: DEFAULT_MAX_DELTA_TIME,
+            fixed_timestep_hz: Some(DEFAULT_FIXED_TIMESTEP_HZ),
+            max_fixed_steps: DEFAULT_MAX_FIXED_STEPS,
+            present_mode: PresentMode::Fifo,
+            new_present_mode: None,
+            hdr: false,
+            new_hdr: None,
+            tonemapper: Tonemapper::default(),
         }
     }
 
+    // 发送给主线程的窗口指令，没有事件循环或事件循环已关闭时返回 false
+    fn send_window_command(&self, command: WindowCommand) -> bool {
+        self.event_loop.as_ref().is_some_and(|event_loop| event_loop.send_event(command).is_ok())
+    }
+
     // setter
     pub fn set_title(&self, title: String) {
-        self.event_loop.send_event(WindowCommand::SetTitle(title)).ok();
+        self.send_window_command(WindowCommand::SetTitle(title));
     }
 
+    // true: 在窗口当前所在的显示器上无边框全屏
     pub fn set_fullscreen(&self, fullscreen: bool) {
-        self.event_loop.send_event(WindowCommand::SetFullscreen(fullscreen)).ok();
+        let mode = if fullscreen { FullscreenMode::Borderless(None) } else { FullscreenMode::Off };
+        self.set_fullscreen_mode(mode);
+    }
+
+    // 显示器/显示模式序号来自 get_monitors，序号无效时不做修改
+    pub fn set_fullscreen_mode(&self, mode: FullscreenMode) {
+        self.send_window_command(WindowCommand::SetFullscreen(mode));
+    }
+
+    // 枚举显示器。需要等待主线程处理请求，不要每帧调用；主线程无响应时返回空列表
+    pub fn get_monitors(&self) -> Vec<MonitorInfo> {
+        const MONITOR_QUERY_TIMEOUT: Duration = Duration::from_secs(1);
+
+        let (sender, receiver) = mpsc::channel();
+        if !self.send_window_command(WindowCommand::GetMonitors(sender)) {
+            return Vec::new();
+        }
+        receiver.recv_timeout(MONITOR_QUERY_TIMEOUT).unwrap_or_else(|e| {
+            error!("Failed to query monitors: {}", e);
+            Vec::new()
+        })
     }
 
     pub fn set_resolution(&self, resolution: Resolution) {
-        self.event_loop.send_event(WindowCommand::SetResolution(resolution)).ok();
+        self.send_window_command(WindowCommand::SetResolution(resolution));
+    }
+
+    pub fn set_resizable(&self, resizable: bool) {
+        self.send_window_command(WindowCommand::SetResizable(resizable));
+    }
+
+    // 是否显示标题栏和边框
+    pub fn set_decorations(&self, decorations: bool) {
+        self.send_window_command(WindowCommand::SetDecorations(decorations));
+    }
+
+    // None: 不限制。之后的 set_resolution 会被限制在最小/最大尺寸之间
+    pub fn set_min_window_size(&self, min_size: Option<Resolution>) {
+        self.send_window_command(WindowCommand::SetMinInnerSize(min_size));
+    }
+
+    pub fn set_max_window_size(&self, max_size: Option<Resolution>) {
+        self.send_window_command(WindowCommand::SetMaxInnerSize(max_size));
     }
 
     pub fn set_window_icon(&self, icon: Icon) {
-        self.event_loop.send_event(WindowCommand::SetWindowIcon(icon)).ok();
+        self.send_window_command(WindowCommand::SetWindowIcon(icon));
+    }
+
+    // 从 PNG/JPEG 图片数据设置窗口图标，解码失败或尺寸不符合要求时打印错误并保持原图标。
+    // macOS、Android、iOS 不支持窗口图标，直接忽略
+    pub fn set_window_icon_from_bytes(&self, bytes: &[u8]) {
+        if let Some(icon) = decode_window_icon(bytes) {
+            self.set_window_icon(icon);
+        }
+    }
+
+    pub fn set_cursor_visible(&self, visible: bool) {
+        self.send_window_command(WindowCommand::SetCursorVisible(visible));
+    }
+
+    // 锁定光标，锁定后通过 MouseInput::get_mouse_delta 获取鼠标位移。
+    // 平台不支持时只打印警告，光标保持原样
+    pub fn set_cursor_grab(&self, grab: bool) {
+        self.send_window_command(WindowCommand::SetCursorGrab(grab));
+    }
+
+    // 自定义光标图片，可通过 CustomCursor::from_rgba 创建
+    pub fn set_cursor(&self, cursor: CustomCursorSource) {
+        self.send_window_command(WindowCommand::SetCursor(cursor));
+    }
+
+    // 显示/隐藏软键盘（Android 通过 JNI 调用 Activity 的 showSoftKeyboard），
+    // 同时开启/关闭输入法，输入的文字通过 KeyInput 获取
+    pub fn show_soft_keyboard(&self, show: bool) {
+        self.send_window_command(WindowCommand::ShowSoftKeyboard(show));
+    }
+
+    // 锁定屏幕方向（Android 通过 JNI 调用 Activity 的 setOrientation），桌面平台上无效
+    pub fn set_orientation(&self, orientation: Orientation) {
+        self.send_window_command(WindowCommand::SetOrientation(orientation));
     }
 
-    // <= 0: v-sync enable
-    pub fn set_target_fps(&mut self, new_target_fps: i32) {
-        self.target_fps = new_target_fps;
+    // 保持屏幕常亮（Android 通过 JNI 设置 FLAG_KEEP_SCREEN_ON），桌面平台上无效
+    pub fn set_keep_screen_on(&self, keep_on: bool) {
+        self.send_window_command(WindowCommand::SetKeepScreenOn(keep_on));
     }
 
+    // 请求退出程序。本帧结束后渲染循环停止，主线程退出事件循环
+    pub fn quit(&mut self) {
+        self.quit_requested = true;
+        self.send_window_command(WindowCommand::Quit);
+    }
+
+    // 同时切换呈现模式：Vsync 使用 Fifo，Unlimited 和 Cap 使用 Immediate/Mailbox（由 CPU 端 spin-sleep 限帧）。
+    // 呈现模式在帧末重新配置 Surface 后生效
+    pub fn set_target_fps(&mut self, target_fps: FrameRate) {
+        self.target_fps = target_fps;
+        self.new_present_mode = Some(match target_fps {
+            FrameRate::Vsync => PresentMode::Fifo,
+            FrameRate::Unlimited | FrameRate::Cap(_) => PresentMode::AutoNoVsync,
+        });
+    }
+
+    // FrameRate::Cap 限帧时等待下一帧的方式，默认 Hybrid(1ms)。
+    // SpinAccurate 精度最高但占满一个核心，笔记本和手机上建议使用 Hybrid 或 OsSleep
+    pub fn set_sleep_strategy(&mut self, sleep_strategy: SleepStrategy) {
+        self.sleep_strategy = sleep_strategy;
+    }
+
+    // 开启后失去焦点、窗口被遮挡时仍按目标帧率运行；关闭时被遮挡暂停渲染，失去焦点降到 background_fps
     pub fn set_background_run_mode(&mut self, background_run_mode: bool) {
         self.background_run_mode = background_run_mode;
     }
 
+    // 失去焦点且未开启后台运行模式时的帧率（至少为 1），这期间调用 GameLoop::background_update 代替 update
+    pub fn set_background_fps(&mut self, fps: u32) {
+        self.background_fps = fps.max(1);
+    }
+
+    // 设备不支持的采样数会降低到不超过它的最大可用值（打印警告），可用的采样数见 get_supported_msaa
     pub fn set_msaa(&mut self, msaa: Msaa) {
+        let mut msaa = msaa;
+        if !self.supported_msaa.is_empty() && !self.supported_msaa.contains(&msaa) {
+            let clamped = msaa.clamp_to(&self.supported_msaa);
+            warn!("{:?} is not supported by this device, using {:?} instead.", msaa, clamped);
+            msaa = clamped;
+        }
         self.new_msaa = Some(msaa);
     }
 
+    // 单个 DrawCall 的顶点/索引上限，超出的绘制命令会被拆分成多个 DrawCall
+    pub fn set_batch_limits(&mut self, max_vertices: usize, max_indices: usize) {
+        self.max_batch_vertices = max_vertices.max(1);
+        self.max_batch_indices = max_indices.max(1);
+    }
+
+    // Surface 允许排队的最大帧数，范围 1..=3，在帧末重新配置 Surface 后生效。
+    // 1：输入延迟最低，但 CPU 与 GPU 几乎无法并行，容易掉帧；
+    // 2/3：吞吐量更高、帧时间更平稳，代价是多出一到两帧的输入延迟。
+    pub fn set_max_frame_latency(&mut self, latency: u32) {
+        self.new_max_frame_latency =
+            Some(latency.clamp(MIN_MAX_FRAME_LATENCY, MAX_MAX_FRAME_LATENCY));
+    }
+
+    // 每帧开始时默认渲染目标的清除颜色（线性颜色，与顶点颜色一致）
+    pub fn set_clear_color(&mut self, clear_color: wgpu::Color) {
+        self.clear_color = clear_color;
+    }
+
+    // 单帧耗时超过 目标帧时间 × factor 时打印警告，None 表示关闭
+    pub fn set_frame_budget_factor(&mut self, factor: Option<f32>) {
+        self.frame_budget_factor = factor.map(|f| f.max(0.0));
+    }
+
+    // TimeManager 统计 FPS 和帧时间分位数所用的样本数量（至少为 1）。
+    // 窗口越长显示的 FPS 越平滑，越短则对帧率变化反应越快
+    pub fn set_frame_history_len(&mut self, len: usize) {
+        self.frame_history_len = len.max(1);
+    }
+
+    // TimeManager::get_delta_time 的上限，防止长时间停顿后游戏逻辑收到过大的增量时间。
+    // 至少为 1ms，为 0 时增量时间会一直是 0，游戏逻辑不再前进；get_time 不受影响，始终是真实经过的时间
+    pub fn set_max_delta_time(&mut self, max_delta_time: Duration) {
+        self.max_delta_time = max_delta_time.max(MIN_MAX_DELTA_TIME);
+    }
+
+    // GameLoop::fixed_update 的调用频率（Hz），<= 0 时不再调用 fixed_update
+    pub fn set_fixed_timestep(&mut self, hz: f32) {
+        self.fixed_timestep_hz = (hz > 0.0).then_some(hz);
+    }
+
+    // 卡顿后每帧最多补跑的固定步数（至少为 1），超出的时间直接丢弃，避免越补越慢
+    pub fn set_max_fixed_steps(&mut self, max_steps: u32) {
+        self.max_fixed_steps = max_steps.max(1);
+    }
+
+    // 请求的模式不被 Surface 支持时退回 Fifo，实际使用的模式可通过 get_present_mode 获取。
+    // 同时更新 target_fps：Fifo/FifoRelaxed 视为 Vsync；其他模式下原来是 Vsync 的改为 Unlimited，Cap 保持不变
+    pub fn set_present_mode(&mut self, present_mode: PresentMode) {
+        self.new_present_mode = Some(present_mode);
+        self.target_fps = match present_mode {
+            PresentMode::Fifo | PresentMode::FifoRelaxed => FrameRate::Vsync,
+            _ if self.target_fps == FrameRate::Vsync => FrameRate::Unlimited,
+            _ => self.target_fps,
+        };
+    }
+
+    // 等同于 set_target_fps(FrameRate::Vsync) / set_target_fps(FrameRate::Unlimited)
+    pub fn set_vsync(&mut self, vsync: bool) {
+        self.set_target_fps(if vsync { FrameRate::Vsync } else { FrameRate::Unlimited });
+    }
+
+    // 开启后默认渲染目标使用 Rgba16Float，颜色可以超过 1.0（适合 bloom 等效果），
+    // 呈现时再按 set_tonemapper 选择的曲线映射到 Surface；Surface 本身是 HDR 格式时不做映射。
+    // 设备不支持 Rgba16Float 渲染目标时保持关闭，实际状态可通过 get_hdr 获取
+    pub fn set_hdr(&mut self, hdr: bool) {
+        self.new_hdr = Some(hdr);
+    }
+
+    pub fn set_tonemapper(&mut self, tonemapper: Tonemapper) {
+        self.tonemapper = tonemapper;
+    }
+
     // getter
-    pub fn get_target_fps(&self) -> i32 {
+    pub fn get_target_fps(&self) -> FrameRate {
         self.target_fps
     }
 
+    pub fn get_sleep_strategy(&self) -> SleepStrategy {
+        self.sleep_strategy
+    }
+
     pub fn get_background_run_mode(&self) -> bool {
         self.background_run_mode
     }
 
+    pub fn get_background_fps(&self) -> u32 {
+        self.background_fps
+    }
+
+    // 当前是否处于降帧的后台状态：失去焦点且未开启后台运行模式
+    pub fn is_background_throttled(&self) -> bool {
+        !self.background_run_mode && !self.is_focused
+    }
+
+    // 窗口是否拥有焦点，失去焦点时可以在这里静音等
+    pub fn get_is_focused(&self) -> bool {
+        self.is_focused
+    }
+
+    // 窗口是否被完全遮挡或最小化，遮挡期间（未开启后台运行模式时）暂停渲染
+    pub fn get_is_occluded(&self) -> bool {
+        self.is_occluded
+    }
+
+    // 窗口所在显示器的刷新率（Hz）。窗口被移动到其他显示器时立即更新，其他情况下每秒检测一次
+    pub fn get_refresh_rate(&self) -> f64 {
+        self.refresh_rate
+    }
+
     pub fn get_window_size(&self) -> PhysicalSize<u32> {
         self.current_window_size
     }
 
+    // 窗口当前的 DPI 缩放系数（物理像素 / 逻辑像素）
+    pub fn get_scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    // 未设置相机时的默认投影是否以逻辑像素为单位，开启后 HiDPI 屏幕上的图形大小与普通屏幕一致
+    pub fn set_logical_pixel_projection(&mut self, enabled: bool) {
+        self.logical_pixel_projection = enabled;
+    }
+
+    pub fn get_logical_pixel_projection(&self) -> bool {
+        self.logical_pixel_projection
+    }
+
+    // 开启后跳过包围盒完全在 2D 相机可见范围之外的绘制命令。
+    // 每个命令录制时需要额外计算包围盒，只对 Camera2D 和默认投影生效
+    pub fn set_culling(&mut self, enabled: bool) {
+        self.culling = enabled;
+    }
+
+    pub fn get_culling(&self) -> bool {
+        self.culling
+    }
+
     pub fn get_msaa(&self) -> Msaa {
         self.msaa
     }
-}
\ No newline at end of file
+
+    pub fn get_supported_msaa(&self) -> &[Msaa] {
+        &self.supported_msaa
+    }
+
+    pub fn get_hdr(&self) -> bool {
+        self.hdr
+    }
+
+    pub fn get_tonemapper(&self) -> Tonemapper {
+        self.tonemapper
+    }
+
+    pub fn get_frame_history_len(&self) -> usize {
+        self.frame_history_len
+    }
+
+    pub fn get_max_delta_time(&self) -> Duration {
+        self.max_delta_time
+    }
+
+    pub fn get_fixed_timestep(&self) -> Option<f32> {
+        self.fixed_timestep_hz
+    }
+
+    pub fn get_max_fixed_steps(&self) -> u32 {
+        self.max_fixed_steps
+    }
+
+    pub fn get_frame_budget_factor(&self) -> Option<f32> {
+        self.frame_budget_factor
+    }
+
+    pub fn get_clear_color(&self) -> wgpu::Color {
+        self.clear_color
+    }
+
+    // 当前 Surface 实际使用的呈现模式
+    pub fn get_present_mode(&self) -> PresentMode {
+        self.present_mode
+    }
+
+    pub fn get_max_frame_latency(&self) -> u32 {
+        self.max_frame_latency
+    }
+
+    pub fn get_max_batch_vertices(&self) -> usize {
+        self.max_batch_vertices
+    }
+
+    pub fn get_max_batch_indices(&self) -> usize {
+        self.max_batch_indices
+    }
+}
+
+// 窗口图标的最大边长，与 Windows 图标的上限一致
+const MAX_WINDOW_ICON_SIZE: u32 = 256;
+
+// 把 PNG/JPEG 图片数据解码为窗口图标，失败时打印错误
+pub(crate) fn decode_window_icon(bytes: &[u8]) -> Option<Icon> {
+    // 这些平台上 winit 忽略窗口图标，不必解码
+    if cfg!(any(target_os = "macos", target_os = "android", target_os = "ios")) {
+        return None;
+    }
+
+    let image = match image::load_from_memory(bytes) {
+        Ok(image) => image.into_rgba8(),
+        Err(err) => {
+            error!("window icon decode error: {}", err);
+            return None;
+        }
+    };
+
+    // Windows 的图标最大为 256x256，所有平台统一按这个限制检查
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 || width > MAX_WINDOW_ICON_SIZE || height > MAX_WINDOW_ICON_SIZE {
+        error!(
+            "window icon must be between 1x1 and {0}x{0}, got {1}x{2}",
+            MAX_WINDOW_ICON_SIZE, width, height
+        );
+        return None;
+    }
+
+    match Icon::from_rgba(image.into_raw(), width, height) {
+        Ok(icon) => Some(icon),
+        Err(err) => {
+            error!("window icon error: {}", err);
+            None
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use std::f32::consts::PI;
+
+use glam::{vec2, Vec2};
+
+use crate::input::{TouchInput, TouchPhase};
+
+/// 基于 `TouchInput` 的双指缩放、旋转和单指拖动识别。
+///
+/// 每帧在读取触控之后调用一次 `update`。手指数量变化（加入或抬起手指）的那一帧只重新记录基准，
+/// 不产生任何增量，因此中途加减手指不会导致缩放/位移突变。
+/// 坐标与 `Touch` 一致，为窗口像素坐标（y 轴向下）。
+#[derive(Debug, Default, Clone)]
+pub struct GestureRecognizer {
+    // 上一帧参与手势的触控点 id，按 id 排序
+    touch_ids: Vec<u64>,
+    // 上一帧的基准：单指位置，或双指的距离与角度
+    prev_position: Vec2,
+    prev_distance: f32,
+    prev_angle: f32,
+
+    pinch_delta: Option<f32>,
+    pinch_center: Option<Vec2>,
+    pan_delta: Option<Vec2>,
+    rotation_delta: Option<f32>,
+}
+
+impl GestureRecognizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 根据本帧的触控更新手势
+    pub fn update(&mut self, touch_input: &TouchInput) {
+        self.pinch_delta = None;
+        self.pinch_center = None;
+        self.pan_delta = None;
+        self.rotation_delta = None;
+
+        let mut touches: Vec<_> = touch_input
+            .get_touches()
+            .into_iter()
+            .filter(|t| t.phase != TouchPhase::Ended && t.phase != TouchPhase::Cancelled)
+            .collect();
+        touches.sort_by_key(|t| t.id);
+
+        let ids: Vec<u64> = touches.iter().map(|t| t.id).collect();
+        let changed = ids != self.touch_ids;
+        self.touch_ids = ids;
+
+        match touches.as_slice() {
+            [touch] => {
+                let position = vec2(touch.x, touch.y);
+                if !changed {
+                    self.pan_delta = Some(position - self.prev_position);
+                }
+                self.prev_position = position;
+            }
+            [a, b] => {
+                let (a, b) = (vec2(a.x, a.y), vec2(b.x, b.y));
+                let offset = b - a;
+                let distance = offset.length();
+                let angle = offset.y.atan2(offset.x);
+
+                if !changed {
+                    if self.prev_distance > f32::EPSILON {
+                        self.pinch_delta = Some(distance / self.prev_distance);
+                    }
+                    self.rotation_delta = Some(wrap_angle(angle - self.prev_angle));
+                }
+                self.pinch_center = Some((a + b) / 2.0);
+                self.prev_distance = distance;
+                self.prev_angle = angle;
+            }
+            _ => {}
+        }
+    }
+
+    /// 本帧双指距离的缩放比例（本帧距离 / 上一帧距离），只在恰好两根手指按下时有值
+    pub fn pinch_delta(&self) -> Option<f32> {
+        self.pinch_delta
+    }
+
+    /// 双指的中点，只在恰好两根手指按下时有值
+    pub fn pinch_center(&self) -> Option<(f32, f32)> {
+        self.pinch_center.map(|c| (c.x, c.y))
+    }
+
+    /// 本帧单指拖动的位移（像素），只在恰好一根手指按下时有值
+    pub fn pan_delta(&self) -> Option<(f32, f32)> {
+        self.pan_delta.map(|d| (d.x, d.y))
+    }
+
+    /// 本帧双指连线转过的角度（弧度，范围 [-π, π]），窗口坐标 y 轴向下，正值为屏幕上的顺时针
+    pub fn two_finger_rotation_delta(&self) -> Option<f32> {
+        self.rotation_delta
+    }
+}
+
+// 把角度差折回 [-π, π]，避免跨过 ±π 时出现接近 2π 的跳变
+fn wrap_angle(angle: f32) -> f32 {
+    (angle + PI).rem_euclid(2.0 * PI) - PI
+}
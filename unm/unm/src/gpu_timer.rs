@@ -0,0 +1,183 @@
+use std::{
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use wgpu::{
+    Buffer, BufferDescriptor, BufferUsages, CommandEncoder, Device, Features, MapMode, PollType,
+    QuerySet, QuerySetDescriptor, QueryType, Queue, RenderPassTimestampWrites, QUERY_SIZE,
+};
+
+// 回读缓冲区的状态
+const SLOT_IDLE: u8 = 0;
+const SLOT_RESOLVED: u8 = 1;
+const SLOT_MAPPING: u8 = 2;
+const SLOT_MAPPED: u8 = 3;
+
+struct ReadbackSlot {
+    buffer: Buffer,
+    query_count: u32,
+    state: Arc<AtomicU8>,
+}
+
+/// 基于 timestamp query 的 GPU 计时器。
+/// 每个 RenderPass 写入开始/结束时间戳，帧末统一 resolve 到 MAP_READ 缓冲区，
+/// 结果会延迟一到两帧可读。
+pub(crate) struct GpuTimer {
+    query_set: QuerySet,
+    resolve_buffer: Buffer,
+    slots: Vec<ReadbackSlot>,
+    current_slot: usize,
+    next_query: u32,
+    // 每个时间戳刻度对应的纳秒数
+    period: f32,
+    last_gpu_time: Option<Duration>,
+}
+
+impl GpuTimer {
+    /// 每帧最多记录的时间戳数量（两个时间戳对应一个 RenderPass）
+    const MAX_QUERIES: u32 = 128;
+    /// 回读缓冲区数量，保证 GPU 仍在使用旧缓冲区时新的一帧也有地方写
+    const READBACK_SLOTS: usize = 3;
+
+    /// 设备不支持 `TIMESTAMP_QUERY` 时返回 `None`（例如许多 Android 驱动）。
+    pub(crate) fn new(device: &Device, queue: &Queue) -> Option<Self> {
+        if !device.features().contains(Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+
+        let query_set = device.create_query_set(&QuerySetDescriptor {
+            label: Some("GPU Timer Query Set"),
+            ty: QueryType::Timestamp,
+            count: Self::MAX_QUERIES,
+        });
+
+        let buffer_size = (Self::MAX_QUERIES * QUERY_SIZE) as u64;
+        let resolve_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("GPU Timer Resolve Buffer"),
+            size: buffer_size,
+            usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let slots = (0..Self::READBACK_SLOTS)
+            .map(|_| ReadbackSlot {
+                buffer: device.create_buffer(&BufferDescriptor {
+                    label: Some("GPU Timer Readback Buffer"),
+                    size: buffer_size,
+                    usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+                    mapped_at_creation: false,
+                }),
+                query_count: 0,
+                state: Arc::new(AtomicU8::new(SLOT_IDLE)),
+            })
+            .collect();
+
+        Some(Self {
+            query_set,
+            resolve_buffer,
+            slots,
+            current_slot: 0,
+            next_query: 0,
+            period: queue.get_timestamp_period(),
+            last_gpu_time: None,
+        })
+    }
+
+    /// 为一个 RenderPass 分配开始/结束时间戳。本帧时间戳用尽时返回 `None`，该 Pass 不计时。
+    pub(crate) fn pass_timestamp_writes(&mut self) -> Option<RenderPassTimestampWrites<'_>> {
+        if self.next_query + 2 > Self::MAX_QUERIES {
+            return None;
+        }
+
+        let begin = self.next_query;
+        self.next_query += 2;
+
+        Some(RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(begin),
+            end_of_pass_write_index: Some(begin + 1),
+        })
+    }
+
+    /// 将本帧的时间戳 resolve 并复制到空闲的回读缓冲区。
+    /// 必须在本帧最后一个使用时间戳的 encoder 上调用。
+    pub(crate) fn resolve(&mut self, encoder: &mut CommandEncoder) {
+        let query_count = self.next_query;
+        self.next_query = 0;
+
+        if query_count == 0 {
+            return;
+        }
+
+        let slot = &mut self.slots[self.current_slot];
+        if slot.state.load(Ordering::Acquire) != SLOT_IDLE {
+            // 回读缓冲区仍被占用，丢弃本帧数据
+            return;
+        }
+
+        encoder.resolve_query_set(&self.query_set, 0..query_count, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &slot.buffer,
+            0,
+            (query_count * QUERY_SIZE) as u64,
+        );
+
+        slot.query_count = query_count;
+        slot.state.store(SLOT_RESOLVED, Ordering::Release);
+    }
+
+    /// 在包含 `resolve` 的 encoder 提交之后调用，开始异步映射回读缓冲区。
+    pub(crate) fn map_resolved(&mut self) {
+        let slot = &self.slots[self.current_slot];
+        if slot.state.load(Ordering::Acquire) != SLOT_RESOLVED {
+            return;
+        }
+
+        slot.state.store(SLOT_MAPPING, Ordering::Release);
+        let state = slot.state.clone();
+        slot.buffer.map_async(MapMode::Read, .., move |result| {
+            let new_state = if result.is_ok() { SLOT_MAPPED } else { SLOT_IDLE };
+            state.store(new_state, Ordering::Release);
+        });
+
+        self.current_slot = (self.current_slot + 1) % self.slots.len();
+    }
+
+    /// 非阻塞地检查已完成映射的回读缓冲区，并更新最近一次的 GPU 帧时间。
+    pub(crate) fn poll(&mut self, device: &Device) {
+        let _ = device.poll(PollType::Poll);
+
+        for slot in &mut self.slots {
+            if slot.state.load(Ordering::Acquire) != SLOT_MAPPED {
+                continue;
+            }
+
+            let bytes = (slot.query_count * QUERY_SIZE) as u64;
+            let ticks: u64 = {
+                let view = slot.buffer.get_mapped_range(..bytes);
+                let timestamps: &[u64] = bytemuck::cast_slice(&view);
+
+                // 累加每个 Pass 的 (结束 - 开始)
+                timestamps
+                    .chunks_exact(2)
+                    .map(|pair| pair[1].saturating_sub(pair[0]))
+                    .sum()
+            };
+            slot.buffer.unmap();
+            slot.state.store(SLOT_IDLE, Ordering::Release);
+
+            let nanos = ticks as f64 * self.period as f64;
+            self.last_gpu_time = Some(Duration::from_nanos(nanos as u64));
+        }
+    }
+
+    pub(crate) fn gpu_frame_time(&self) -> Option<Duration> {
+        self.last_gpu_time
+    }
+}
@@ -1,40 +1,44 @@
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::{
+    collections::{HashMap, HashSet},
+    num::NonZeroU64,
+    time::Instant,
+};
 
+use ab_glyph::FontArc;
 use glam::{uvec2, vec2, vec3, Mat4, Quat, UVec2, Vec3};
-use image::GenericImageView;
 use log::*;
 use unm_tools::id_map::IdMap;
 use wgpu::{
-    util::{self, DeviceExt},
     BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
-    BindGroupLayoutEntry, BindingType, Buffer, BufferBindingType, BufferUsages,
-    CommandEncoderDescriptor, Extent3d, IndexFormat, Origin3d, PipelineLayout, RenderPass,
-    SamplerDescriptor, ShaderStages, SurfaceError, TexelCopyTextureInfo, TextureDescriptor,
-    TextureDimension, TextureFormat, TextureUsages, TextureViewDescriptor,
+    BindGroupLayoutEntry, BindingType, Buffer, BufferBindingType, BufferUsages, ShaderStages,
+    SurfaceError, TextureFormat, TextureViewDescriptor,
 };
 use winit::{dpi::PhysicalSize, window::Window};
 
-#[allow(unused_imports)] // 暂时允许未使用的导入
 use crate::{
-    camera::{Camera, CameraUniform},
+    blit::{Blitter, Tonemapper},
+    camera::{Camera, CameraUniform, Rect},
+    debug_draw::DebugDraw,
+    compute::{ComputeDispatch, ComputeMaterial, ComputeMaterialHandle, StorageBufferHandle},
     draw_call::DrawCall,
-    game_settings::GameSettings,
-    material::{Material, MaterialDescriptor, MaterialHandle},
+    frame_stats::FrameStats,
+    game_settings::{GameSettings, DEFAULT_MAX_BATCH_INDICES, DEFAULT_MAX_BATCH_VERTICES},
+    gpu_timer::GpuTimer,
+    material::{Material, MaterialDescriptor, MaterialHandle, MaterialKind, PipelineKey},
     msaa::Msaa,
-    render_context::RenderContext,
-    render_target::{RenderTarget, RenderTargetHandle},
-    uniform::{Uniform, UniformDef},
-    utils::{BufferType, SizedBuffer},
-    vertex::Vertex,
-};
-use crate::{
-    draw_call, get_context, get_quad_context,
+    profile_scope,
     render_command::RenderCommand,
+    render_context::RenderContext,
+    render_target::{RenderTarget, RenderTargetDescriptor, RenderTargetHandle, DEPTH_STENCIL_FORMAT},
+    text::{Font, FontHandle, GlyphAtlas, GlyphAtlasHandle},
     texture::{Texture2D, Texture2DHandle},
-    vertex::calculate_object_center,
+    tools::ProfileScope,
+    uniform::UniformDef,
+    utils::{BufferType, SizedBuffer, SizedBufferRing},
+    vertex::{calculate_object_bounds, calculate_object_center, Vertex},
 };
 
-// 新增的 PassAction 枚举，用于指示渲染通道的加载行为
+/// 渲染通道的颜色加载方式，也用作渲染目标每帧的清除方式，见 `WgpuState::set_clear_action`
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum PassAction {
     Clear(wgpu::Color), // 使用给定颜色清除
@@ -50,36 +54,94 @@ impl PassAction {
     }
 }
 
+/// 全局顶点/索引缓冲区的数量，即 CPU 最多领先 GPU 的帧数
+const FRAMES_IN_FLIGHT: usize = 3;
+
+/// 开启 HDR 时默认渲染目标的颜色格式
+const HDR_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+/// 切换 MSAA 后每帧预先创建的材质管线数量
+const PIPELINE_WARMUP_PER_FRAME: usize = 4;
+
+/// 能存储超过 1.0 的颜色、不需要色调映射即可呈现的格式
+fn is_hdr_format(format: TextureFormat) -> bool {
+    matches!(format, TextureFormat::Rgba16Float | TextureFormat::Rgba32Float)
+}
+
 #[allow(dead_code)]
 pub struct WgpuState {
     pub(crate) size: PhysicalSize<u32>, // 这应该代表物理窗口的大小
     pub(crate) context: RenderContext,
 
-    global_vertex_buffer: SizedBuffer,
-    global_index_buffer: SizedBuffer,
+    // 每帧轮换使用，避免覆盖上一帧 GPU 仍在读取的数据
+    global_vertex_buffer: SizedBufferRing,
+    global_index_buffer: SizedBufferRing,
 
     batch_vertex_buffer: Vec<Vertex>,
     batch_index_buffer: Vec<u32>,
 
     camera_uniform: CameraUniform,
+    // 每个渲染目标占一个槽位，绘制前一次性写入，Pass 之间通过动态偏移切换
     camera_buffer: Buffer,
+    camera_buffer_slots: usize,
+    // 相邻槽位之间的字节间隔，满足 min_uniform_buffer_offset_alignment
+    camera_uniform_stride: usize,
+    // 上一次写入相机缓冲区的数据，内容不变时跳过 write_buffer
+    last_camera_upload: Vec<u8>,
     camera_bind_group: BindGroup,
     camera_bind_group_layout: BindGroupLayout,
 
     camera: Option<Box<dyn Camera + Send + Sync>>,
 
     default_render_target: RenderTargetHandle,
+    // `with_render_target` 期间强制使用的渲染目标，优先于相机的渲染目标
+    render_target_override: Option<RenderTargetHandle>,
+    // 各渲染目标下一个 RenderPass 的颜色加载方式，在下一次 draw() 中使用后移除，
+    // 没有记录的目标按 Load 处理
+    pending_pass_actions: HashMap<RenderTargetHandle, PassAction>,
+    // 本帧已经清除过深度和模板的渲染目标，只在每帧第一个使用该目标的 Pass 中清除（模板另外在显式的
+    // `PassAction::Clear` 时清除），因此 flush、set_camera 等拆分出的后续 Pass 仍保留之前写入的深度和模板遮罩
+    cleared_targets: HashSet<RenderTargetHandle>,
 
     basic_shapes_triangle_mat: MaterialHandle,
     basic_shapes_lines_mat: MaterialHandle,
     basic_shapes_points_mat: MaterialHandle,
+    // 调试线段使用的线段材质，关闭深度测试
+    debug_lines_mat: MaterialHandle,
+    debug_draw: DebugDraw,
+    // blit 未指定材质时使用的直接复制材质
+    post_process_copy_mat: MaterialHandle,
+    // 每帧呈现前依次作用于默认渲染目标的后处理材质，以及链中来回切换的两个中间目标
+    post_process_chain: Vec<MaterialHandle>,
+    post_process_targets: Option<[RenderTargetHandle; 2]>,
+    // 纹理 × 顶点颜色，用于文字等带纹理的四边形
+    pub(crate) textured_mat: MaterialHandle,
+    // 与 textured_mat 相同但使用叠加混合，用于粒子等发光效果
+    pub(crate) additive_mat: MaterialHandle,
+    // 带纹理的材质没有指定纹理时绑定的 1x1 白色纹理
+    white_texture: Texture2DHandle,
 
     msaa: Msaa,
+    // 切换 MSAA 后等待预先创建新管线的材质，每帧处理 PIPELINE_WARMUP_PER_FRAME 个
+    pipeline_warmup: Vec<MaterialHandle>,
+    // 默认渲染目标是否为 HDR 格式，以及呈现时使用的色调映射
+    hdr: bool,
+    tonemapper: Tonemapper,
 
     pub(crate) render_targets: IdMap<RenderTarget, RenderTargetHandle>,
     pub(crate) materials: IdMap<Material, MaterialHandle>,
     pub(crate) texture2ds: IdMap<Texture2D, Texture2DHandle>,
 
+    pub(crate) compute_materials: IdMap<ComputeMaterial, ComputeMaterialHandle>,
+    pub(crate) storage_buffers: IdMap<SizedBuffer, StorageBufferHandle>,
+    // dispatch_compute 排队的调度，录制到下一次绘制的 encoder 中，与绘制一起提交
+    pending_dispatches: Vec<ComputeDispatch>,
+
+    pub(crate) fonts: IdMap<Font, FontHandle>,
+    pub(crate) glyph_atlases: IdMap<GlyphAtlas, GlyphAtlasHandle>,
+    // 每个 (字体, 像素大小) 对应一个图集
+    glyph_atlas_lookup: HashMap<(FontHandle, u32), GlyphAtlasHandle>,
+    current_font: Option<FontHandle>,
+
     current_material: Option<MaterialHandle>,
 
     render_commands: Vec<RenderCommand>,
@@ -89,6 +151,21 @@ pub struct WgpuState {
 
     max_vertices: usize,
     max_indices: usize,
+    // 窗口的 DPI 缩放系数，以及默认投影是否以逻辑像素为单位
+    scale_factor: f64,
+    logical_pixel_projection: bool,
+    // 是否剔除相机可见范围之外的绘制命令
+    culling: bool,
+
+    // 当前帧正在累积的统计数据，以及上一帧完整的统计数据
+    frame_stats: FrameStats,
+    last_frame_stats: FrameStats,
+
+    // 设备不支持 TIMESTAMP_QUERY 时为 None
+    gpu_timer: Option<GpuTimer>,
+
+    // 默认渲染目标与 Surface 尺寸/格式不一致时用于呈现
+    pub(crate) blitter: Blitter,
 }
 
 impl WgpuState {
@@ -97,15 +174,32 @@ impl WgpuState {
         info!("Initializing WGPU for window size: {:?}", size);
 
         let context = RenderContext::new(window, size).await?;
+        Ok(Self::from_context(context, size))
+    }
+
+    /// 不创建窗口和 Surface 的渲染状态，所有绘制都输出到离屏的默认渲染目标，
+    /// 配合 `headless::run_frames` 和 `read_render_target` 可以在 CI（lavapipe/WARP）中对渲染结果做像素断言。
+    /// 默认资源（基础材质等）已经创建，可以直接绘制
+    pub async fn new_headless(size: UVec2) -> anyhow::Result<Self> {
+        let size = PhysicalSize::new(size.x.max(1), size.y.max(1));
+        info!("Initializing headless WGPU with size: {:?}", size);
+
+        let context = RenderContext::new_headless(size).await?;
+        let mut state = Self::from_context(context, size);
+        state.create_default_resources().await;
+        Ok(state)
+    }
 
+    fn from_context(context: RenderContext, size: PhysicalSize<u32>) -> Self {
         let camera_uniform = CameraUniform::new();
-        let camera_buffer = context
-            .device
-            .create_buffer_init(&util::BufferInitDescriptor {
-                label: Some("Camera Buffer"),
-                contents: bytemuck::cast_slice(&[camera_uniform]),
-                usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
-            });
+        let camera_uniform_stride = std::mem::size_of::<CameraUniform>().next_multiple_of(
+            context.device.limits().min_uniform_buffer_offset_alignment as usize,
+        );
+        let camera_buffer_slots = 1;
+        let camera_buffer = Self::create_camera_buffer(
+            &context.device,
+            camera_buffer_slots * camera_uniform_stride,
+        );
         let camera_bind_group_layout: wgpu::BindGroupLayout = context
             .device
             .create_bind_group_layout(&BindGroupLayoutDescriptor {
@@ -114,67 +208,90 @@ impl WgpuState {
                     visibility: ShaderStages::VERTEX,
                     ty: BindingType::Buffer {
                         ty: BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
+                        has_dynamic_offset: true,
+                        min_binding_size: NonZeroU64::new(
+                            std::mem::size_of::<CameraUniform>() as u64
+                        ),
                     },
                     count: None,
                 }],
                 label: Some("camera_bind_group_layout"),
             });
-        let camera_bind_group = context.device.create_bind_group(&BindGroupDescriptor {
-            layout: &camera_bind_group_layout,
-            entries: &[BindGroupEntry {
-                binding: 0,
-                resource: camera_buffer.as_entire_binding(),
-            }],
-            label: Some("camera_bind_group"),
-        });
-
-        let max_vertices: usize = 1024 * 1024;
-        let max_indices: usize = 1024 * 1024;
-
-        let vertex_buffer = SizedBuffer::new(
-            "Mesh Vertex Buffer",
+        let camera_bind_group = Self::create_camera_bind_group(
             &context.device,
-            max_vertices,
-            BufferType::Vertex,
+            &camera_bind_group_layout,
+            &camera_buffer,
         );
 
-        let index_buffer = SizedBuffer::new(
-            "Mesh Index Buffer",
-            &context.device,
-            max_indices,
-            BufferType::Index,
-        );
+        let gpu_timer = GpuTimer::new(&context.device, &context.queue);
+        if gpu_timer.is_none() {
+            info!("TIMESTAMP_QUERY not supported, GPU timing disabled.");
+        }
+
+        let blitter = Blitter::new(&context.device);
+
+        // 创建时还没有 GameSettings，先按默认上限创建，之后由 apply_batch_limits 按配置调整
+        let max_vertices: usize = DEFAULT_MAX_BATCH_VERTICES;
+        let max_indices: usize = DEFAULT_MAX_BATCH_INDICES;
 
-        Ok(Self {
+        let (vertex_buffer, index_buffer) =
+            Self::create_batch_buffers(&context.device, max_vertices, max_indices);
+
+        Self {
             context,
             size,
 
             global_vertex_buffer: vertex_buffer,
             global_index_buffer: index_buffer,
 
-            batch_vertex_buffer: Vec::with_capacity(max_vertices),
-            batch_index_buffer: Vec::with_capacity(max_indices),
+            // CPU 端缓冲按需增长，不预先分配上限大小的内存
+            batch_vertex_buffer: Vec::new(),
+            batch_index_buffer: Vec::new(),
 
             camera_uniform,
             camera_buffer,
+            camera_buffer_slots,
+            camera_uniform_stride,
+            last_camera_upload: Vec::new(),
             camera_bind_group,
             camera_bind_group_layout,
 
             camera: None,
 
             default_render_target: RenderTargetHandle::default(), // 将在 `create_default_rt` 中设置
+            render_target_override: None,
+            pending_pass_actions: HashMap::new(),
+            cleared_targets: HashSet::new(),
 
             msaa: Msaa::Off,
+            pipeline_warmup: Vec::new(),
+            hdr: false,
+            tonemapper: Tonemapper::default(),
 
             render_targets: IdMap::<RenderTarget, RenderTargetHandle>::new(),
             materials: IdMap::<Material, MaterialHandle>::new(),
             texture2ds: IdMap::<Texture2D, Texture2DHandle>::new(),
 
+            compute_materials: IdMap::<ComputeMaterial, ComputeMaterialHandle>::new(),
+            storage_buffers: IdMap::<SizedBuffer, StorageBufferHandle>::new(),
+            pending_dispatches: Vec::new(),
+
+            fonts: IdMap::<Font, FontHandle>::new(),
+            glyph_atlases: IdMap::<GlyphAtlas, GlyphAtlasHandle>::new(),
+            glyph_atlas_lookup: HashMap::new(),
+            current_font: None,
+
             basic_shapes_triangle_mat: MaterialHandle::default(),
             basic_shapes_lines_mat: MaterialHandle::default(),
             basic_shapes_points_mat: MaterialHandle::default(),
+            debug_lines_mat: MaterialHandle::default(),
+            debug_draw: DebugDraw::default(),
+            post_process_copy_mat: MaterialHandle::default(),
+            post_process_chain: Vec::new(),
+            post_process_targets: None,
+            textured_mat: MaterialHandle::default(),
+            additive_mat: MaterialHandle::default(),
+            white_texture: Texture2DHandle::default(),
             current_material: None,
 
             render_commands: Vec::with_capacity(200),
@@ -184,7 +301,17 @@ impl WgpuState {
 
             max_vertices,
             max_indices,
-        })
+            scale_factor: 1.0,
+            logical_pixel_projection: false,
+            culling: false,
+
+            frame_stats: FrameStats::default(),
+            last_frame_stats: FrameStats::default(),
+
+            gpu_timer,
+
+            blitter,
+        }
     }
 
     pub(crate) async fn create_default_resources(&mut self) {
@@ -192,7 +319,7 @@ impl WgpuState {
 
         let basic_shapes_shader_str = include_str!("shaders/BasicShapes.wgsl").to_string();
 
-        self.basic_shapes_triangle_mat = create_material(
+        self.basic_shapes_triangle_mat = self.create_material(
             "BasicShapes Triangle".to_owned(),
             basic_shapes_shader_str.clone(),
             MaterialDescriptor::triangle(),
@@ -203,7 +330,7 @@ impl WgpuState {
 
         self.current_material = Some(self.basic_shapes_triangle_mat);
 
-        self.basic_shapes_lines_mat = create_material(
+        self.basic_shapes_lines_mat = self.create_material(
             "BasicShapes Lines".to_owned(),
             basic_shapes_shader_str.clone(),
             MaterialDescriptor::lines(),
@@ -212,7 +339,7 @@ impl WgpuState {
         .await
         .unwrap_or_default();
 
-        self.basic_shapes_points_mat = create_material(
+        self.basic_shapes_points_mat = self.create_material(
             "BasicShapes Points".to_owned(), // 修正标签
             basic_shapes_shader_str.clone(),
             MaterialDescriptor::lines(), // 如果你有 Points 专用的 MaterialDescriptor，请用它
@@ -220,6 +347,53 @@ impl WgpuState {
         )
         .await
         .unwrap_or_default();
+
+        self.debug_lines_mat = self.create_material(
+            "Debug Lines".to_owned(),
+            basic_shapes_shader_str.clone(),
+            MaterialDescriptor::lines()
+                .with_depth_compare(wgpu::CompareFunction::Always)
+                .with_depth_write(false),
+            None,
+        )
+        .await
+        .unwrap_or_default();
+
+        self.post_process_copy_mat = self.create_post_process_material(
+            "PostProcess Copy".to_owned(),
+            include_str!("shaders/Blit.wgsl").to_string(),
+            None,
+        )
+        .await
+        .unwrap_or_default();
+
+        self.textured_mat = self.create_textured_material(
+            "Textured".to_owned(),
+            include_str!("shaders/Textured.wgsl").to_string(),
+            MaterialDescriptor::triangle(),
+            None,
+        )
+        .await
+        .unwrap_or_default();
+
+        self.additive_mat = self.create_textured_material(
+            "Textured Additive".to_owned(),
+            include_str!("shaders/Textured.wgsl").to_string(),
+            MaterialDescriptor::additive(),
+            None,
+        )
+        .await
+        .unwrap_or_default();
+
+        let white_texture = Texture2D::new_empty(
+            &self.context,
+            self.blitter.bind_group_layout(),
+            uvec2(1, 1),
+            TextureFormat::Rgba8UnormSrgb,
+            Some("White Texture"),
+        );
+        white_texture.write(&self.context, UVec2::ZERO, uvec2(1, 1), &[255; 4]);
+        self.white_texture = self.texture2ds.insert(white_texture);
     }
 
     // 窗口大小改变时调用
@@ -237,6 +411,24 @@ impl WgpuState {
         self.create_default_rt();
     }
 
+    /// 应用进入后台：销毁 Surface（Android 会销毁原生窗口），纹理、材质等资源保留
+    pub(crate) fn suspend(&mut self) {
+        self.context.destroy_surface();
+    }
+
+    /// 应用回到前台：重新创建 Surface，并按新的窗口大小和 Surface 格式重建默认渲染目标
+    pub(crate) fn resume(&mut self, window: &'static Window) -> Option<PhysicalSize<u32>> {
+        let old_format = self.context.surface_view_format();
+        let size = self.context.resume(window)?;
+
+        self.size = size;
+        if !self.hdr && self.context.surface_view_format() != old_format {
+            self.set_default_rt_hdr(false);
+        }
+        self.create_default_rt();
+        Some(size)
+    }
+
     pub fn screen_width(&self) -> f32 {
         self.size.width as f32
     }
@@ -244,6 +436,42 @@ impl WgpuState {
     pub fn screen_height(&self) -> f32 {
         self.size.height as f32
     }
+
+    /// 获取上一帧的渲染统计数据（DrawCall、顶点数、合批数等）。
+    pub fn last_frame_stats(&self) -> FrameStats {
+        self.last_frame_stats
+    }
+
+    /// 当前使用的显卡信息（名称、厂商、后端、集成/独立显卡等），可据此调整画质。
+    pub fn adapter_info(&self) -> wgpu::AdapterInfo {
+        self.context.adapter.get_info()
+    }
+
+    /// 设备实际生效的限制，例如 `max_texture_dimension_2d`。
+    pub fn device_limits(&self) -> wgpu::Limits {
+        self.context.device.limits()
+    }
+
+    /// Surface 颜色格式和深度/模板格式都支持的 MSAA 采样数，至少包含 `Msaa::Off`。
+    pub fn supported_sample_counts(&self) -> Vec<Msaa> {
+        let color = self.context.adapter.get_texture_format_features(self.context.surface_view_format());
+        let depth = self.context.adapter.get_texture_format_features(DEPTH_STENCIL_FORMAT);
+        Msaa::ALL
+            .into_iter()
+            .filter(|&msaa| {
+                msaa == Msaa::Off
+                    || (color.flags.sample_count_supported(msaa.into())
+                        && depth.flags.sample_count_supported(msaa.into()))
+            })
+            .collect()
+    }
+
+    /// 获取最近一次测得的 GPU 帧耗时（所有 RenderPass 耗时之和，包括呈现时缩放/色调映射的全屏 Pass，延迟一到两帧）。
+    /// 呈现时尺寸和格式一致、直接复制到 Surface 的那一步不是 RenderPass，不计入。
+    /// 设备不支持 timestamp query 时返回 `None`。
+    pub fn gpu_frame_time(&self) -> Option<std::time::Duration> {
+        self.gpu_timer.as_ref().and_then(|timer| timer.gpu_frame_time())
+    }
 }
 
 // RT 部分
@@ -252,292 +480,1193 @@ impl WgpuState {
         let current_size = uvec2(self.size.width, self.size.height);
         if let Some(rt) = self.render_targets.get_mut(self.default_render_target) {
             rt.rebuild_with_size_and_msaa(&self.context, current_size, self.msaa);
+            // 重建后的深度/模板纹理没有初始化，下一个 Pass 需要重新清除
+            self.cleared_targets.remove(&self.default_render_target);
         } else {
             self.default_render_target = self.create_render_target(current_size);
+            self.set_clear_action(self.default_render_target, PassAction::Clear(wgpu::Color::BLACK));
+        }
+    }
+
+    /// 设置渲染目标每帧的清除方式，在该目标本帧的第一个 RenderPass 中执行。
+    ///
+    /// `PassAction::Clear` 每帧开始时清除为指定颜色，`PassAction::Load` 保留上一帧的内容（拖尾、累积效果）。
+    /// 离屏渲染目标默认为 `Load`；默认渲染目标默认清除，颜色始终使用 `GameSettings::set_clear_color`，
+    /// 设置为 `Load` 后不再清除。`clear_background` 仍可在帧中途额外清除当前渲染目标
+    pub fn set_clear_action(&mut self, render_target: RenderTargetHandle, action: PassAction) -> bool {
+        match self.render_targets.get_mut(render_target) {
+            Some(rt) => {
+                rt.clear_action = action;
+                true
+            }
+            None => {
+                error!("RenderTarget not found. ID: {}", render_target);
+                false
+            }
         }
     }
 
+    pub fn get_clear_action(&self, render_target: RenderTargetHandle) -> Option<PassAction> {
+        self.render_targets.get(render_target).map(|rt| rt.clear_action)
+    }
+
+    /// 默认渲染目标，每帧最终呈现到窗口，无窗口模式下可用 `read_render_target` 读回
+    pub fn get_default_render_target(&self) -> RenderTargetHandle {
+        self.default_render_target
+    }
+
+    /// 创建 Surface 颜色格式、带深度/模板附件的离屏渲染目标。
+    /// 需要 HDR 等其他颜色格式时使用 `create_render_target_ex` 或 `create_render_target_with`。
     pub fn create_render_target(&mut self, size: UVec2) -> RenderTargetHandle {
+        let size = self.clamp_render_target_size(size);
         self.render_targets
             .insert(RenderTarget::new(&self.context, size, self.msaa))
     }
 
-    pub(crate) fn get_active_render_target(&self) -> RenderTargetHandle {
-        self.camera
-            .as_ref()
-            .and_then(|cam| cam.get_render_target())
-            .unwrap_or(self.default_render_target)
+    // 超出设备限制的尺寸会被截断到 max_texture_dimension_2d，并打印错误
+    fn clamp_render_target_size(&self, size: UVec2) -> UVec2 {
+        if let Err(e) = RenderTarget::validate_size(&self.context, size) {
+            error!("Render target size clamped: {}", e);
+            return size.min(UVec2::splat(self.context.device.limits().max_texture_dimension_2d));
+        }
+        size
     }
-}
-
-// Camera 部分
-impl WgpuState {
-    #[rustfmt::skip]
-    fn pixel_perfect_projection_matrix(&self, size: UVec2) -> Mat4 {
-        // 假设 size 是窗口的物理尺寸 (例如 1280, 720)
-        let half_width = size.x as f32 / 2.0;
-        let half_height = size.y as f32 / 2.0;
-
-        let up_vec         = Vec3::Y;     // Y 轴向上为正
-        let camera_pos     = Vec3::ZERO;  // 相机位于窗口中心，Z=0
-        let look_direction = Vec3::NEG_Z; // 相机看向负 Z 轴 (即看向屏幕内部)
 
-        let view = Mat4::look_at_rh(camera_pos, camera_pos + look_direction, up_vec);
+    // 以 HDR 或 Surface 格式重建默认渲染目标，句柄保持不变
+    fn set_default_rt_hdr(&mut self, hdr: bool) {
+        let format = if hdr { HDR_FORMAT } else { self.context.surface_view_format() };
+        if let Err(e) = RenderTarget::validate_format(&self.context, format, self.msaa) {
+            error!("Failed to switch HDR rendering: {}", e);
+            return;
+        }
 
-        // 正交投影的边界
-        // left/right/bottom/top 定义了视图体的范围
-        // 以 (0,0) 为中心，X轴从 -half_width 到 half_width
-        // Y轴从 -half_height 到 half_height (向上为正)
-        let left   = -half_width;
-        let right  =  half_width;
-        let bottom = -half_height; // Y 轴负方向，屏幕下半部分
-        let top    =  half_height; // Y 轴正方向，屏幕上半部分
+        if let Some(rt) = self.render_targets.get_mut(self.default_render_target) {
+            let size = uvec2(rt.size.width, rt.size.height);
+            let depth_format = rt.depth_format;
+            let clear_action = rt.clear_action;
+            rt.destroy();
+            *rt = RenderTarget::new_ex(&self.context, size, self.msaa, format, depth_format);
+            rt.clear_action = clear_action;
+            self.hdr = hdr;
+            self.cleared_targets.remove(&self.default_render_target);
+        }
+    }
 
-        let near = -100.0; // 近裁剪平面，距离相机 -100.0 单位
-        let far  =  100.0; // 远裁剪平面，距离相机  100.0 单位
+    /// 创建指定颜色格式的离屏渲染目标，例如 `Rgba16Float` 用于 HDR 累积、`R8Unorm` 用于遮罩。
+    ///
+    /// `with_depth` 为 false 时不创建深度/模板附件，为 true 时使用 `Depth24PlusStencil8`，
+    /// 相当于 `create_render_target_with` 的简写。
+    pub fn create_render_target_ex(
+        &mut self,
+        size: UVec2,
+        format: TextureFormat,
+        with_depth: bool,
+    ) -> Option<RenderTargetHandle> {
+        self.create_render_target_with(
+            RenderTargetDescriptor::new(size)
+                .with_format(format)
+                .with_depth(with_depth.then_some(DEPTH_STENCIL_FORMAT)),
+        )
+    }
 
-        let proj = Mat4::orthographic_rh(
-            left,
-            right,
-            bottom,
-            top,
-            near,
-            far,
-        );
+    /// 按描述创建离屏渲染目标，可以指定颜色格式、固定 MSAA 采样数和深度/模板格式。
+    ///
+    /// `depth` 为 `None` 时不创建深度附件，渲染到该目标的材质会使用不带深度测试的管线；
+    /// 显式开启深度写入（`with_depth_write(true)`）的材质无法绘制到该目标，
+    /// 开启模板测试的材质只能绘制到带模板的深度格式（如 `Depth24PlusStencil8`），否则会打印错误。
+    /// 固定了 `msaa` 的目标在全局 MSAA 改变时不会重建。
+    /// 格式不可用、不支持所用的采样数或尺寸超出设备 `max_texture_dimension_2d` 时返回 `None`。
+    pub fn create_render_target_with(&mut self, descriptor: RenderTargetDescriptor) -> Option<RenderTargetHandle> {
+        let format = descriptor.format.unwrap_or(self.context.surface_view_format());
+        let sample_count = descriptor.msaa.unwrap_or(self.msaa);
+        let result = RenderTarget::validate_format(&self.context, format, sample_count)
+            .and_then(|_| match descriptor.depth {
+                Some(depth) => RenderTarget::validate_depth_format(&self.context, depth, sample_count),
+                None => Ok(()),
+            })
+            .and_then(|_| RenderTarget::validate_size(&self.context, descriptor.size));
+        if let Err(e) = result {
+            error!("Failed to create render target: {}", e);
+            return None;
+        }
 
-        proj * view // 乘以 view 矩阵以创建最终的 ViewProjection 矩阵。
+        Some(self.render_targets.insert(RenderTarget::from_descriptor(
+            &self.context,
+            &descriptor,
+            self.msaa,
+        )))
     }
 
-    pub fn set_camera<C>(&mut self, new_camera: Option<C>)
-    where
-        C: Camera + Send + Sync + 'static,
-    {
+    /// 删除渲染目标并立即释放其纹理。默认渲染目标不可删除。
+    ///
+    /// 删除前会 flush 当前批次；仍指向该目标的相机会改回默认渲染目标。
+    pub fn delete_render_target(&mut self, render_target: RenderTargetHandle) -> bool {
+        if render_target == self.default_render_target {
+            error!("The default RenderTarget cannot be deleted.");
+            return false;
+        }
+        if self.render_targets.get(render_target).is_none() {
+            error!("RenderTarget not found. ID: {}", render_target);
+            return false;
+        }
+
         self.draw();
 
-        self.camera =
-            new_camera.map(|cam| Box::new(cam) as Box<dyn Camera + Send + Sync + 'static>);
-    }
-}
+        if let Some(rt) = self.render_targets.remove(render_target) {
+            rt.destroy();
+        }
+        self.pending_pass_actions.remove(&render_target);
 
-// Material 部分
-pub async fn create_material(
-    name: String,
-    shader_str: String,
-    material_descriptor: MaterialDescriptor,
-    uniform_defs: Option<HashMap<String, UniformDef>>,
-) -> Option<MaterialHandle> {
-    let ctx = get_quad_context();
-    match Material::new(
-        &ctx.context,
-        &ctx.camera_bind_group_layout,
-        ctx.msaa,
-        name,
-        shader_str,
-        material_descriptor,
-        uniform_defs,
-    )
-    .await
-    {
-        Ok(new_mat) => Some(ctx.materials.insert(new_mat)),
-        Err(err) => {
-            error!("material create error: {}", err);
-            None
+        if let Some(camera) = self.camera.as_mut() {
+            if camera.get_render_target() == Some(render_target) {
+                warn!("Camera RenderTarget {} deleted, falling back to the default target.", render_target);
+                camera.set_render_target(None);
+            }
         }
+        true
     }
-}
 
-pub fn set_material(new_mat: MaterialHandle) {
-    let ctx = get_quad_context();
-    if let Some(current_mat_handle) = ctx.current_material {
-        if current_mat_handle == new_mat {
-            return;
+    /// 修改渲染目标的尺寸，纹理会按当前 MSAA 设置重建。
+    /// 默认渲染目标跟随窗口大小，不能手动修改。
+    pub fn resize_render_target(&mut self, render_target: RenderTargetHandle, new_size: UVec2) -> bool {
+        if render_target == self.default_render_target {
+            error!("The default RenderTarget follows the window size and cannot be resized.");
+            return false;
+        }
+        if self.render_targets.get(render_target).is_none() {
+            error!("RenderTarget not found. ID: {}", render_target);
+            return false;
+        }
+        if let Err(e) = RenderTarget::validate_size(&self.context, new_size) {
+            error!("Failed to resize render target: {}", e);
+            return false;
         }
-    }
 
-    ctx.break_batching = true;
-    ctx.current_material = Some(new_mat);
-}
+        // 之前的绘制仍使用旧尺寸的纹理
+        self.draw();
 
-// Renderer
-impl WgpuState {
-    // 渲染逻辑 - 这个方法现在只负责呈现最终结果，不再进行实际绘制。
-    // 它应该只处理默认渲染目标的解析和呈现。
-    pub(crate) fn render(&mut self) -> Result<(), SurfaceError> {
-        let context = &self.context;
-        if context.surface.is_none() {
-            return Err(wgpu::SurfaceError::Lost);
+        if let Some(rt) = self.render_targets.get_mut(render_target) {
+            rt.rebuild_with_size_and_msaa(&self.context, new_size.max(UVec2::ONE), self.msaa);
         }
+        self.cleared_targets.remove(&render_target);
+        true
+    }
 
-        let output = context.surface.as_ref().unwrap().get_current_texture()?;
-
-        if let Some(rt) = self.render_targets.get(self.default_render_target) {
-            let mut encoder =
-                context
-                    .device
-                    .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                        label: Some("Final Render Encoder (Resolve & Present)"),
-                    });
+    /// 以全屏三角形把 `src` 的内容经过后处理材质绘制到 `dst`（`None` 为默认渲染目标）。
+    ///
+    /// `material` 为 `None` 时直接复制，若 `src` 是 HDR 格式而 `dst` 不是，复制时按 `set_tonemapper`
+    /// 选择的曲线做色调映射（与呈现到 Surface 时一致）；材质需由 `create_post_process_material` 创建，
+    /// 其用户 Uniform（如暗角强度、模糊半径）会在绘制前上传。调用前会 flush 当前批次，
+    /// 因此 blit 与普通绘制按调用顺序执行，同一帧内可以在两个 RT 之间来回 blit。
+    pub fn blit(
+        &mut self,
+        src: RenderTargetHandle,
+        dst: Option<RenderTargetHandle>,
+        material: Option<MaterialHandle>,
+    ) {
+        let dst = dst.unwrap_or(self.default_render_target);
+        let mat_handle = material.unwrap_or(self.post_process_copy_mat);
 
-            if let Some(msaa_view) = &rt.msaa_texture_view {
-                let _resolve_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                    label: Some("DefaultRT Msaa Resolve Render Pass"),
-                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                        view: msaa_view,
-                        resolve_target: Some(&rt.resolve_texture_view),
-                        ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Load,
-                            store: wgpu::StoreOp::Store,
-                        },
-                        depth_slice: None,
-                    })],
-                    depth_stencil_attachment: None,
-                    timestamp_writes: None,
-                    occlusion_query_set: None,
-                    multiview_mask: None,
-                });
+        if src == dst {
+            error!("Blit source and destination must be different RenderTargets. ID: {}", src);
+            return;
+        }
+        if self.render_targets.get(src).is_none() || self.render_targets.get(dst).is_none() {
+            error!("Blit RenderTarget not found. src: {}, dst: {}", src, dst);
+            return;
+        }
+        match self.materials.get(mat_handle) {
+            Some(mat) if mat.kind == MaterialKind::PostProcess => {}
+            _ => {
+                error!("Blit requires a post-process material.");
+                return;
             }
+        }
 
-            encoder.copy_texture_to_texture(
-                wgpu::TexelCopyTextureInfo {
-                    texture: &rt.resolve_texture,
-                    mip_level: 0,
-                    origin: wgpu::Origin3d::ZERO,
-                    aspect: wgpu::TextureAspect::All,
-                },
-                wgpu::TexelCopyTextureInfo {
-                    texture: &output.texture,
-                    mip_level: 0,
-                    origin: wgpu::Origin3d::ZERO,
-                    aspect: wgpu::TextureAspect::All,
-                },
-                rt.size.into(),
-            );
+        // 保证之前记录的绘制先于本次 blit 执行
+        self.draw();
 
-            context.queue.submit(std::iter::once(encoder.finish()));
-        }
+        // 整个目标都会被覆盖，之前请求的清除不再需要
+        self.pending_pass_actions.remove(&dst);
 
-        // 呈现 SurfaceTexture
-        output.present();
-        Ok(())
+        self.fullscreen_pass(Some(src), dst, mat_handle, material.is_none(), PassAction::Load);
     }
 
-    pub(crate) fn clear_draw_calls(&mut self) {
-        self.draw_calls.clear();
-    }
+    /// 以覆盖整个渲染目标的三角形把后处理材质绘制到当前活动的渲染目标，不使用相机矩阵，
+    /// 适合天空、背景渐变等全屏效果。三角形顶点由着色器根据 `vertex_index` 生成，不需要顶点缓冲区。
+    ///
+    /// 材质需由 `create_post_process_material` 创建，`@group(0)` 的源纹理此时为 1x1 白色纹理。
+    /// 调用前会 flush 当前批次，绘制结果按调用顺序位于之前的绘制之上。
+    pub fn draw_fullscreen(&mut self, material: MaterialHandle) {
+        match self.materials.get(material) {
+            Some(mat) if mat.kind == MaterialKind::PostProcess => {}
+            _ => {
+                error!("draw_fullscreen requires a post-process material.");
+                return;
+            }
+        }
 
-    pub(crate) fn reset(&mut self) {
-        self.clear_draw_calls();
-    }
+        self.draw();
 
-    pub(crate) fn prepare_for_new_frame(&mut self) {
-        self.reset();
-        self.clear_background(wgpu::Color::BLACK);
+        let dst = self.get_active_render_target();
+        // 本帧还没有绘制时，clear_background 请求的清除在这个 Pass 里完成
+        let load = self.pending_pass_actions.remove(&dst).unwrap_or(PassAction::Load);
+        self.fullscreen_pass(None, dst, material, false, load);
     }
 
-    pub(crate) fn end_frame(&mut self, game_settings: &mut GameSettings) {
-        // ... MSAA 更改处理 ...
-        if let Some(new_msaa) = game_settings.new_msaa {
-            if self.msaa == new_msaa {
-                game_settings.new_msaa = None; // 已经相同，无需操作
+    // 以全屏三角形执行后处理材质，`source` 为 None 时第 0 组绑定白色纹理。
+    // `tonemap` 为 true 时若源为 HDR、目标不是，改用色调映射管线代替材质
+    fn fullscreen_pass(
+        &mut self,
+        source: Option<RenderTargetHandle>,
+        dst: RenderTargetHandle,
+        mat_handle: MaterialHandle,
+        tonemap: bool,
+        load: PassAction,
+    ) {
+        let (Some(dst_rt), Some(mat)) = (
+            self.render_targets.get(dst),
+            self.materials.get_mut(mat_handle),
+        ) else {
+            return;
+        };
+        let src_rt = source.and_then(|src| self.render_targets.get(src));
+
+        // HDR 内容写入非 HDR 目标需要色调映射
+        let tonemapper = (tonemap
+            && src_rt.is_some_and(|rt| is_hdr_format(rt.format))
+            && !is_hdr_format(dst_rt.format))
+        .then_some(self.tonemapper);
+
+        let key = (dst_rt.sample_count, dst_rt.format, None);
+        if tonemapper.is_none() {
+            if !mat.ensure_pipeline(&self.context, key) {
                 return;
             }
+            if let Err(e) = mat.update_user_uniforms(&self.context) {
+                error!("Failed to update uniforms of material '{}': {}", mat.name, e);
+            }
+        }
 
-            self.msaa = new_msaa;
-            game_settings.msaa = new_msaa; // 保存新的 MSAA 设置
-
-            // 使用新的 MSAA 设置重新创建所有渲染目标
-            self.render_targets.iter_mut().for_each(|(_, rt_ref)| {
-                rt_ref.re_create(&self.context, self.msaa);
-            });
+        let source_bind_group = match src_rt {
+            Some(rt) => self.blitter.source_bind_group(&self.context.device, &rt.resolve_texture_view),
+            None => match self.texture2ds.get(self.white_texture) {
+                Some(texture) => texture.bind_group.clone(),
+                None => return,
+            },
+        };
 
-            // 使用新的 MSAA 设置重建所有材质的管线
-            self.materials.iter_mut().for_each(|(_, mat_ref)| {
-                mat_ref.rebuild_pipeline(&self.context, &self.camera_bind_group_layout, self.msaa);
-            });
-        }
+        let pipeline = match tonemapper {
+            Some(tonemapper) => Some(self.blitter.pipeline(
+                &self.context.device,
+                dst_rt.format,
+                Some(tonemapper),
+                dst_rt.sample_count.into(),
+            )),
+            None => mat.pipeline_for(&key),
+        };
 
-        game_settings.new_msaa = None;
-    }
+        let (view, resolve) = if let Some(msaa_view) = dst_rt.msaa_texture_view.as_ref() {
+            (msaa_view, Some(&dst_rt.resolve_texture_view))
+        } else {
+            (&dst_rt.resolve_texture_view, None)
+        };
 
-    pub fn clear_background(&mut self, color: wgpu::Color) {
         let mut encoder =
             self.context
                 .device
                 .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                    label: Some("Clear Background Encoder"),
+                    label: Some("Blit Encoder"),
                 });
         {
-            // 获取渲染目标实例。
-            let render_target = self
-                .render_targets
-                .get(self.get_active_render_target())
-                .expect("RenderTarget not found for handle");
-
-            // 确定用于渲染的视图和解析视图。
-            let (view_to_render_to, resolve_target_view) =
-                if render_target.msaa_texture_view.is_some() {
-                    (
-                        render_target.msaa_texture_view.as_ref().unwrap(),
-                        Some(&render_target.resolve_texture_view),
-                    )
-                } else {
-                    (&render_target.resolve_texture_view, None)
-                };
-
-            // 配置深度/模板附件
-            let depth_stencil_attachment =
-                render_target.depth_texture_view.as_ref().map(|depth_view| {
-                    wgpu::RenderPassDepthStencilAttachment {
-                        view: depth_view,
-                        depth_ops: Some(wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(1.0), // 清除深度到 1.0 (最远)
-                            store: wgpu::StoreOp::Store,
-                        }),
-                        stencil_ops: None, // 如果需要，配置模板
-                    }
-                });
-
-            // 创建 `wgpu::RenderPass`
-            encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Active Render Pass"),
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Post Process Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: view_to_render_to,
-                    resolve_target: resolve_target_view,
+                    view,
+                    resolve_target: resolve,
                     ops: wgpu::Operations {
-                        load: PassAction::Clear(color).load_op(),
+                        load: load.load_op(),
                         store: wgpu::StoreOp::Store,
                     },
                     depth_slice: None,
                 })],
-                depth_stencil_attachment, //depth_stencil_attachment_desc,
-                timestamp_writes: None,
-                occlusion_query_set: None,
-                multiview_mask: None,
+                timestamp_writes: self
+                    .gpu_timer
+                    .as_mut()
+                    .and_then(|timer| timer.pass_timestamp_writes()),
+                ..Default::default()
             });
+
+            if let Some(pipeline) = pipeline {
+                pass.set_pipeline(pipeline);
+                pass.set_bind_group(0, &source_bind_group, &[]);
+                if tonemapper.is_none() {
+                    if let Some(user_bind_group) = mat.user_uniform_bind_group.as_ref() {
+                        pass.set_bind_group(mat.user_uniform_group, user_bind_group, &[]);
+                    }
+                }
+                pass.draw(0..3, 0..1);
+            }
         }
         self.context.queue.submit(std::iter::once(encoder.finish()));
 
-        self.render_commands.clear();
+        self.frame_stats.draw_calls += 1;
+        self.frame_stats.render_target_switches += 1;
     }
 
-    pub(crate) fn draw(&mut self) {
-        self.geometry();
-
-        // 1. 全局数据上传（整帧一次）
+    /// 设置每帧呈现前作用于整个画面的后处理链，传入空列表关闭后处理。
+    ///
+    /// 每个材质（由 `create_post_process_material` 创建）读取上一个材质的输出，
+    /// 最后一个材质的结果写回默认渲染目标后呈现。中间结果在两个与默认渲染目标同尺寸、同格式的
+    /// 渲染目标之间来回切换，因此 HDR 内容会一直保留到呈现时的色调映射。
+    /// 列表中有材质不存在或不是后处理材质时不做修改并返回 false。
+    pub fn set_post_process(&mut self, materials: Vec<MaterialHandle>) -> bool {
+        for &mat_handle in &materials {
+            match self.materials.get(mat_handle) {
+                Some(mat) if mat.kind == MaterialKind::PostProcess => {}
+                _ => {
+                    error!("Post-process chain requires post-process materials. ID: {:?}", mat_handle);
+                    return false;
+                }
+            }
+        }
+
+        if materials.is_empty() {
+            self.destroy_post_process_targets();
+        }
+        self.post_process_chain = materials;
+        true
+    }
+
+    /// 在默认渲染目标上执行后处理链，在本帧所有绘制之后、呈现之前调用。
+    pub(crate) fn apply_post_process(&mut self) {
+        if self.post_process_chain.is_empty() {
+            return;
+        }
+        let Some(targets) = self.ensure_post_process_targets() else {
+            return;
+        };
+
+        let default = self.default_render_target;
+        let chain = self.post_process_chain.clone();
+        let mut src = default;
+        // 只有一个材质时先把画面复制出来，blit 的源和目标不能是同一个渲染目标
+        if chain.len() == 1 {
+            self.blit(default, Some(targets[0]), None);
+            src = targets[0];
+        }
+        for (i, &mat_handle) in chain.iter().enumerate() {
+            let dst = if i + 1 == chain.len() { default } else { targets[i % 2] };
+            self.blit(src, Some(dst), Some(mat_handle));
+            src = dst;
+        }
+    }
+
+    // 中间目标与默认渲染目标的尺寸或格式不一致时（窗口缩放、切换 HDR）重新创建
+    fn ensure_post_process_targets(&mut self) -> Option<[RenderTargetHandle; 2]> {
+        let default_rt = self.render_targets.get(self.default_render_target)?;
+        let size = uvec2(default_rt.size.width, default_rt.size.height);
+        let format = default_rt.format;
+
+        if let Some(targets) = self.post_process_targets {
+            let matches = targets.iter().all(|&handle| {
+                self.render_targets
+                    .get(handle)
+                    .is_some_and(|rt| rt.size == default_rt.size && rt.format == format)
+            });
+            if matches {
+                return Some(targets);
+            }
+            self.destroy_post_process_targets();
+        }
+
+        // 全屏 Pass 不需要深度和 MSAA
+        let descriptor = RenderTargetDescriptor::new(size)
+            .with_format(format)
+            .with_msaa(Msaa::Off)
+            .with_depth(None);
+        let targets = [
+            self.render_targets.insert(RenderTarget::from_descriptor(&self.context, &descriptor, self.msaa)),
+            self.render_targets.insert(RenderTarget::from_descriptor(&self.context, &descriptor, self.msaa)),
+        ];
+        self.post_process_targets = Some(targets);
+        Some(targets)
+    }
+
+    fn destroy_post_process_targets(&mut self) {
+        for handle in self.post_process_targets.take().into_iter().flatten() {
+            if let Some(rt) = self.render_targets.remove(handle) {
+                rt.destroy();
+            }
+        }
+    }
+
+    pub(crate) fn get_active_render_target(&mut self) -> RenderTargetHandle {
+        // 相机指向的渲染目标已被删除时，改回默认渲染目标（只警告一次）
+        if let Some(camera) = self.camera.as_mut() {
+            if let Some(rt) = camera.get_render_target() {
+                if self.render_targets.get(rt).is_none() {
+                    warn!("Camera RenderTarget {} not found, falling back to the default target.", rt);
+                    camera.set_render_target(None);
+                }
+            }
+        }
+
+        self.render_target_override
+            .or_else(|| self.camera.as_ref().and_then(|cam| cam.get_render_target()))
+            .unwrap_or(self.default_render_target)
+    }
+
+    /// 将 `draw_fn` 中的所有绘制渲染到 `render_target`，用于小地图、后处理等离屏 Pass。
+    ///
+    /// 调用前后都会 flush 当前批次，离屏内容不会与屏幕内容混在同一批次中。
+    /// `camera` 只在 `draw_fn` 期间生效，为 `None` 时使用与 RT 尺寸匹配的像素投影；
+    /// 结束后恢复之前的相机和渲染目标，此后同一帧内即可采样该 RT。
+    pub fn with_render_target<C, F>(
+        &mut self,
+        render_target: RenderTargetHandle,
+        camera: Option<C>,
+        draw_fn: F,
+    ) where
+        C: Camera + Send + Sync + 'static,
+        F: FnOnce(&mut WgpuState),
+    {
+        if self.render_targets.get(render_target).is_none() {
+            error!("RenderTarget not found. ID: {}", render_target);
+            return;
+        }
+
+        self.draw();
+
+        let prev_camera = self.camera.take();
+        let prev_override = self.render_target_override.replace(render_target);
+        self.camera = camera.map(|cam| Box::new(cam) as Box<dyn Camera + Send + Sync + 'static>);
+
+        draw_fn(self);
+        self.draw();
+
+        self.camera = prev_camera;
+        self.render_target_override = prev_override;
+    }
+}
+
+// Camera 部分
+impl WgpuState {
+    #[rustfmt::skip]
+    fn pixel_perfect_projection_matrix(&self, size: UVec2) -> Mat4 {
+        // 假设 size 是窗口的物理尺寸 (例如 1280, 720)，开启逻辑像素投影时换算为逻辑尺寸
+        let scale = if self.logical_pixel_projection { self.scale_factor.max(f64::EPSILON) as f32 } else { 1.0 };
+        let half_width = size.x as f32 / scale / 2.0;
+        let half_height = size.y as f32 / scale / 2.0;
+
+        let up_vec         = Vec3::Y;     // Y 轴向上为正
+        let camera_pos     = Vec3::ZERO;  // 相机位于窗口中心，Z=0
+        let look_direction = Vec3::NEG_Z; // 相机看向负 Z 轴 (即看向屏幕内部)
+
+        let view = Mat4::look_at_rh(camera_pos, camera_pos + look_direction, up_vec);
+
+        // 正交投影的边界
+        // left/right/bottom/top 定义了视图体的范围
+        // 以 (0,0) 为中心，X轴从 -half_width 到 half_width
+        // Y轴从 -half_height 到 half_height (向上为正)
+        let left   = -half_width;
+        let right  =  half_width;
+        let bottom = -half_height; // Y 轴负方向，屏幕下半部分
+        let top    =  half_height; // Y 轴正方向，屏幕上半部分
+
+        let near = -100.0; // 近裁剪平面，距离相机 -100.0 单位
+        let far  =  100.0; // 远裁剪平面，距离相机  100.0 单位
+
+        let proj = Mat4::orthographic_rh(
+            left,
+            right,
+            bottom,
+            top,
+            near,
+            far,
+        );
+
+        proj * view // 乘以 view 矩阵以创建最终的 ViewProjection 矩阵。
+    }
+
+    fn create_camera_buffer(device: &wgpu::Device, size: usize) -> Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Camera Buffer"),
+            size: size as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn create_camera_bind_group(
+        device: &wgpu::Device,
+        layout: &BindGroupLayout,
+        buffer: &Buffer,
+    ) -> BindGroup {
+        device.create_bind_group(&BindGroupDescriptor {
+            layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer,
+                    offset: 0,
+                    size: NonZeroU64::new(std::mem::size_of::<CameraUniform>() as u64),
+                }),
+            }],
+            label: Some("camera_bind_group"),
+        })
+    }
+
+    /// 在录制任何 Pass 之前，为本批次用到的每个渲染目标计算相机矩阵并一次性上传。
+    /// 返回每个渲染目标在相机缓冲区中的动态偏移。
+    fn upload_camera_uniforms(&mut self) -> HashMap<RenderTargetHandle, u32> {
+        let mut offsets = HashMap::new();
+        let mut data: Vec<u8> = Vec::new();
+
+        for dc in &self.draw_calls {
+            let rt_handle = dc.render_target;
+            if offsets.contains_key(&rt_handle) {
+                continue;
+            }
+            let Some(render_target) = self.render_targets.get(rt_handle) else {
+                continue;
+            };
+
+            // RT 尺寸不同，投影矩阵也可能不同
+            let rt_size = uvec2(render_target.size.width, render_target.size.height);
+            let proj = if let Some(camera) = self.camera.as_mut() {
+                camera.resize(rt_size);
+                camera.matrix()
+            } else {
+                self.pixel_perfect_projection_matrix(rt_size)
+            };
+            self.camera_uniform.update_matrix(proj);
+
+            offsets.insert(rt_handle, data.len() as u32);
+            data.extend_from_slice(bytemuck::bytes_of(&self.camera_uniform));
+            data.resize(data.len().next_multiple_of(self.camera_uniform_stride), 0);
+        }
+
+        if data.is_empty() || data == self.last_camera_upload {
+            return offsets;
+        }
+
+        // 槽位不够时扩容，并重建引用该缓冲区的绑定组
+        let slots = data.len() / self.camera_uniform_stride;
+        if slots > self.camera_buffer_slots {
+            self.camera_buffer_slots = slots.next_power_of_two();
+            self.camera_buffer.destroy();
+            self.camera_buffer = Self::create_camera_buffer(
+                &self.context.device,
+                self.camera_buffer_slots * self.camera_uniform_stride,
+            );
+            self.camera_bind_group = Self::create_camera_bind_group(
+                &self.context.device,
+                &self.camera_bind_group_layout,
+                &self.camera_buffer,
+            );
+        }
+
+        self.context.queue.write_buffer(&self.camera_buffer, 0, &data);
+        self.last_camera_upload = data;
+
+        offsets
+    }
+
+    /// 当前相机在世界空间 XY 平面上可见的范围（`(x, y)` 为左下角），用于剔除屏幕外的图形。
+    ///
+    /// 未设置相机时为以原点为中心、与渲染目标同尺寸的区域。
+    /// 透视相机返回整个视锥体在 XY 平面上的包围范围，结果偏保守。
+    pub fn camera_view_bounds(&mut self) -> Rect {
+        let rt_handle = self.get_active_render_target();
+        let rt_size = self
+            .render_targets
+            .get(rt_handle)
+            .map_or(UVec2::ONE, |rt| uvec2(rt.size.width, rt.size.height));
+
+        let view_proj = if let Some(camera) = self.camera.as_mut() {
+            camera.resize(rt_size);
+            camera.matrix()
+        } else {
+            self.pixel_perfect_projection_matrix(rt_size)
+        };
+
+        // 把裁剪空间的 8 个角反投影回世界空间，取 XY 的包围盒
+        let inverse = view_proj.inverse();
+        let (mut min, mut max) = (glam::Vec2::splat(f32::MAX), glam::Vec2::splat(f32::MIN));
+        for corner in [
+            Vec3::new(-1.0, -1.0, 0.0), Vec3::new(1.0, -1.0, 0.0),
+            Vec3::new(-1.0, 1.0, 0.0), Vec3::new(1.0, 1.0, 0.0),
+            Vec3::new(-1.0, -1.0, 1.0), Vec3::new(1.0, -1.0, 1.0),
+            Vec3::new(-1.0, 1.0, 1.0), Vec3::new(1.0, 1.0, 1.0),
+        ] {
+            let world = inverse.project_point3(corner).truncate();
+            min = min.min(world);
+            max = max.max(world);
+        }
+
+        Rect { x: min.x, y: min.y, w: max.x - min.x, h: max.y - min.y }
+    }
+
+    pub fn set_camera<C>(&mut self, new_camera: Option<C>)
+    where
+        C: Camera + Send + Sync + 'static,
+    {
+        self.draw();
+
+        self.camera =
+            new_camera.map(|cam| Box::new(cam) as Box<dyn Camera + Send + Sync + 'static>);
+    }
+
+    /// 修改当前相机的渲染目标。会先 flush 当前批次，
+    /// 保证之前的绘制仍然输出到旧的渲染目标。
+    pub fn set_camera_render_target(&mut self, render_target: Option<RenderTargetHandle>) {
+        self.draw();
+
+        if let Some(camera) = self.camera.as_mut() {
+            camera.set_render_target(render_target);
+        }
+    }
+}
+
+// Material 部分
+impl WgpuState {
+    pub async fn create_material(
+        &mut self,
+        name: String,
+        shader_str: String,
+        material_descriptor: MaterialDescriptor,
+        uniform_defs: Option<HashMap<String, UniformDef>>,
+    ) -> Option<MaterialHandle> {
+        match Material::new(
+            &self.context,
+            MaterialKind::Mesh,
+            &[&self.camera_bind_group_layout],
+            self.msaa,
+            name,
+            shader_str,
+            material_descriptor,
+            uniform_defs,
+        )
+        .await
+        {
+            Ok(new_mat) => Some(self.materials.insert(new_mat)),
+            Err(err) => {
+                error!("material create error: {}", err);
+                None
+            }
+        }
+    }
+
+    /// 创建用于 `WgpuState::blit` 的后处理材质。
+    ///
+    /// 着色器不接收顶点缓冲区：`vs_main` 通过 `@builtin(vertex_index)` 生成全屏三角形（参考 `shaders/Blit.wgsl`），
+    /// `@group(0)` 的 binding 0/1 为源纹理与采样器，用户 Uniform 位于 `@group(1) @binding(0)`。
+    pub async fn create_post_process_material(
+        &mut self,
+        name: String,
+        shader_str: String,
+        uniform_defs: Option<HashMap<String, UniformDef>>,
+    ) -> Option<MaterialHandle> {
+        match Material::new(
+            &self.context,
+            MaterialKind::PostProcess,
+            &[self.blitter.bind_group_layout()],
+            self.msaa,
+            name,
+            shader_str,
+            MaterialDescriptor::opaque(),
+            uniform_defs,
+        )
+        .await
+        {
+            Ok(new_mat) => Some(self.materials.insert(new_mat)),
+            Err(err) => {
+                error!("material create error: {}", err);
+                None
+            }
+        }
+    }
+
+    /// 创建带纹理的网格材质，顶点输入与 `create_material` 相同。
+    ///
+    /// `@group(1)` 的 binding 0/1 为纹理与采样器（参考 `shaders/Textured.wgsl`），
+    /// 用户 Uniform 位于 `@group(2) @binding(0)`。
+    pub async fn create_textured_material(
+        &mut self,
+        name: String,
+        shader_str: String,
+        material_descriptor: MaterialDescriptor,
+        uniform_defs: Option<HashMap<String, UniformDef>>,
+    ) -> Option<MaterialHandle> {
+        match Material::new(
+            &self.context,
+            MaterialKind::TexturedMesh,
+            &[&self.camera_bind_group_layout, self.blitter.bind_group_layout()],
+            self.msaa,
+            name,
+            shader_str,
+            material_descriptor,
+            uniform_defs,
+        )
+        .await
+        {
+            Ok(new_mat) => Some(self.materials.insert(new_mat)),
+            Err(err) => {
+                error!("material create error: {}", err);
+                None
+            }
+        }
+    }
+
+    /// 创建计算着色器材质，`entry_point` 为 `@compute` 入口函数名。
+    /// 存储缓冲区绑定在 `@group(0)`，按 `@binding` 顺序与 `dispatch_compute` 的 `bind_data` 对应。
+    pub async fn create_compute_material(
+        &mut self,
+        name: String,
+        shader_str: String,
+        entry_point: &str,
+    ) -> Option<ComputeMaterialHandle> {
+        match ComputeMaterial::new(&self.context, name, shader_str, entry_point).await {
+            Ok(new_mat) => Some(self.compute_materials.insert(new_mat)),
+            Err(err) => {
+                error!("compute material create error: {}", err);
+                None
+            }
+        }
+    }
+
+    /// 所有材质的句柄和名称（按创建顺序），用于调试面板列出材质，
+    /// 配合 `MaterialHandle::uniforms` 和 `set_uniform` 实时查看、调整 Uniform
+    pub fn material_names(&self) -> Vec<(MaterialHandle, String)> {
+        let mut names: Vec<_> = self.materials.iter().map(|(handle, mat)| (handle, mat.name.clone())).collect();
+        names.sort_by_key(|(handle, _)| *handle);
+        names
+    }
+
+    pub fn set_material(&mut self, new_mat: MaterialHandle) {
+        if self
+            .materials
+            .get(new_mat)
+            .is_some_and(|mat| mat.kind == MaterialKind::PostProcess)
+        {
+            error!("Post-process materials can only be used with blit.");
+            return;
+        }
+
+        if let Some(current_mat_handle) = self.current_material {
+            if current_mat_handle == new_mat {
+                return;
+            }
+        }
+
+        self.break_batching = true;
+        self.current_material = Some(new_mat);
+    }
+}
+
+// 以下为旧的全局函数，保留一个版本，之后删除。改为显式接收 `&mut WgpuState` 后直接转发到对应方法
+#[deprecated(note = "使用 WgpuState::create_material")]
+pub async fn create_material(
+    graphics: &mut WgpuState,
+    name: String,
+    shader_str: String,
+    material_descriptor: MaterialDescriptor,
+    uniform_defs: Option<HashMap<String, UniformDef>>,
+) -> Option<MaterialHandle> {
+    graphics.create_material(name, shader_str, material_descriptor, uniform_defs).await
+}
+
+#[deprecated(note = "使用 WgpuState::create_post_process_material")]
+pub async fn create_post_process_material(
+    graphics: &mut WgpuState,
+    name: String,
+    shader_str: String,
+    uniform_defs: Option<HashMap<String, UniformDef>>,
+) -> Option<MaterialHandle> {
+    graphics.create_post_process_material(name, shader_str, uniform_defs).await
+}
+
+#[deprecated(note = "使用 WgpuState::create_textured_material")]
+pub async fn create_textured_material(
+    graphics: &mut WgpuState,
+    name: String,
+    shader_str: String,
+    material_descriptor: MaterialDescriptor,
+    uniform_defs: Option<HashMap<String, UniformDef>>,
+) -> Option<MaterialHandle> {
+    graphics.create_textured_material(name, shader_str, material_descriptor, uniform_defs).await
+}
+
+#[deprecated(note = "使用 WgpuState::create_compute_material")]
+pub async fn create_compute_material(
+    graphics: &mut WgpuState,
+    name: String,
+    shader_str: String,
+    entry_point: &str,
+) -> Option<ComputeMaterialHandle> {
+    graphics.create_compute_material(name, shader_str, entry_point).await
+}
+
+#[deprecated(note = "使用 WgpuState::set_material")]
+pub fn set_material(graphics: &mut WgpuState, new_mat: MaterialHandle) {
+    graphics.set_material(new_mat);
+}
+
+// Renderer
+impl WgpuState {
+    // 渲染逻辑 - 这个方法现在只负责呈现最终结果，不再进行实际绘制。
+    // 它应该只处理默认渲染目标的解析和呈现。
+    pub(crate) fn render(&mut self) -> Result<(), SurfaceError> {
+        let context = &self.context;
+        if context.surface.is_none() {
+            return Err(wgpu::SurfaceError::Lost);
+        }
+
+        let output = context.surface.as_ref().unwrap().get_current_texture()?;
+
+        if let Some(rt) = self.render_targets.get(self.default_render_target) {
+            let mut encoder =
+                context
+                    .device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                        label: Some("Final Render Encoder (Present)"),
+                    });
+
+            // MSAA 只在写入渲染目标的 Pass 中 resolve（批次绘制、清除、blit 都设置了 resolve_target），
+            // 这样离屏 RT 在同一帧内即可被采样。到这里 resolve_texture 已经是最终结果，不再重复 resolve
+
+            // 尺寸和格式完全一致时直接复制；否则（例如 resize 期间两边尺寸暂时不同）
+            // 用全屏 Pass 缩放绘制，避免 copy_texture_to_texture 因尺寸不符而 panic。
+            // 时间戳只能写在 Pass 上，直接复制不计入 gpu_frame_time，全屏 Pass 计入
+            // HDR 默认渲染目标呈现到 LDR Surface 时需要色调映射
+            // Surface 不是 sRGB 格式时以它的 sRGB 视图写入，由 GPU 完成 gamma 编码；
+            // 只有 sRGB 后缀不同的格式之间可以直接复制，数据已经是编码后的值
+            let surface_size = output.texture.size();
+            let surface_format = context.surface_view_format();
+            let tonemapper = (is_hdr_format(rt.format) && !is_hdr_format(surface_format))
+                .then_some(self.tonemapper);
+            if rt.size == surface_size && rt.format == surface_format {
+                encoder.copy_texture_to_texture(
+                    wgpu::TexelCopyTextureInfo {
+                        texture: &rt.resolve_texture,
+                        mip_level: 0,
+                        origin: wgpu::Origin3d::ZERO,
+                        aspect: wgpu::TextureAspect::All,
+                    },
+                    wgpu::TexelCopyTextureInfo {
+                        texture: &output.texture,
+                        mip_level: 0,
+                        origin: wgpu::Origin3d::ZERO,
+                        aspect: wgpu::TextureAspect::All,
+                    },
+                    rt.size,
+                );
+            } else {
+                let surface_view = output.texture.create_view(&TextureViewDescriptor {
+                    format: Some(surface_format),
+                    ..Default::default()
+                });
+                self.blitter.blit(
+                    &context.device,
+                    &mut encoder,
+                    &rt.resolve_texture_view,
+                    &surface_view,
+                    surface_format,
+                    tonemapper,
+                    self.gpu_timer.as_mut().and_then(|timer| timer.pass_timestamp_writes()),
+                );
+            }
+
+            // 本帧所有时间戳都已写入，统一 resolve
+            if let Some(timer) = self.gpu_timer.as_mut() {
+                timer.resolve(&mut encoder);
+            }
+
+            context.queue.submit(std::iter::once(encoder.finish()));
+
+            if let Some(timer) = self.gpu_timer.as_mut() {
+                timer.map_resolved();
+            }
+        }
+
+        // 呈现 SurfaceTexture
+        output.present();
+        Ok(())
+    }
+
+    // 无窗口模式的帧结束：没有 Surface 可以呈现，只 resolve 本帧的 GPU 时间戳
+    pub(crate) fn finish_headless_frame(&mut self) {
+        if let Some(timer) = self.gpu_timer.as_mut() {
+            let mut encoder = self.context.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Headless Frame Encoder"),
+            });
+            timer.resolve(&mut encoder);
+            self.context.queue.submit(std::iter::once(encoder.finish()));
+            timer.map_resolved();
+        }
+    }
+
+    pub(crate) fn clear_draw_calls(&mut self) {
+        self.draw_calls.clear();
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.clear_draw_calls();
+    }
+
+    pub(crate) fn prepare_for_new_frame(&mut self, clear_color: wgpu::Color) {
+        self.reset();
+        self.debug_draw.clear();
+
+        // 将上一帧累积的统计数据保存下来，并为新的一帧重新计数
+        self.last_frame_stats = self.frame_stats;
+        self.frame_stats.reset();
+
+        if let Some(timer) = self.gpu_timer.as_mut() {
+            timer.poll(&self.context.device);
+        }
+
+        // 切换到下一组全局缓冲区，只有 GPU 落后超过 FRAMES_IN_FLIGHT 帧时才会等待
+        let vertex_stalled = self.global_vertex_buffer.advance(&self.context.device);
+        let index_stalled = self.global_index_buffer.advance(&self.context.device);
+        if vertex_stalled || index_stalled {
+            self.frame_stats.buffer_ring_stalls += 1;
+        }
+
+        // 默认渲染目标的清除颜色来自 GameSettings
+        if let Some(rt) = self.render_targets.get_mut(self.default_render_target) {
+            if let PassAction::Clear(_) = rt.clear_action {
+                rt.clear_action = PassAction::Clear(clear_color);
+            }
+        }
+
+        // 按各渲染目标的清除方式登记本帧的清除，在各自的第一个 Pass 中完成
+        self.render_commands.clear();
+        self.cleared_targets.clear();
+        for (handle, rt) in self.render_targets.iter() {
+            if let PassAction::Clear(_) = rt.clear_action {
+                self.pending_pass_actions.insert(handle, rt.clear_action);
+            }
+        }
+    }
+
+    // 全局顶点/索引缓冲区，每块至少能容纳一个达到上限的 DrawCall，整帧数据更多时上传时再增长
+    fn create_batch_buffers(device: &wgpu::Device, max_vertices: usize, max_indices: usize) -> (SizedBufferRing, SizedBufferRing) {
+        let vertex_buffer = SizedBufferRing::new(
+            "Mesh Vertex Buffer",
+            device,
+            max_vertices * std::mem::size_of::<Vertex>(),
+            BufferType::Vertex,
+            FRAMES_IN_FLIGHT,
+        );
+
+        let index_buffer = SizedBufferRing::new(
+            "Mesh Index Buffer",
+            device,
+            max_indices * std::mem::size_of::<u32>(),
+            BufferType::Index,
+            FRAMES_IN_FLIGHT,
+        );
+
+        (vertex_buffer, index_buffer)
+    }
+
+    /// 读取 GameSettings 中单个 DrawCall 的顶点/索引上限，上限改变时按新的上限重新创建全局顶点/索引缓冲区。
+    /// 需要在录制绘制命令之前调用，拆分超出上限的命令时使用的就是这里的上限
+    pub(crate) fn apply_batch_limits(&mut self, game_settings: &GameSettings) {
+        let max_vertices = game_settings.get_max_batch_vertices();
+        let max_indices = game_settings.get_max_batch_indices();
+        if max_vertices == self.max_vertices && max_indices == self.max_indices {
+            return;
+        }
+
+        self.max_vertices = max_vertices;
+        self.max_indices = max_indices;
+        // 旧缓冲区由 wgpu 保留到引用它的提交执行完毕，新缓冲区还未被 GPU 使用，不需要等待
+        (self.global_vertex_buffer, self.global_index_buffer) =
+            Self::create_batch_buffers(&self.context.device, max_vertices, max_indices);
+    }
+
+    pub(crate) fn end_frame(&mut self, game_settings: &mut GameSettings) {
+        // 本帧的绘制都已提交，GPU 完成后当前缓冲区才可以再次写入
+        self.global_vertex_buffer.mark_submitted(&self.context.queue);
+        self.global_index_buffer.mark_submitted(&self.context.queue);
+
+        self.apply_batch_limits(game_settings);
+
+        self.warm_up_pipelines();
+
+        // 默认投影按逻辑像素计算时需要的缩放系数
+        self.scale_factor = game_settings.get_scale_factor();
+        self.logical_pixel_projection = game_settings.get_logical_pixel_projection();
+        self.culling = game_settings.get_culling();
+
+        // 最大帧延迟更改处理
+        if let Some(latency) = game_settings.new_max_frame_latency.take() {
+            if self.context.config.desired_maximum_frame_latency != latency {
+                self.context.set_max_frame_latency(latency);
+            }
+            game_settings.max_frame_latency = latency;
+        }
+
+        // 呈现模式更改处理。Surface 不存在时保留请求，等恢复后再应用
+        if let Some(requested) = game_settings.new_present_mode {
+            if self.context.set_present_mode(requested).is_some() {
+                game_settings.new_present_mode = None;
+            }
+        }
+        game_settings.present_mode = self.context.config.present_mode;
+
+        // HDR 更改处理：重建默认渲染目标，材质在下一次绘制时按新格式选择管线
+        self.tonemapper = game_settings.get_tonemapper();
+        if let Some(hdr) = game_settings.new_hdr.take() {
+            if hdr != self.hdr {
+                self.set_default_rt_hdr(hdr);
+            }
+            game_settings.hdr = self.hdr;
+        }
+
+        // ... MSAA 更改处理 ...
+        if let Some(new_msaa) = game_settings.new_msaa {
+            if self.msaa == new_msaa {
+                // 已经相同，无需重建，只同步 GameSettings 中记录的值
+                game_settings.msaa = new_msaa;
+                game_settings.new_msaa = None;
+                return;
+            }
+
+            // 启动时的默认值没有经过 set_msaa 检查，在这里同样降低到设备支持的采样数
+            let supported = self.supported_sample_counts();
+            let new_msaa = if supported.contains(&new_msaa) {
+                new_msaa
+            } else {
+                let clamped = new_msaa.clamp_to(&supported);
+                warn!("{:?} is not supported by this device, using {:?} instead.", new_msaa, clamped);
+                clamped
+            };
+            if self.msaa == new_msaa {
+                game_settings.msaa = new_msaa;
+                game_settings.new_msaa = None;
+                return;
+            }
+
+            let switch_start = Instant::now();
+            let old_msaa = self.msaa;
+            self.msaa = new_msaa;
+            game_settings.msaa = new_msaa; // 保存新的 MSAA 设置
+
+            // 使用新的 MSAA 设置重新创建所有渲染目标，包括默认渲染目标和后处理的中间目标
+            self.render_targets.iter_mut().for_each(|(_, rt_ref)| {
+                rt_ref.re_create(&self.context, self.msaa);
+            });
+
+            // 材质管线不在这里集中重建：下一次绘制时由 ensure_pipeline 按需创建（之后切换回来直接复用缓存），
+            // 画过默认渲染目标的其他材质在之后几帧中分批预先创建
+            if let Some(rt) = self.render_targets.get(self.default_render_target) {
+                let old_key = (old_msaa, rt.format, rt.depth_format);
+                self.pipeline_warmup = self
+                    .materials
+                    .iter()
+                    .filter(|(_, mat)| mat.pipeline_for(&old_key).is_some())
+                    .map(|(handle, _)| handle)
+                    .collect();
+            }
+            debug!(
+                "MSAA switched to {:?} in {:.2}ms, {} pipelines queued for warm-up",
+                new_msaa,
+                switch_start.elapsed().as_secs_f64() * 1000.0,
+                self.pipeline_warmup.len()
+            );
+        }
+
+        game_settings.new_msaa = None;
+    }
+
+    // 为当前 MSAA 下的默认渲染目标预先创建最多 PIPELINE_WARMUP_PER_FRAME 个材质管线
+    fn warm_up_pipelines(&mut self) {
+        if self.pipeline_warmup.is_empty() {
+            return;
+        }
+        let Some(rt) = self.render_targets.get(self.default_render_target) else {
+            self.pipeline_warmup.clear();
+            return;
+        };
+        let key = (self.msaa, rt.format, rt.depth_format);
+
+        let count = self.pipeline_warmup.len().min(PIPELINE_WARMUP_PER_FRAME);
+        for handle in self.pipeline_warmup.drain(..count) {
+            if let Some(mat) = self.materials.get_mut(handle) {
+                mat.ensure_pipeline(&self.context, key);
+            }
+        }
+    }
+
+    /// 清除当前渲染目标。清除操作会合并到下一次 `draw()` 中该目标的第一个 RenderPass，
+    /// 不会单独提交一个全屏 Pass；尚未绘制的命令会被丢弃。
+    pub fn clear_background(&mut self, color: wgpu::Color) {
+        let rt_handle = self.get_active_render_target();
+        self.pending_pass_actions.insert(rt_handle, PassAction::Clear(color));
+
+        self.render_commands.clear();
+    }
+
+    /// 指定当前渲染目标下一个 RenderPass 的颜色加载方式。会先 flush 当前批次，
+    /// 之后的绘制从按 `action` 处理后的画面开始：`Clear` 清除为指定颜色（同时清除模板），
+    /// `Load` 保留已有内容，可以取消本帧尚未执行的清除（包括 `set_clear_action` 登记的每帧清除）。
+    /// 深度只在本帧第一次使用该目标时清除，`Load` 保留模板遮罩
+    pub fn set_pass_action(&mut self, action: PassAction) {
+        self.draw();
+        let rt_handle = self.get_active_render_target();
+        self.pending_pass_actions.insert(rt_handle, action);
+    }
+
+    /// 立即提交目前录制的所有绘制命令，建立一个硬性的顺序边界。
+    ///
+    /// 绘制命令的排序只在两次 flush 之间进行：flush 之前的绘制总是先于之后的绘制渲染，
+    /// 与 `z_order`、渲染队列和材质无关，例如先画世界、flush，再画 UI。
+    /// 深度缓冲只在每帧第一次使用渲染目标时清除，flush 之后开启深度测试的绘制仍会被之前写入的深度遮挡。
+    pub fn flush(&mut self) {
+        self.draw();
+    }
+
+    pub(crate) fn draw(&mut self) {
+        let geometry_scope = ProfileScope::new("geometry");
+        self.geometry();
+        drop(geometry_scope);
+
+        self.frame_stats.draw_calls += self.draw_calls.len();
+        self.frame_stats.vertices += self.batch_vertex_buffer.len();
+        self.frame_stats.indices += self.batch_index_buffer.len();
+
+        // 1. 全局数据上传（整帧一次）
+        let upload_scope = ProfileScope::new("buffer upload");
+        let upload_start = Instant::now();
         if !self.batch_vertex_buffer.is_empty() {
-            self.global_vertex_buffer.ensure_size_and_copy(
+            self.frame_stats.buffer_uploads_bytes +=
+                std::mem::size_of_val(self.batch_vertex_buffer.as_slice());
+            self.global_vertex_buffer.current_mut().ensure_size_and_copy(
                 &self.context.device,
                 &self.context.queue,
                 bytemuck::cast_slice(&self.batch_vertex_buffer),
             );
         }
         if !self.batch_index_buffer.is_empty() {
-            self.global_index_buffer.ensure_size_and_copy(
+            self.frame_stats.buffer_uploads_bytes +=
+                std::mem::size_of_val(self.batch_index_buffer.as_slice());
+            self.global_index_buffer.current_mut().ensure_size_and_copy(
                 &self.context.device,
                 &self.context.queue,
                 bytemuck::cast_slice(&self.batch_index_buffer),
             );
         }
+        self.frame_stats.buffer_upload_time += upload_start.elapsed();
+
+        // 相机矩阵同样在录制 Pass 之前上传，录制过程中不再写缓冲区
+        let camera_offsets = self.upload_camera_uniforms();
+        drop(upload_scope);
+
+        profile_scope!("encode");
 
         let mut encoder =
             self.context
@@ -545,9 +1674,35 @@ impl WgpuState {
                 .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                     label: Some("Draw Encoder"),
                 });
+        self.encode_compute_dispatches(&mut encoder);
+
+        // 找出使用了模板测试的渲染目标，只有这些目标的 Pass 需要模板的 load/store
+        let stencil_targets: HashSet<RenderTargetHandle> = self
+            .draw_calls
+            .iter()
+            .filter(|dc| {
+                self.materials
+                    .get(dc.mat_handle)
+                    .is_some_and(|mat| mat.material_descriptor.is_stencil_enabled())
+            })
+            .map(|dc| dc.render_target)
+            .collect();
+
+        // 每个渲染目标对应的管线键，材质按目标的采样数/格式/深度附件选择管线
+        let mut target_keys: HashMap<RenderTargetHandle, PipelineKey> = HashMap::new();
+        for dc in &self.draw_calls {
+            let Some(render_target) = self.render_targets.get(dc.render_target) else {
+                continue;
+            };
+            let key = *target_keys
+                .entry(dc.render_target)
+                .or_insert((render_target.sample_count, render_target.format, render_target.depth_format));
+            if let Some(mat) = self.materials.get_mut(dc.mat_handle) {
+                mat.ensure_pipeline(&self.context, key);
+            }
+        }
 
         // 状态追踪
-        let mut cleared_targets = HashSet::new();
         let mut current_rt_handle = None;
         // 关键：将 RenderPass 放在 Option 中以延长生命周期并允许手动 Drop
         let mut render_pass: Option<wgpu::RenderPass> = None;
@@ -562,7 +1717,11 @@ impl WgpuState {
 
                 // 2. 准备新的 Pass 环境
                 if let Some(render_target) = self.render_targets.get(rt_handle) {
-                    let is_first_usage = cleared_targets.insert(rt_handle);
+                    let is_first_usage = self.cleared_targets.insert(rt_handle);
+                    // clear_background / set_pass_action 请求的加载方式在这个 Pass 里一并完成
+                    let load = self.pending_pass_actions.remove(&rt_handle).unwrap_or(PassAction::Load);
+                    let clears_color = matches!(load, PassAction::Clear(_));
+                    let clear_stencil = clears_color || is_first_usage;
 
                     // 确定视图
                     let (view, resolve) = if render_target.msaa_texture_view.is_some() {
@@ -574,7 +1733,10 @@ impl WgpuState {
                         (&render_target.resolve_texture_view, None)
                     };
 
-                    // 确定深度负载
+                    // 确定深度负载，没有深度附件的目标直接不带深度/模板附件
+                    let has_stencil = render_target
+                        .depth_format
+                        .is_some_and(|format| format.has_stencil_aspect());
                     let depth_stencil_attachment =
                         render_target.depth_texture_view.as_ref().map(|depth_view| {
                             wgpu::RenderPassDepthStencilAttachment {
@@ -587,30 +1749,25 @@ impl WgpuState {
                                     },
                                     store: wgpu::StoreOp::Store,
                                 }),
-                                stencil_ops: None, // 如有特需可按同样逻辑配置
+                                // 本帧第一个 Pass 总是清除模板，之后拆分出的 Pass 即使不用模板也不会留下上一帧的遮罩
+                                stencil_ops: (has_stencil
+                                    && (clear_stencil || stencil_targets.contains(&rt_handle)))
+                                .then_some(wgpu::Operations {
+                                    load: if clear_stencil {
+                                        wgpu::LoadOp::Clear(0)
+                                    } else {
+                                        wgpu::LoadOp::Load
+                                    },
+                                    store: wgpu::StoreOp::Store,
+                                }),
                             }
                         });
 
-                    if depth_stencil_attachment.is_none() {
+                    if render_target.depth_format.is_some() && depth_stencil_attachment.is_none() {
                         error!("RenderTarget DepthTexture Lost. ID: {}", rt_handle);
                         continue;
                     }
 
-                    // 更新相机 (因为 RT 变了，投影矩阵可能需要变)
-                    let rt_size = uvec2(render_target.size.width, render_target.size.height);
-                    let proj = if let Some(camera) = self.camera.as_mut() {
-                        camera.resize(rt_size);
-                        camera.matrix()
-                    } else {
-                        self.pixel_perfect_projection_matrix(rt_size)
-                    };
-                    self.camera_uniform.update_matrix(proj);
-                    self.context.queue.write_buffer(
-                        &self.camera_buffer,
-                        0,
-                        bytemuck::cast_slice(&[self.camera_uniform]),
-                    );
-
                     // 3. 开启新的 RenderPass
                     let mut new_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                         label: Some("Batched Render Pass"),
@@ -618,25 +1775,31 @@ impl WgpuState {
                             view,
                             resolve_target: resolve,
                             ops: wgpu::Operations {
-                                load: wgpu::LoadOp::Load,
+                                load: load.load_op(),
                                 store: wgpu::StoreOp::Store,
                             },
                             depth_slice: None,
                         })],
                         depth_stencil_attachment,
+                        timestamp_writes: self
+                            .gpu_timer
+                            .as_mut()
+                            .and_then(|timer| timer.pass_timestamp_writes()),
                         ..Default::default()
                     });
 
                     // 4. 初始化新 Pass 的全局绑定
-                    new_pass.set_bind_group(0, &self.camera_bind_group, &[]);
-                    new_pass.set_vertex_buffer(0, self.global_vertex_buffer.buffer.slice(..));
+                    let camera_offset = camera_offsets.get(&rt_handle).copied().unwrap_or(0);
+                    new_pass.set_bind_group(0, &self.camera_bind_group, &[camera_offset]);
+                    new_pass.set_vertex_buffer(0, self.global_vertex_buffer.current().buffer.slice(..));
                     new_pass.set_index_buffer(
-                        self.global_index_buffer.buffer.slice(..),
+                        self.global_index_buffer.current().buffer.slice(..),
                         wgpu::IndexFormat::Uint32,
                     );
 
                     render_pass = Some(new_pass);
                     current_rt_handle = Some(rt_handle);
+                    self.frame_stats.render_target_switches += 1;
                 }
             }
 
@@ -644,74 +1807,267 @@ impl WgpuState {
             if let (Some(pass), Some(mat)) =
                 (render_pass.as_mut(), self.materials.get(dc.mat_handle))
             {
-                pass.set_pipeline(&mat.pipeline);
+                // 材质与目标格式不兼容时跳过（错误已在 ensure_pipeline 中报告）
+                let Some(pipeline) = target_keys
+                    .get(&rt_handle)
+                    .and_then(|key| mat.pipeline_for(key))
+                else {
+                    continue;
+                };
+                pass.set_pipeline(pipeline);
+
+                if mat.material_descriptor.is_stencil_enabled() {
+                    pass.set_stencil_reference(mat.material_descriptor.stencil_reference);
+                }
+
+                if mat.kind == MaterialKind::TexturedMesh {
+                    // 纹理已被删除时同样退回白色纹理
+                    let texture = dc
+                        .texture
+                        .and_then(|handle| self.texture2ds.get(handle))
+                        .or_else(|| self.texture2ds.get(self.white_texture));
+                    if let Some(texture) = texture {
+                        pass.set_bind_group(1, &texture.bind_group, &[]);
+                    }
+                }
 
                 if mat.user_uniform_bind_group.is_some() {
                     // 每次切换材质时尝试更新和绑定
                     if let Ok(_) = mat.update_user_uniforms(&self.context) {
-                        pass.set_bind_group(1, mat.user_uniform_bind_group.as_ref().unwrap(), &[]);
+                        pass.set_bind_group(
+                            mat.user_uniform_group,
+                            mat.user_uniform_bind_group.as_ref().unwrap(),
+                            &[],
+                        );
                     }
                 }
 
-                let index_start = dc.indices_start as u32;
-                let index_end = (dc.indices_start + dc.indices_count) as u32;
-                pass.draw_indexed(index_start..index_end, dc.vertices_start as i32, 0..1);
-            }
+                if dc.indexed {
+                    // 批次中的索引相对于 DrawCall 的起始顶点，由 base_vertex 补上偏移
+                    let index_start = dc.indices_start as u32;
+                    let index_end = (dc.indices_start + dc.indices_count) as u32;
+                    pass.draw_indexed(index_start..index_end, dc.vertices_start as i32, 0..1);
+                } else {
+                    let vertex_start = dc.vertices_start as u32;
+                    pass.draw(vertex_start..vertex_start + dc.vertices_count as u32, 0..1);
+                }
+            }
+        }
+
+        // 释放最后一个 pass
+        render_pass = None;
+
+        // 没有任何绘制的渲染目标仍需要执行清除，Load 不需要单独的 Pass
+        for (rt_handle, action) in self.pending_pass_actions.drain() {
+            if let (PassAction::Clear(color), Some(render_target)) = (action, self.render_targets.get(rt_handle)) {
+                encode_clear_pass(&mut encoder, render_target, color);
+            }
+        }
+
+        self.context.queue.submit(std::iter::once(encoder.finish()));
+
+        self.draw_calls.clear();
+        self.batch_index_buffer.clear();
+        self.batch_vertex_buffer.clear();
+    }
+
+    pub(crate) fn record_draw_command(
+        &mut self,
+        _vertices: &[Vertex],
+        _indices: &[u32],
+        z_order: u32,
+    ) {
+        let mat_handle = self
+            .current_material
+            .unwrap_or(self.basic_shapes_triangle_mat);
+        self.record_draw_command_ex(_vertices, _indices, z_order, mat_handle, None);
+    }
+
+    /// 使用指定材质和纹理录制绘制命令，不受 `set_material` 影响
+    pub(crate) fn record_draw_command_ex(
+        &mut self,
+        _vertices: &[Vertex],
+        _indices: &[u32],
+        z_order: u32,
+        mat_handle: MaterialHandle,
+        texture: Option<Texture2DHandle>,
+    ) {
+        let command_id = self.render_commands.len() as u32;
+        let render_target = self.get_active_render_target();
+
+        let depth = self.command_depth(mat_handle, _vertices);
+
+        if _vertices.len() <= self.max_vertices && _indices.len() <= self.max_indices {
+            self.render_commands.push(RenderCommand {
+                id: command_id,
+                vertices: _vertices.to_vec(),
+                indices: _indices.to_vec(),
+                indexed: true,
+                mat_handle,
+                uniforms: None, // 示例
+                texture,
+                render_target,
+                render_queue: z_order,
+                depth,
+                bounds: if self.culling { calculate_object_bounds(_vertices) } else { None },
+            });
+            return;
+        }
+
+        // 超出单个 DrawCall 上限：按完整图元拆分成多个命令，而不是截断索引
+        let primitive_size = self
+            .materials
+            .get(mat_handle)
+            .map(|mat| mat.material_descriptor.primitive_type.vertices_per_primitive())
+            .unwrap_or(3);
+
+        let chunks = split_geometry(
+            _vertices,
+            _indices,
+            primitive_size,
+            self.max_vertices,
+            self.max_indices,
+        );
+
+        for (i, (vertices, indices)) in chunks.into_iter().enumerate() {
+            let bounds = if self.culling { calculate_object_bounds(&vertices) } else { None };
+            self.render_commands.push(RenderCommand {
+                id: command_id + i as u32,
+                vertices,
+                indices,
+                indexed: true,
+                mat_handle,
+                uniforms: None,
+                texture,
+                render_target,
+                render_queue: z_order,
+                depth,
+                bounds,
+            });
+        }
+    }
+
+    /// 录制不使用索引的绘制命令：顶点按顺序组成图元（三角形列表每 3 个顶点一个三角形），
+    /// 绘制时使用 `pass.draw`，省去索引数据。使用 `set_material` 设置的材质
+    pub(crate) fn record_draw_command_unindexed(&mut self, vertices: &[Vertex], z_order: u32) {
+        let mat_handle = self
+            .current_material
+            .unwrap_or(self.basic_shapes_triangle_mat);
+        let command_id = self.render_commands.len() as u32;
+        let render_target = self.get_active_render_target();
+        let depth = self.command_depth(mat_handle, vertices);
+
+        let primitive_size = self
+            .materials
+            .get(mat_handle)
+            .map(|mat| mat.material_descriptor.primitive_type.vertices_per_primitive())
+            .unwrap_or(3);
+        if !vertices.len().is_multiple_of(primitive_size) {
+            warn!(
+                "Vertex count {} is not a multiple of primitive size {}, trailing vertices ignored.",
+                vertices.len(),
+                primitive_size
+            );
+        }
+        let vertices = &vertices[..vertices.len() - vertices.len() % primitive_size];
+        if vertices.is_empty() {
+            return;
+        }
+
+        // 超出单个 DrawCall 上限时按完整图元拆分，不需要重新映射索引
+        let chunk_len = self.max_vertices / primitive_size * primitive_size;
+        if chunk_len == 0 {
+            error!(
+                "Batch limit ({} vertices) is too small for a single primitive, command dropped.",
+                self.max_vertices
+            );
+            return;
+        }
+
+        for (i, chunk) in vertices.chunks(chunk_len).enumerate() {
+            self.render_commands.push(RenderCommand {
+                id: command_id + i as u32,
+                vertices: chunk.to_vec(),
+                indices: Vec::new(),
+                indexed: false,
+                mat_handle,
+                uniforms: None,
+                texture: None,
+                render_target,
+                render_queue: z_order,
+                depth,
+                bounds: if self.culling { calculate_object_bounds(chunk) } else { None },
+            });
+        }
+    }
+
+    // 开启深度测试的材质按物体中心在相机观察方向上的距离排序，其他材质不参与深度排序
+    fn command_depth(&self, mat_handle: MaterialHandle, vertices: &[Vertex]) -> f32 {
+        if !mat_handle.is_depth_enabled(self) {
+            return 0f32;
         }
 
-        // 释放最后一个 pass
-        render_pass = None;
+        let obj_world_center = calculate_object_center(vertices);
+        let (camera_position, camera_forward) = if let Some(cam) = self.camera.as_ref() {
+            (cam.get_position(), cam.get_forward())
+        } else {
+            (Vec3::ZERO, Quat::IDENTITY * Vec3::NEG_Z)
+        };
 
-        self.context.queue.submit(std::iter::once(encoder.finish()));
+        // 从摄像机指向物体的向量
+        let to_obj = obj_world_center - camera_position;
 
-        self.draw_calls.clear();
-        self.batch_index_buffer.clear();
-        self.batch_vertex_buffer.clear();
+        // 使用点积 (Dot Product) 计算投影距离
+        // 这就是物体在摄像机观察轴线上的 Z 深度
+        to_obj.dot(camera_forward)
     }
 
-    pub(crate) fn record_draw_command(
-        &mut self,
-        _vertices: &[Vertex],
-        _indices: &[u32],
-        z_order: u32,
-    ) {
-        let command_id = self.render_commands.len() as u32;
-        let render_target = self.get_active_render_target();
-        let mat_handle = self
-            .current_material
-            .unwrap_or(self.basic_shapes_triangle_mat);
+    // 剔除包围盒完全在可见范围之外的命令。每个渲染目标的可见范围只计算一次，
+    // 透视相机等无法给出可见矩形的情况不剔除
+    fn cull_render_commands(&mut self) {
+        let mut visible_rects: HashMap<RenderTargetHandle, Option<Rect>> = HashMap::new();
+        let before = self.render_commands.len();
 
-        let depth = if mat_handle.is_depth_enabled() {
-            let obj_world_center = calculate_object_center(_vertices);
-            let (camera_position, camera_forward) = if let Some(cam) = self.camera.as_ref() {
-                (cam.get_position(), cam.get_forward())
-            } else {
-                (Vec3::ZERO, Quat::IDENTITY * Vec3::NEG_Z)
+        let mut commands = std::mem::take(&mut self.render_commands);
+        commands.retain(|cmd| {
+            let Some(bounds) = cmd.bounds else {
+                return true;
             };
+            let visible = *visible_rects
+                .entry(cmd.render_target)
+                .or_insert_with(|| self.visible_rect_for(cmd.render_target));
+            visible.is_none_or(|rect| rect.overlaps(&bounds))
+        });
+        self.render_commands = commands;
 
-            // 从摄像机指向物体的向量
-            let to_obj = obj_world_center - camera_position;
+        self.frame_stats.culled_commands += before - self.render_commands.len();
+    }
 
-            // 使用点积 (Dot Product) 计算投影距离
-            // 这就是物体在摄像机观察轴线上的 Z 深度
-            to_obj.dot(camera_forward)
-        } else {
-            0f32
-        };
+    // 当前相机在指定渲染目标上的可见矩形，未设置相机时为默认投影的范围
+    fn visible_rect_for(&mut self, render_target: RenderTargetHandle) -> Option<Rect> {
+        let rt = self.render_targets.get(render_target)?;
+        let rt_size = uvec2(rt.size.width, rt.size.height);
 
-        self.render_commands.push(RenderCommand {
-            id: command_id,
-            vertices: _vertices.to_vec(),
-            indices: _indices.to_vec(),
-            mat_handle,
-            uniforms: None, // 示例
-            render_target,
-            render_queue: z_order,
-            depth,
-        });
+        match self.camera.as_mut() {
+            Some(camera) => {
+                camera.resize(rt_size);
+                camera.visible_rect()
+            }
+            None => {
+                let scale = if self.logical_pixel_projection { self.scale_factor.max(f64::EPSILON) as f32 } else { 1.0 };
+                let size = rt_size.as_vec2() / scale;
+                Some(Rect { x: -size.x / 2.0, y: -size.y / 2.0, w: size.x, h: size.y })
+            }
+        }
     }
 
     pub(crate) fn geometry(&mut self) {
+        self.frame_stats.render_commands += self.render_commands.len();
+
+        if self.culling {
+            self.cull_render_commands();
+        }
+
         self.sort_render_commands();
 
         if self.render_commands.is_empty() {
@@ -721,36 +2077,39 @@ impl WgpuState {
         // 1. 初始化第一个 DrawCall，使用第一个命令的数据
         let first_cmd = &self.render_commands[0];
 
-        // 同样对第一个命令的数据进行截断校准
-        let v_limit = self.max_vertices.min(first_cmd.vertices.len());
-        let i_limit = self.max_indices.min(first_cmd.indices.len());
+        // record_draw_command 已保证单个命令不超过上限
+        let v_limit = first_cmd.vertices.len();
+        let i_limit = first_cmd.indices.len();
 
         let mut current_draw_call = DrawCall {
             vertices_start: self.batch_vertex_buffer.len(), // 应该是当前 buffer 的末尾
             indices_start: self.batch_index_buffer.len(),
             vertices_count: v_limit,
             indices_count: i_limit,
+            indexed: first_cmd.indexed,
             mat_handle: first_cmd.mat_handle,
             uniforms: first_cmd.uniforms.clone(),
+            texture: first_cmd.texture,
             render_target: first_cmd.render_target,
         };
 
         // 将第一个命令的数据写入全局缓冲
-        let vertex_offset = self.batch_vertex_buffer.len() as u32;
+        // 它是 DrawCall 的第一个命令，索引无需偏移
         self.batch_vertex_buffer
             .extend_from_slice(&first_cmd.vertices[0..v_limit]);
-        for &idx in (&first_cmd.indices[0..i_limit]).iter() {
-            self.batch_index_buffer.push(idx + vertex_offset);
-        }
+        self.batch_index_buffer
+            .extend_from_slice(&first_cmd.indices[0..i_limit]);
 
         // 2. 从第二个命令开始遍历 (skip 1)
         for cmd in self.render_commands.iter().skip(1) {
-            let v_len = cmd.vertices.len().min(self.max_vertices);
-            let i_len = cmd.indices.len().min(self.max_indices);
+            let v_len = cmd.vertices.len();
+            let i_len = cmd.indices.len();
 
             let is_state_compatible = cmd.render_target == current_draw_call.render_target
+                && cmd.indexed == current_draw_call.indexed
                 && cmd.mat_handle == current_draw_call.mat_handle
-                && cmd.uniforms == current_draw_call.uniforms;
+                && cmd.uniforms == current_draw_call.uniforms
+                && cmd.texture == current_draw_call.texture;
 
             let has_space = (current_draw_call.vertices_count + v_len <= self.max_vertices)
                 && (current_draw_call.indices_count + i_len <= self.max_indices);
@@ -764,14 +2123,18 @@ impl WgpuState {
                     indices_start: self.batch_index_buffer.len(),
                     vertices_count: 0,
                     indices_count: 0,
+                    indexed: cmd.indexed,
                     mat_handle: cmd.mat_handle,
                     uniforms: cmd.uniforms.clone(),
+                    texture: cmd.texture,
                     render_target: cmd.render_target,
                 };
             }
 
             // 写入数据
-            let current_v_offset = self.batch_vertex_buffer.len() as u32;
+            // 索引相对于当前 DrawCall 的起始顶点，绘制时由 base_vertex 补上
+            let current_v_offset =
+                (self.batch_vertex_buffer.len() - current_draw_call.vertices_start) as u32;
             self.batch_vertex_buffer
                 .extend_from_slice(&cmd.vertices[0..v_len]);
             for &idx in (&cmd.indices[0..i_len]).iter() {
@@ -788,6 +2151,7 @@ impl WgpuState {
     }
 
     pub fn sort_render_commands(&mut self) {
+        let materials = &self.materials;
         self.render_commands.sort_by(|a, b| {
             // 1. 渲染目标 (Render Target)
             let target_cmp = a.render_target.cmp(&b.render_target);
@@ -802,12 +2166,24 @@ impl WgpuState {
                 return queue_cmp;
             }
 
-            // --- 在相同的 Render Target 和 Render Queue 内部进行排序 ---
+            // 3. 不透明/透明拆分
+            // 同一渲染队列内，不透明物体写入深度，必须先于透明物体渲染
+            let is_transparent = |mat: MaterialHandle| {
+                materials
+                    .get(mat)
+                    .is_some_and(|mat| mat.material_descriptor.should_render_as_transparent())
+            };
+            let a_is_transparent = is_transparent(a.mat_handle);
+            let b_is_transparent = is_transparent(b.mat_handle);
+
+            let opaque_cmp = a_is_transparent.cmp(&b_is_transparent);
+            if opaque_cmp != std::cmp::Ordering::Equal {
+                return opaque_cmp;
+            }
 
-            // 3. 透明性判断和深度排序
-            let a_is_transparent = a.mat_handle.should_render_as_transparent();
-            let b_is_transparent = b.mat_handle.should_render_as_transparent();
+            // --- 在相同的 Render Target、Render Queue 和透明性内部进行排序 ---
 
+            // 4. 深度排序
             let depth_cmp = if a_is_transparent && b_is_transparent {
                 // 如果两者都是透明：从远到近 (递减顺序)
                 // b.depth - a.depth 得到负值是升序，正值是降序
@@ -815,43 +2191,112 @@ impl WgpuState {
                 b.depth
                     .partial_cmp(&a.depth)
                     .unwrap_or(std::cmp::Ordering::Equal)
-            } else if !a_is_transparent && !b_is_transparent {
-                // 如果两者都是不透明：从近到远 (递增顺序)
+            } else {
+                // 如果两者都是不透明：从近到远 (递增顺序)，配合深度写入减少 overdraw
                 a.depth
                     .partial_cmp(&b.depth)
                     .unwrap_or(std::cmp::Ordering::Equal)
-            } else {
-                // 一个透明一个不透明：
-                // 这种情况应该很少发生，因为通常会在不同的 render_queue 范围内。
-                // 如果确实发生了，通常应该让不透明的先渲染。
-                // 但是，如果 render_queue 设计得好，这个 else 分支几乎不会被调用
-                // 因为透明和不透明物体会先被 render_queue 分开。
-                // 如果它们在同一个 render_queue 比如 2500，且一个透明一个不透明，
-                // 那你可能需要强制不透明先渲染。
-                if a_is_transparent {
-                    std::cmp::Ordering::Greater // a 是透明，b 不透明，a 后渲染
-                } else {
-                    std::cmp::Ordering::Less // b 是透明，a 不透明，b 后渲染
-                }
             };
 
             if depth_cmp != std::cmp::Ordering::Equal {
                 return depth_cmp;
             }
 
-            // 4. 材质/Shader (Material Handle)
+            // 透明物体的绘制顺序决定混合结果：深度相同时（例如 2D 精灵）按录制顺序绘制，
+            // 不能为了合批按材质/纹理重排，否则后画的精灵可能被先画的盖住。
+            // id 互不相同，因此透明命令之间材质和纹理不会参与比较
+            if a_is_transparent {
+                return a.id.cmp(&b.id);
+            }
+
+            // 5. 材质/Shader (Material Handle)
             // 避免频繁切换材质状态
             let mat_cmp = a.mat_handle.cmp(&b.mat_handle); // 假设 MaterialHandle 实现了 Ord
             if mat_cmp != std::cmp::Ordering::Equal {
                 return mat_cmp;
             }
 
-            // 5. 原始 ID 作为最终的决胜键 (提供稳定性)
+            // 6. 纹理，相同纹理的命令才能合批
+            let texture_cmp = a.texture.cmp(&b.texture);
+            if texture_cmp != std::cmp::Ordering::Equal {
+                return texture_cmp;
+            }
+
+            // 7. 原始 ID 作为最终的决胜键 (提供稳定性)
             a.id.cmp(&b.id)
         });
     }
 }
 
+/// 将超出上限的几何体按完整图元拆分，每一段重新映射索引，
+/// 保证每段的顶点数不超过 `max_vertices`、索引数不超过 `max_indices`。
+fn split_geometry(
+    vertices: &[Vertex],
+    indices: &[u32],
+    primitive_size: usize,
+    max_vertices: usize,
+    max_indices: usize,
+) -> Vec<(Vec<Vertex>, Vec<u32>)> {
+    let mut chunks = Vec::new();
+
+    if max_vertices < primitive_size || max_indices < primitive_size {
+        error!(
+            "Batch limits ({} vertices, {} indices) are too small for a single primitive, command dropped.",
+            max_vertices, max_indices
+        );
+        return chunks;
+    }
+
+    if !indices.len().is_multiple_of(primitive_size) {
+        warn!(
+            "Index count {} is not a multiple of primitive size {}, trailing indices ignored.",
+            indices.len(),
+            primitive_size
+        );
+    }
+
+    let mut remap: HashMap<u32, u32> = HashMap::new();
+    let mut chunk_vertices: Vec<Vertex> = Vec::new();
+    let mut chunk_indices: Vec<u32> = Vec::new();
+
+    for primitive in indices.chunks_exact(primitive_size) {
+        if let Some(&bad) = primitive.iter().find(|&&idx| idx as usize >= vertices.len()) {
+            error!("Index {} out of range ({} vertices), primitive skipped.", bad, vertices.len());
+            continue;
+        }
+
+        let new_vertices = primitive
+            .iter()
+            .enumerate()
+            .filter(|&(i, idx)| !remap.contains_key(idx) && !primitive[..i].contains(idx))
+            .count();
+
+        if chunk_indices.len() + primitive_size > max_indices
+            || chunk_vertices.len() + new_vertices > max_vertices
+        {
+            chunks.push((
+                std::mem::take(&mut chunk_vertices),
+                std::mem::take(&mut chunk_indices),
+            ));
+            remap.clear();
+        }
+
+        for &idx in primitive {
+            let local = *remap.entry(idx).or_insert_with(|| {
+                chunk_vertices.push(vertices[idx as usize]);
+                (chunk_vertices.len() - 1) as u32
+            });
+            chunk_indices.push(local);
+        }
+    }
+
+    if !chunk_indices.is_empty() {
+        chunks.push((chunk_vertices, chunk_indices));
+    }
+
+    chunks
+}
+
 // 简易绘制部分
 impl WgpuState {
     #[rustfmt::skip]
@@ -902,4 +2347,574 @@ impl WgpuState {
 
         self.record_draw_command(&vertices, &indices, z_order);
     }
-}
\ No newline at end of file
+
+    /// 绘制轴对齐矩形，`dest` 的 `(x, y)` 为左下角。
+    ///
+    /// 顶点颜色为 `color * tint`，在 CPU 上相乘后写入顶点，不会打断合批；
+    /// 给一组图形传入相同的 `tint`（如 alpha 渐变）即可整体淡出，且仍在同一次绘制调用中。
+    pub fn draw_rectangle(&mut self, dest: Rect, color: wgpu::Color, tint: wgpu::Color, z_order: u32) {
+        let mut vertices = rect_vertices(dest, color);
+        vertices.iter_mut().for_each(|v| v.tint(tint));
+        self.record_draw_command(&vertices, &RECT_INDICES, z_order);
+    }
+
+    /// 使用指定材质绘制自定义网格（瓦片地图、程序生成的图形等），不受 `set_material` 影响。
+    ///
+    /// 索引按材质的图元类型解释（三角形每 3 个一组）。任何索引超出 `vertices` 范围时整个网格不绘制并打印错误；
+    /// 后处理材质不能用于网格绘制。超出单个 DrawCall 上限的网格会按完整图元自动拆分。
+    pub fn draw_mesh(&mut self, vertices: &[Vertex], indices: &[u32], material: MaterialHandle, z_order: u32) {
+        match self.materials.get(material) {
+            Some(mat) if mat.kind == MaterialKind::PostProcess => {
+                error!("draw_mesh: post-process material '{}' cannot draw meshes.", mat.name);
+                return;
+            }
+            Some(_) => {}
+            None => {
+                error!("draw_mesh: material {:?} not found.", material);
+                return;
+            }
+        }
+
+        if let Some(&bad) = indices.iter().find(|&&idx| idx as usize >= vertices.len()) {
+            error!("draw_mesh: index {} out of range ({} vertices), mesh skipped.", bad, vertices.len());
+            return;
+        }
+        if indices.is_empty() {
+            return;
+        }
+
+        self.record_draw_command_ex(vertices, indices, z_order, material, None);
+    }
+
+    /// 绘制三角形列表，每 3 个顶点一个三角形（线段材质为每 2 个顶点一条线段），不使用索引。
+    /// 使用 `set_material` 设置的材质。
+    ///
+    /// 适合粒子、程序生成的三角形等顶点不共享的几何体：与 `draw_mesh` 传入 0, 1, 2... 的索引相比，
+    /// 省去了索引的生成、上传和合批时的偏移计算
+    pub fn draw_triangle_list(&mut self, vertices: &[Vertex], z_order: u32) {
+        self.record_draw_command_unindexed(vertices, z_order);
+    }
+}
+
+// 调试绘制部分
+impl WgpuState {
+    /// 绘制一条调试线段，本帧结束时自动清除。
+    ///
+    /// 调试图形在所有绘制和后处理之后单独提交到默认渲染目标，不参与排序、不做深度测试，
+    /// 总是位于画面最上层，使用提交时的相机。适合显示碰撞盒、相机范围等
+    pub fn debug_line(&mut self, from: glam::Vec2, to: glam::Vec2, color: wgpu::Color) {
+        self.debug_draw.line(from, to, color);
+    }
+
+    /// 绘制调试矩形的边框，`rect` 的 `(x, y)` 为左下角，见 `debug_line`
+    pub fn debug_rect(&mut self, rect: Rect, color: wgpu::Color) {
+        self.debug_draw.rect(rect, color);
+    }
+
+    /// 绘制调试圆的边框，见 `debug_line`
+    pub fn debug_circle(&mut self, center: glam::Vec2, radius: f32, color: wgpu::Color) {
+        self.debug_draw.circle(center, radius, color);
+    }
+
+    // 在后处理之后调用，把本帧的调试图形绘制到默认渲染目标的最上层
+    pub(crate) fn draw_debug(&mut self) {
+        if self.debug_draw.is_empty() {
+            return;
+        }
+        let debug_draw = std::mem::take(&mut self.debug_draw);
+
+        let previous_override = self.render_target_override.replace(self.default_render_target);
+        let debug_lines_mat = self.debug_lines_mat;
+        self.record_draw_command_ex(&debug_draw.vertices, &debug_draw.indices, u32::MAX, debug_lines_mat, None);
+        self.render_target_override = previous_override;
+
+        // 单独提交，保证位于本帧所有绘制之后
+        self.draw();
+    }
+}
+
+pub(crate) const RECT_INDICES: [u32; 6] = [3, 2, 0, 0, 2, 1];
+
+// 覆盖整张纹理的矩形四个顶点，`dest` 的 `(x, y)` 为左下角
+fn rect_vertices(dest: Rect, color: wgpu::Color) -> [Vertex; 4] {
+    rect_vertices_uv(dest, glam::Vec2::ZERO, glam::Vec2::ONE, color)
+}
+
+// 矩形四个顶点，`uv_min` 对应左上角、`uv_max` 对应右下角
+pub(crate) fn rect_vertices_uv(dest: Rect, uv_min: glam::Vec2, uv_max: glam::Vec2, color: wgpu::Color) -> [Vertex; 4] {
+    let (left, right, bottom, top) = (dest.x, dest.x + dest.w, dest.y, dest.y + dest.h);
+    [
+        // 0: Top-left
+        Vertex::new(vec3(left, top, 0.0), uv_min, color),
+        // 1: Top-right
+        Vertex::new(vec3(right, top, 0.0), vec2(uv_max.x, uv_min.y), color),
+        // 2: Bottom-right
+        Vertex::new(vec3(right, bottom, 0.0), uv_max, color),
+        // 3: Bottom-left
+        Vertex::new(vec3(left, bottom, 0.0), vec2(uv_min.x, uv_max.y), color),
+    ]
+}
+
+// 计算部分
+impl WgpuState {
+    /// 创建存储缓冲区并写入初始数据（例如粒子的位置/速度数组）。
+    /// wgpu 不允许绑定空的存储缓冲区，`data` 为空时返回 `None`
+    pub fn create_storage_buffer(&mut self, label: &str, data: &[u8]) -> Option<StorageBufferHandle> {
+        if data.is_empty() {
+            error!("Storage buffer '{}' has no data, storage buffers cannot be empty.", label);
+            return None;
+        }
+        Some(self.storage_buffers.insert(SizedBuffer::new_storage(label, &self.context.device, data)))
+    }
+
+    /// 读回存储缓冲区的内容（例如计算着色器的结果），会先提交排队的计算调度，并阻塞直到 GPU 执行完之前提交的工作
+    pub fn read_storage_buffer(&mut self, handle: StorageBufferHandle) -> Option<Vec<u8>> {
+        self.submit_compute_dispatches();
+        let Some(buffer) = self.storage_buffers.get(handle) else {
+            error!("Storage buffer {:?} not found.", handle);
+            return None;
+        };
+        buffer.read_back(&self.context.device, &self.context.queue)
+    }
+
+    /// 读回渲染目标（MSAA resolve 之后）的像素，按行紧密排列，格式与渲染目标相同（默认为 RGBA8 sRGB）。
+    /// 会阻塞直到 GPU 执行完之前提交的工作，主要用于无窗口模式下的图像比对测试
+    pub fn read_render_target(&self, handle: RenderTargetHandle) -> Option<Vec<u8>> {
+        let Some(rt) = self.render_targets.get(handle) else {
+            error!("RenderTarget {:?} not found.", handle);
+            return None;
+        };
+        let Some(pixel_size) = rt.format.block_copy_size(None) else {
+            error!("RenderTarget format {:?} cannot be read back.", rt.format);
+            return None;
+        };
+
+        // 每行的字节数需要按 COPY_BYTES_PER_ROW_ALIGNMENT 对齐，读回后再去掉填充
+        let row_bytes = rt.size.width * pixel_size;
+        let padded_row_bytes = row_bytes.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let staging = SizedBuffer::new(
+            "RenderTarget Readback",
+            &self.context.device,
+            (padded_row_bytes * rt.size.height) as usize,
+            BufferType::Read,
+        );
+
+        let mut encoder = self.context.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("RenderTarget Readback Encoder"),
+        });
+        encoder.copy_texture_to_buffer(
+            rt.resolve_texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &staging.buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_bytes),
+                    rows_per_image: Some(rt.size.height),
+                },
+            },
+            rt.size,
+        );
+        self.context.queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = staging.buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        let _ = self.context.device.poll(wgpu::PollType::wait_indefinitely());
+        receiver.recv().ok()?.ok()?;
+
+        let mapped = slice.get_mapped_range();
+        let mut pixels = Vec::with_capacity((row_bytes * rt.size.height) as usize);
+        for row in mapped.chunks(padded_row_bytes as usize) {
+            pixels.extend_from_slice(&row[..row_bytes as usize]);
+        }
+        drop(mapped);
+        staging.buffer.unmap();
+        Some(pixels)
+    }
+
+    /// 覆盖存储缓冲区的内容，数据比原缓冲区大时重新分配。
+    /// 之前排队的计算调度会先提交，读到的仍是写入前的数据
+    pub fn write_storage_buffer(&mut self, handle: StorageBufferHandle, data: &[u8]) -> bool {
+        self.submit_compute_dispatches();
+        let Some(buffer) = self.storage_buffers.get_mut(handle) else {
+            error!("Storage buffer {:?} not found.", handle);
+            return false;
+        };
+        buffer.ensure_size_and_copy(&self.context.device, &self.context.queue, data);
+        true
+    }
+
+    pub fn delete_storage_buffer(&mut self, handle: StorageBufferHandle) -> bool {
+        // 排队的调度可能仍引用这个缓冲区，销毁之前先提交
+        self.submit_compute_dispatches();
+        match self.storage_buffers.remove(handle) {
+            Some(buffer) => {
+                buffer.buffer.destroy();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 排队一次计算调度，`bind_data[i]` 绑定到 `@group(0) @binding(i)`。
+    /// 调度录制到下一次 `flush` 或帧末绘制的 encoder 中，在同一批绘制之前执行；
+    /// 读写存储缓冲区之前会先提交排队的调度。
+    /// 绑定缺失、缓冲区过小或工作组数量超出设备限制时打印错误并跳过这次调度
+    pub fn dispatch_compute(
+        &mut self,
+        handle: ComputeMaterialHandle,
+        workgroups: glam::UVec3,
+        bind_data: &[StorageBufferHandle],
+    ) {
+        let Some(mat) = self.compute_materials.get(handle) else {
+            error!("Compute material {:?} not found.", handle);
+            return;
+        };
+
+        let max_workgroups = self.context.device.limits().max_compute_workgroups_per_dimension;
+        if workgroups.max_element() > max_workgroups {
+            error!(
+                "Dispatch of '{}' with {} workgroups exceeds the device limit of {} per dimension, skipped.",
+                mat.name, workgroups, max_workgroups
+            );
+            return;
+        }
+
+        // 按反射得到的绑定检查，不符合的调度不能录制到绘制的 encoder 中，否则整帧的提交都会失败
+        let mut entries = Vec::with_capacity(mat.bindings.len());
+        for binding in &mat.bindings {
+            let Some(&buffer_handle) = bind_data.get(binding.binding as usize) else {
+                error!(
+                    "Compute material '{}' expects a storage buffer at binding {}, got {}, dispatch skipped.",
+                    mat.name, binding.binding, bind_data.len()
+                );
+                return;
+            };
+            let Some(buffer) = self.storage_buffers.get(buffer_handle) else {
+                error!("Storage buffer {:?} not found, dispatch of '{}' skipped.", buffer_handle, mat.name);
+                return;
+            };
+            if buffer.buffer.size() < binding.min_size {
+                error!(
+                    "Storage buffer '{}' ({} bytes) is smaller than binding {} of '{}' ({} bytes), dispatch skipped.",
+                    buffer.label, buffer.buffer.size(), binding.binding, mat.name, binding.min_size
+                );
+                return;
+            }
+            entries.push(BindGroupEntry {
+                binding: binding.binding,
+                resource: buffer.buffer.as_entire_binding(),
+            });
+        }
+
+        let bind_group = self.context.device.create_bind_group(&BindGroupDescriptor {
+            label: Some(&format!("{} Compute Bind Group", mat.name)),
+            layout: &mat.bind_group_layout,
+            entries: &entries,
+        });
+        self.pending_dispatches.push(ComputeDispatch {
+            material: handle,
+            bind_group,
+            workgroups,
+        });
+    }
+
+    // 把排队的计算调度录制到 encoder 中，它们在同一 encoder 之后录制的渲染 Pass 之前执行
+    fn encode_compute_dispatches(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if self.pending_dispatches.is_empty() {
+            return;
+        }
+
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Compute Pass"),
+            timestamp_writes: None,
+        });
+        for dispatch in self.pending_dispatches.drain(..) {
+            let Some(mat) = self.compute_materials.get(dispatch.material) else {
+                continue;
+            };
+            pass.set_pipeline(&mat.pipeline);
+            pass.set_bind_group(0, &dispatch.bind_group, &[]);
+            pass.dispatch_workgroups(dispatch.workgroups.x, dispatch.workgroups.y, dispatch.workgroups.z);
+            self.frame_stats.compute_dispatches += 1;
+        }
+    }
+
+    // 单独提交排队的计算调度，用于读写存储缓冲区之前保证调度的顺序
+    fn submit_compute_dispatches(&mut self) {
+        if self.pending_dispatches.is_empty() {
+            return;
+        }
+
+        let mut encoder =
+            self.context
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Compute Encoder"),
+                });
+        self.encode_compute_dispatches(&mut encoder);
+        self.context.queue.submit(std::iter::once(encoder.finish()));
+    }
+}
+
+// 纹理绘制部分
+impl WgpuState {
+    /// 纹理的像素尺寸，纹理不存在时返回 `None`
+    pub fn texture_size(&self, texture: Texture2DHandle) -> Option<UVec2> {
+        self.texture2ds.get(texture).map(|t| t.size)
+    }
+
+    // 预留纹理句柄，上传之前绘制该句柄会使用白色纹理
+    pub(crate) fn reserve_texture(&mut self) -> Texture2DHandle {
+        self.texture2ds.reserve()
+    }
+
+    // 把后台线程解码好的图像上传到预留的句柄
+    pub(crate) fn upload_reserved_texture(&mut self, handle: Texture2DHandle, image: &image::RgbaImage) -> bool {
+        let texture = self.context.create_texture_from_rgba(
+            image,
+            Some("Asset Texture"),
+            wgpu::AddressMode::ClampToEdge,
+            self.blitter.bind_group_layout(),
+        );
+        self.texture2ds.insert_reserved(handle, texture)
+    }
+
+    /// 把纹理的一部分绘制到 `dest`（`(x, y)` 为左下角），用于精灵图集。
+    ///
+    /// `src` 为纹理上的像素区域，`(x, y)` 为左上角（与图片坐标一致）。`tint` 写入顶点颜色，不会打断合批。
+    pub fn draw_texture_region(
+        &mut self,
+        texture: Texture2DHandle,
+        src: Rect,
+        dest: Rect,
+        tint: wgpu::Color,
+        z_order: u32,
+    ) {
+        let Some(texture_size) = self.texture_size(texture).map(|size| size.as_vec2()) else {
+            error!("draw_texture_region: texture {:?} not found.", texture);
+            return;
+        };
+
+        let uv_min = vec2(src.x, src.y) / texture_size;
+        let uv_max = vec2(src.x + src.w, src.y + src.h) / texture_size;
+        let vertices = rect_vertices_uv(dest, uv_min, uv_max, tint);
+        self.record_draw_command_ex(&vertices, &RECT_INDICES, z_order, self.textured_mat, Some(texture));
+    }
+
+    /// 把整张纹理绘制到 `dest`（`(x, y)` 为左下角），纹理颜色乘以 `tint`。
+    ///
+    /// `tint` 在 CPU 上写入顶点颜色，不会打断合批：同一纹理的多个精灵即使 `tint` 各不相同，
+    /// 也仍在同一次绘制调用中。
+    pub fn draw_texture(&mut self, texture: Texture2DHandle, dest: Rect, tint: wgpu::Color, z_order: u32) {
+        if self.texture2ds.get(texture).is_none() {
+            error!("draw_texture: texture {:?} not found.", texture);
+            return;
+        }
+
+        let vertices = rect_vertices(dest, tint);
+        self.record_draw_command_ex(&vertices, &RECT_INDICES, z_order, self.textured_mat, Some(texture));
+    }
+
+    /// 九宫格绘制，适合可缩放的 UI 面板背景。
+    ///
+    /// `dest` 的 `(x, y)` 为左下角，`w/h` 为宽高；`border` 为源纹理上 (左, 右, 上, 下) 的边框像素宽度。
+    /// 四个角保持原始大小，四条边和中心拉伸；目标小于两侧边框之和时按比例缩小边框。
+    /// 九个四边形共用同一纹理，作为一个绘制命令提交。
+    pub fn draw_nine_slice(
+        &mut self,
+        texture: Texture2DHandle,
+        dest: Rect,
+        border: (f32, f32, f32, f32),
+        color: wgpu::Color,
+        z_order: u32,
+    ) {
+        let Some(texture_size) = self.texture2ds.get(texture).map(|t| t.size.as_vec2()) else {
+            error!("draw_nine_slice: texture {:?} not found.", texture);
+            return;
+        };
+
+        let (left, right, top, bottom) = border;
+        let (left, right, top, bottom) = (left.max(0.0), right.max(0.0), top.max(0.0), bottom.max(0.0));
+
+        // 目标尺寸放不下两侧边框时，按比例缩小边框
+        let scale_x = if left + right > dest.w && left + right > 0.0 { dest.w.max(0.0) / (left + right) } else { 1.0 };
+        let scale_y = if top + bottom > dest.h && top + bottom > 0.0 { dest.h.max(0.0) / (top + bottom) } else { 1.0 };
+
+        // 目标上的分割线（x 从左到右，y 从下到上）
+        let xs = [dest.x, dest.x + left * scale_x, dest.x + dest.w - right * scale_x, dest.x + dest.w];
+        let ys = [dest.y, dest.y + bottom * scale_y, dest.y + dest.h - top * scale_y, dest.y + dest.h];
+        // 源纹理上对应的 UV（v 从上到下，所以 y 方向反过来）
+        let us = [0.0, left / texture_size.x, 1.0 - right / texture_size.x, 1.0];
+        let vs = [1.0, 1.0 - bottom / texture_size.y, top / texture_size.y, 0.0];
+
+        let mut vertices = Vec::with_capacity(36);
+        let mut indices = Vec::with_capacity(54);
+        for row in 0..3 {
+            for col in 0..3 {
+                if xs[col + 1] <= xs[col] || ys[row + 1] <= ys[row] {
+                    continue;
+                }
+
+                let base = vertices.len() as u32;
+                vertices.extend_from_slice(&[
+                    // 0: Top-left
+                    Vertex::new(vec3(xs[col], ys[row + 1], 0.0), vec2(us[col], vs[row + 1]), color),
+                    // 1: Top-right
+                    Vertex::new(vec3(xs[col + 1], ys[row + 1], 0.0), vec2(us[col + 1], vs[row + 1]), color),
+                    // 2: Bottom-right
+                    Vertex::new(vec3(xs[col + 1], ys[row], 0.0), vec2(us[col + 1], vs[row]), color),
+                    // 3: Bottom-left
+                    Vertex::new(vec3(xs[col], ys[row], 0.0), vec2(us[col], vs[row]), color),
+                ]);
+                indices.extend([3, 2, 0, 0, 2, 1].map(|i| base + i));
+            }
+        }
+
+        if indices.is_empty() {
+            return;
+        }
+        self.record_draw_command_ex(&vertices, &indices, z_order, self.textured_mat, Some(texture));
+    }
+}
+
+// 文字部分
+impl WgpuState {
+    /// 从内存中加载 TrueType/OpenType 字体
+    pub fn load_font_from_memory(&mut self, bytes: Vec<u8>) -> Option<FontHandle> {
+        match FontArc::try_from_vec(bytes) {
+            Ok(font) => Some(self.fonts.insert(Font { font })),
+            Err(err) => {
+                error!("font load error: {}", err);
+                None
+            }
+        }
+    }
+
+    /// 从文件加载 TrueType/OpenType 字体
+    pub async fn load_font(&mut self, file_path: &str) -> Option<FontHandle> {
+        match tokio::fs::read(file_path).await {
+            Ok(bytes) => self.load_font_from_memory(bytes),
+            Err(err) => {
+                error!("font load error: {}: {}", file_path, err);
+                None
+            }
+        }
+    }
+
+    /// 设置 `draw_text` 使用的字体，字体通过 `load_font` 加载
+    pub fn set_font(&mut self, font: Option<FontHandle>) {
+        self.current_font = font;
+    }
+
+    // 获取 (字体, 像素大小) 对应的图集，不存在时创建
+    fn glyph_atlas_for(&mut self, font: FontHandle, px_size: u32) -> GlyphAtlasHandle {
+        if let Some(&atlas) = self.glyph_atlas_lookup.get(&(font, px_size)) {
+            return atlas;
+        }
+
+        let texture = self.texture2ds.insert(GlyphAtlas::create_texture(
+            &self.context,
+            self.blitter.bind_group_layout(),
+        ));
+        let size = self.texture2ds.get(texture).map_or(UVec2::ZERO, |t| t.size);
+        let atlas = self
+            .glyph_atlases
+            .insert(GlyphAtlas::new(texture, px_size, size));
+        self.glyph_atlas_lookup.insert((font, px_size), atlas);
+        atlas
+    }
+
+    /// 使用当前字体绘制文本，`position` 为第一行的左上角，`size` 为字体像素大小。
+    /// 每个字形一个四边形，同一字体大小的文本共享图集纹理，可以合批。
+    pub fn draw_text(
+        &mut self,
+        text: &str,
+        position: glam::Vec2,
+        size: f32,
+        color: wgpu::Color,
+        z_order: u32,
+    ) {
+        let Some(font_handle) = self
+            .current_font
+            .filter(|&font| self.fonts.get(font).is_some())
+        else {
+            error!("draw_text requires a font, call set_font first.");
+            return;
+        };
+
+        let px_size = (size.round() as u32).max(1);
+        let atlas_handle = self.glyph_atlas_for(font_handle, px_size);
+
+        let (Some(atlas), Some(font)) = (
+            self.glyph_atlases.get_mut(atlas_handle),
+            self.fonts.get(font_handle),
+        ) else {
+            return;
+        };
+        let Some(texture) = self.texture2ds.get(atlas.texture) else {
+            return;
+        };
+
+        let (vertices, indices) =
+            atlas.layout(&self.context, texture, &font.font, text, position, color);
+        if indices.is_empty() {
+            return;
+        }
+
+        let texture = Some(atlas.texture);
+        self.record_draw_command_ex(&vertices, &indices, z_order, self.textured_mat, texture);
+    }
+}
+
+/// 录制一个只清除颜色、深度和模板的 RenderPass
+fn encode_clear_pass(
+    encoder: &mut wgpu::CommandEncoder,
+    render_target: &RenderTarget,
+    color: wgpu::Color,
+) {
+    // 确定用于渲染的视图和解析视图。
+    let (view_to_render_to, resolve_target_view) = if render_target.msaa_texture_view.is_some() {
+        (
+            render_target.msaa_texture_view.as_ref().unwrap(),
+            Some(&render_target.resolve_texture_view),
+        )
+    } else {
+        (&render_target.resolve_texture_view, None)
+    };
+
+    // 配置深度/模板附件
+    let depth_stencil_attachment =
+        render_target.depth_texture_view.as_ref().map(|depth_view| {
+            wgpu::RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0), // 清除深度到 1.0 (最远)
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(0), // 清除模板到 0
+                    store: wgpu::StoreOp::Store,
+                }),
+            }
+        });
+
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Clear Render Pass"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: view_to_render_to,
+            resolve_target: resolve_target_view,
+            ops: wgpu::Operations {
+                load: PassAction::Clear(color).load_op(),
+                store: wgpu::StoreOp::Store,
+            },
+            depth_slice: None,
+        })],
+        depth_stencil_attachment,
+        timestamp_writes: None,
+        occlusion_query_set: None,
+        multiview_mask: None,
+    });
+}
@@ -0,0 +1,584 @@
+use std::time::Duration;
+
+use log::error;
+use unm_sfx::player::SfxManager;
+
+use crate::{
+    assets::Assets,
+    game_loop::GameLoop,
+    game_settings::GameSettings,
+    graphics::WgpuState,
+    input::{KeyInput, MouseInput, TouchInput},
+    tools::TimeManager,
+};
+
+/// 不使用事件循环驱动 `GameLoop`：调用一次 `start`，然后运行 `frames` 帧，最后调用 `on_exit`。
+///
+/// `graphics` 通常由 `WgpuState::new_headless` 创建。每帧的顺序与窗口模式相同
+/// （`fixed_update`、`update`、绘制、后处理、`end_frame`），只是不呈现到 Surface，
+/// 结束后可用 `read_render_target(get_default_render_target())` 读回最后一帧。
+/// 输入始终为空，时间按真实时间流逝；游戏调用 `GameSettings::quit` 时提前结束
+pub async fn run_frames(game: &mut dyn GameLoop, graphics: &mut WgpuState, frames: u32) -> anyhow::Result<()> {
+    let mut sfx_manager = SfxManager::new();
+    let mut assets = Assets::new();
+    let mouse_input = MouseInput::new();
+    let touch_input = TouchInput::new();
+    let mut key_input = KeyInput::new();
+
+    let mut game_settings = GameSettings::new_headless();
+    game_settings.current_window_size = graphics.size;
+    game_settings.supported_msaa = graphics.supported_sample_counts();
+    graphics.apply_batch_limits(&game_settings);
+    game.start(&mut game_settings, graphics, &mut sfx_manager, &mut assets).await?;
+    graphics.end_frame(&mut game_settings);
+
+    let mut time_manager = TimeManager::with_frame_history_len(game_settings.get_frame_history_len());
+    for _ in 0..frames {
+        time_manager.set_frame_history_len(game_settings.get_frame_history_len());
+        time_manager.set_max_delta_time(game_settings.get_max_delta_time());
+        time_manager.update();
+
+        graphics.prepare_for_new_frame(game_settings.get_clear_color());
+
+        assets.process(graphics, &mut sfx_manager);
+
+        if let Some(hz) = game_settings.get_fixed_timestep() {
+            let step = Duration::from_secs_f32(1.0 / hz);
+            let steps = time_manager.advance_fixed_steps(step, game_settings.get_max_fixed_steps());
+            for _ in 0..steps {
+                if let Err(e) = game.fixed_update(&mut game_settings, step.as_secs_f32()).await {
+                    error!("Game fixed update failed: {:#}", e);
+                    if !game.on_error(&e) {
+                        game.on_exit().await;
+                        return Err(e);
+                    }
+                }
+            }
+        }
+
+        let result = game
+            .update(
+                &mut game_settings,
+                graphics,
+                &time_manager,
+                &mut sfx_manager,
+                &mut assets,
+                &mouse_input,
+                &touch_input,
+                &mut key_input,
+            )
+            .await;
+        if let Err(e) = result {
+            error!("Game update failed: {:#}", e);
+            if !game.on_error(&e) {
+                game.on_exit().await;
+                return Err(e);
+            }
+        }
+
+        graphics.draw();
+        graphics.apply_post_process();
+        graphics.draw_debug();
+        graphics.finish_headless_frame();
+        graphics.end_frame(&mut game_settings);
+
+        if game_settings.quit_requested {
+            break;
+        }
+    }
+
+    game.on_exit().await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+
+    use glam::{uvec2, vec2, vec3, UVec2};
+    use unm_sfx::player::SfxManager;
+
+    use super::run_frames;
+    use crate::{
+        assets::Assets,
+        game_loop::GameLoop,
+        game_settings::GameSettings,
+        camera::Rect,
+        graphics::{rect_vertices_uv, PassAction, WgpuState, RECT_INDICES},
+        input::{KeyInput, MouseInput, TouchInput},
+        material::{MaterialDescriptor, MaterialHandle},
+        msaa::Msaa,
+        tools::TimeManager,
+        vertex::Vertex,
+    };
+
+    const SIZE: UVec2 = uvec2(16, 16);
+
+    // 每帧调用一次闭包录制绘制命令的测试游戏
+    struct DrawEachFrame<F>(F);
+
+    #[async_trait]
+    impl<F: FnMut(&mut GameSettings, &mut WgpuState) + Send> GameLoop for DrawEachFrame<F> {
+        async fn start(
+            &mut self,
+            game_settings: &mut GameSettings,
+            _graphics: &mut WgpuState,
+            _sfx_manager: &mut SfxManager,
+            _assets: &mut Assets,
+        ) -> anyhow::Result<()> {
+            // wgpu 的 GL 后端（llvmpipe）无法创建多重采样的深度附件，像素比对的测试关闭 MSAA
+            game_settings.set_msaa(Msaa::Off);
+            Ok(())
+        }
+
+        async fn update(
+            &mut self,
+            game_settings: &mut GameSettings,
+            graphics: &mut WgpuState,
+            _time_manager: &TimeManager,
+            _sfx_manager: &mut SfxManager,
+            _assets: &mut Assets,
+            _mouse_input: &MouseInput,
+            _touch_input: &TouchInput,
+            _key_input: &mut KeyInput,
+        ) -> anyhow::Result<()> {
+            (self.0)(game_settings, graphics);
+            Ok(())
+        }
+    }
+
+    // 设置了该环境变量时，没有可用适配器的机器上跳过 GPU 测试
+    const SKIP_GPU_TESTS_ENV: &str = "UNM_SKIP_GPU_TESTS";
+
+    // 没有可用的适配器时测试失败，CI 需要提供 lavapipe/llvmpipe/WARP；
+    // 只有显式设置 UNM_SKIP_GPU_TESTS 时才跳过
+    fn headless_state() -> Option<WgpuState> {
+        match pollster::block_on(WgpuState::new_headless(SIZE)) {
+            Ok(state) => Some(state),
+            Err(e) if std::env::var_os(SKIP_GPU_TESTS_ENV).is_some() => {
+                eprintln!("skipping headless test, no adapter ({SKIP_GPU_TESTS_ENV} is set): {e:#}");
+                None
+            }
+            Err(e) => panic!("no adapter for headless tests, set {SKIP_GPU_TESTS_ENV} to skip them: {e:#}"),
+        }
+    }
+
+    fn create_basic_material(graphics: &mut WgpuState, name: &str, descriptor: MaterialDescriptor) -> MaterialHandle {
+        let shader = include_str!("shaders/BasicShapes.wgsl").to_string();
+        pollster::block_on(graphics.create_material(name.to_owned(), shader, descriptor, None))
+            .expect("failed to create test material")
+    }
+
+    // 运行 `frames` 帧后读回默认渲染目标
+    fn render(graphics: &mut WgpuState, frames: u32, draw: impl FnMut(&mut GameSettings, &mut WgpuState) + Send) -> Vec<u8> {
+        let mut game = DrawEachFrame(draw);
+        pollster::block_on(run_frames(&mut game, graphics, frames)).expect("run_frames failed");
+        graphics
+            .read_render_target(graphics.get_default_render_target())
+            .expect("failed to read back the default render target")
+    }
+
+    fn pixel(pixels: &[u8], x: u32, y: u32) -> [u8; 4] {
+        let i = ((y * SIZE.x + x) * 4) as usize;
+        pixels[i..i + 4].try_into().unwrap()
+    }
+
+    // 覆盖整个画面、位于 `z` 处的矩形
+    fn full_screen_quad(z: f32, color: wgpu::Color) -> [Vertex; 4] {
+        let half = SIZE.as_vec2();
+        [
+            Vertex::new(vec3(-half.x, half.y, z), vec2(0.0, 0.0), color),
+            Vertex::new(vec3(half.x, half.y, z), vec2(1.0, 0.0), color),
+            Vertex::new(vec3(half.x, -half.y, z), vec2(1.0, 1.0), color),
+            Vertex::new(vec3(-half.x, -half.y, z), vec2(0.0, 1.0), color),
+        ]
+    }
+
+    #[test]
+    fn opaque_overlap_resolves_by_depth() {
+        let Some(mut graphics) = headless_state() else {
+            return;
+        };
+        let opaque = create_basic_material(&mut graphics, "Test Opaque", MaterialDescriptor::opaque());
+
+        // 相机看向 -Z，z 越大越近。近处的红色先录制，远处的蓝色后录制：
+        // 只有近处的矩形写入了深度，远处的矩形才会被深度测试丢弃
+        let pixels = render(&mut graphics, 1, move |_, graphics| {
+            graphics.draw_mesh(&full_screen_quad(10.0, wgpu::Color::RED), &RECT_INDICES, opaque, 0);
+            graphics.draw_mesh(&full_screen_quad(-10.0, wgpu::Color::BLUE), &RECT_INDICES, opaque, 0);
+        });
+        assert_eq!(pixel(&pixels, SIZE.x / 2, SIZE.y / 2), [255, 0, 0, 255]);
+
+        // 录制顺序相反时结果相同
+        let pixels = render(&mut graphics, 1, move |_, graphics| {
+            graphics.draw_mesh(&full_screen_quad(-10.0, wgpu::Color::BLUE), &RECT_INDICES, opaque, 0);
+            graphics.draw_mesh(&full_screen_quad(10.0, wgpu::Color::RED), &RECT_INDICES, opaque, 0);
+        });
+        assert_eq!(pixel(&pixels, SIZE.x / 2, SIZE.y / 2), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn opaque_overlap_at_equal_depth_keeps_z_order() {
+        let Some(mut graphics) = headless_state() else {
+            return;
+        };
+        let opaque = create_basic_material(&mut graphics, "Test Opaque", MaterialDescriptor::opaque());
+
+        // 2D 精灵都位于 z = 0：先录制 z_order 更大的绿色，后录制的红色先画，
+        // 绿色写入相同的深度时仍需通过深度测试覆盖红色
+        let pixels = render(&mut graphics, 1, move |_, graphics| {
+            graphics.draw_mesh(&full_screen_quad(0.0, wgpu::Color::GREEN), &RECT_INDICES, opaque, 1);
+            graphics.draw_mesh(&full_screen_quad(0.0, wgpu::Color::RED), &RECT_INDICES, opaque, 0);
+        });
+        assert_eq!(pixel(&pixels, SIZE.x / 2, SIZE.y / 2), [0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn depth_survives_flush() {
+        let Some(mut graphics) = headless_state() else {
+            return;
+        };
+        let opaque = create_basic_material(&mut graphics, "Test Opaque", MaterialDescriptor::opaque());
+
+        // flush 拆分出新的 Pass，近处红色写入的深度仍然遮挡之后绘制的远处蓝色
+        let pixels = render(&mut graphics, 1, move |_, graphics| {
+            graphics.draw_mesh(&full_screen_quad(10.0, wgpu::Color::RED), &RECT_INDICES, opaque, 0);
+            graphics.flush();
+            graphics.draw_mesh(&full_screen_quad(-10.0, wgpu::Color::BLUE), &RECT_INDICES, opaque, 0);
+        });
+        assert_eq!(pixel(&pixels, SIZE.x / 2, SIZE.y / 2), [255, 0, 0, 255]);
+
+        // 深度每帧重新清除：第 2 帧只画远处的蓝色，不会被上一帧的深度遮挡
+        let mut frame = 0;
+        let pixels = render(&mut graphics, 2, move |_, graphics| {
+            frame += 1;
+            if frame == 1 {
+                graphics.draw_mesh(&full_screen_quad(10.0, wgpu::Color::RED), &RECT_INDICES, opaque, 0);
+            } else {
+                graphics.draw_mesh(&full_screen_quad(-10.0, wgpu::Color::BLUE), &RECT_INDICES, opaque, 0);
+            }
+        });
+        assert_eq!(pixel(&pixels, SIZE.x / 2, SIZE.y / 2), [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn stencil_mask_survives_flush() {
+        let Some(mut graphics) = headless_state() else {
+            return;
+        };
+        // 遮罩只写模板不写颜色
+        let mask = create_basic_material(
+            &mut graphics,
+            "Test Mask",
+            MaterialDescriptor::alpha_blend()
+                .with_color_write(wgpu::ColorWrites::empty())
+                .with_stencil_write(1),
+        );
+        let masked = create_basic_material(
+            &mut graphics,
+            "Test Masked",
+            MaterialDescriptor::alpha_blend().with_stencil_test(1),
+        );
+        let half = SIZE.as_vec2() / 2.0;
+        let left = Rect { x: -half.x, y: -half.y, w: half.x, h: SIZE.y as f32 };
+        let left_quad = rect_vertices_uv(left, vec2(0.0, 0.0), vec2(1.0, 1.0), wgpu::Color::WHITE);
+
+        // 遮罩与被遮罩的绘制之间分别经过 flush 和 set_pass_action(Load) 拆分出的新 Pass
+        for split in [WgpuState::flush as fn(&mut WgpuState), |g| g.set_pass_action(PassAction::Load)] {
+            let pixels = render(&mut graphics, 1, move |_, graphics| {
+                graphics.draw_mesh(&left_quad, &RECT_INDICES, mask, 0);
+                split(graphics);
+                graphics.draw_mesh(&full_screen_quad(0.0, wgpu::Color::GREEN), &RECT_INDICES, masked, 0);
+            });
+            assert_eq!(pixel(&pixels, SIZE.x / 4, SIZE.y / 2), [0, 255, 0, 255]);
+            assert_eq!(pixel(&pixels, SIZE.x * 3 / 4, SIZE.y / 2), [0, 0, 0, 255]);
+        }
+
+        // 模板每帧重新清除：第 2 帧没有遮罩，被遮罩的绘制不可见。
+        // 第 2 帧的第一个 Pass 不使用模板，之后 flush 出的 Pass 也不能读到上一帧的遮罩
+        let mut frame = 0;
+        let right = Rect { x: 0.0, y: -half.y, w: half.x, h: SIZE.y as f32 };
+        let pixels = render(&mut graphics, 2, move |_, graphics| {
+            frame += 1;
+            if frame == 1 {
+                graphics.draw_mesh(&left_quad, &RECT_INDICES, mask, 0);
+            } else {
+                graphics.draw_rectangle(right, wgpu::Color::RED, wgpu::Color::WHITE, 0);
+                graphics.flush();
+                graphics.draw_mesh(&full_screen_quad(0.0, wgpu::Color::GREEN), &RECT_INDICES, masked, 0);
+            }
+        });
+        assert_eq!(pixel(&pixels, SIZE.x / 4, SIZE.y / 2), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn stencil_mask_does_not_occlude() {
+        let Some(mut graphics) = headless_state() else {
+            return;
+        };
+        let mask = create_basic_material(
+            &mut graphics,
+            "Test Mask",
+            MaterialDescriptor::alpha_blend()
+                .with_color_write(wgpu::ColorWrites::empty())
+                .with_stencil_write(1),
+        );
+        let opaque = create_basic_material(&mut graphics, "Test Opaque", MaterialDescriptor::opaque());
+
+        // 遮罩位于近处并先绘制，不写入深度，之后远处不使用模板的绘制不会被它挡住
+        let pixels = render(&mut graphics, 1, move |_, graphics| {
+            graphics.draw_mesh(&full_screen_quad(10.0, wgpu::Color::WHITE), &RECT_INDICES, mask, 0);
+            graphics.draw_mesh(&full_screen_quad(-10.0, wgpu::Color::BLUE), &RECT_INDICES, opaque, 0);
+        });
+        assert_eq!(pixel(&pixels, SIZE.x / 2, SIZE.y / 2), [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn translucent_overlap_keeps_recording_order() {
+        let Some(mut graphics) = headless_state() else {
+            return;
+        };
+        // 先创建的材质句柄更小：如果按材质排序，后录制的蓝色会先画，被红色盖住
+        let blue_mat = create_basic_material(&mut graphics, "Test Blue", MaterialDescriptor::alpha_blend());
+        let red_mat = create_basic_material(&mut graphics, "Test Red", MaterialDescriptor::alpha_blend());
+        let red = wgpu::Color { r: 1.0, g: 0.0, b: 0.0, a: 0.5 };
+        let blue = wgpu::Color { r: 0.0, g: 0.0, b: 1.0, a: 0.5 };
+
+        // 同一 z_order：后录制的在上面
+        let pixels = render(&mut graphics, 1, move |_, graphics| {
+            graphics.draw_mesh(&full_screen_quad(0.0, red), &RECT_INDICES, red_mat, 0);
+            graphics.draw_mesh(&full_screen_quad(0.0, blue), &RECT_INDICES, blue_mat, 0);
+        });
+        let [r, _, b, _] = pixel(&pixels, SIZE.x / 2, SIZE.y / 2);
+        assert!(b > r, "blue should be blended over red, got r={r} b={b}");
+
+        // z_order 优先于录制顺序
+        let pixels = render(&mut graphics, 1, move |_, graphics| {
+            graphics.draw_mesh(&full_screen_quad(0.0, blue), &RECT_INDICES, blue_mat, 1);
+            graphics.draw_mesh(&full_screen_quad(0.0, red), &RECT_INDICES, red_mat, 0);
+        });
+        let [r, _, b, _] = pixel(&pixels, SIZE.x / 2, SIZE.y / 2);
+        assert!(b > r, "the higher z_order should be blended last, got r={r} b={b}");
+    }
+
+    #[test]
+    fn custom_batch_limits_split_draws() {
+        let Some(mut graphics) = headless_state() else {
+            return;
+        };
+        let opaque = create_basic_material(&mut graphics, "Test Opaque", MaterialDescriptor::opaque());
+        let half = SIZE.as_vec2() / 2.0;
+        let left = Rect { x: -half.x, y: -half.y, w: half.x, h: SIZE.y as f32 };
+        let right = Rect { x: 0.0, y: -half.y, w: half.x, h: SIZE.y as f32 };
+        let mut vertices = rect_vertices_uv(left, vec2(0.0, 0.0), vec2(1.0, 1.0), wgpu::Color::RED).to_vec();
+        vertices.extend(rect_vertices_uv(right, vec2(0.0, 0.0), vec2(1.0, 1.0), wgpu::Color::GREEN));
+        let indices: Vec<u32> = RECT_INDICES.into_iter().chain(RECT_INDICES.map(|i| i + 4)).collect();
+
+        // 第 1 帧设置上限，之后的帧中两个矩形组成的网格超出一个 DrawCall 的上限，拆分成两个
+        let mut frame = 0;
+        let pixels = render(&mut graphics, 3, move |game_settings, graphics| {
+            frame += 1;
+            if frame == 1 {
+                game_settings.set_batch_limits(4, 6);
+            } else {
+                graphics.draw_mesh(&vertices, &indices, opaque, 0);
+            }
+        });
+        assert_eq!(pixel(&pixels, SIZE.x / 4, SIZE.y / 2), [255, 0, 0, 255]);
+        assert_eq!(pixel(&pixels, SIZE.x * 3 / 4, SIZE.y / 2), [0, 255, 0, 255]);
+        // 最后一帧开始时保存的是第 2 帧的统计数据
+        assert_eq!(graphics.last_frame_stats().draw_calls, 2);
+    }
+
+    #[test]
+    fn storage_buffer_round_trip() {
+        let Some(mut graphics) = headless_state() else {
+            return;
+        };
+        let initial: Vec<u32> = (0..64).collect();
+        let buffer = graphics
+            .create_storage_buffer("Test Storage", bytemuck::cast_slice(&initial))
+            .expect("failed to create test storage buffer");
+        assert_eq!(graphics.read_storage_buffer(buffer).as_deref(), Some(bytemuck::cast_slice(&initial)));
+
+        let written: Vec<u32> = (0..64).map(|i| i * 3 + 1).collect();
+        assert!(graphics.write_storage_buffer(buffer, bytemuck::cast_slice(&written)));
+        assert_eq!(graphics.read_storage_buffer(buffer).as_deref(), Some(bytemuck::cast_slice(&written)));
+
+        // 计算着色器原地把每个元素乘 2
+        let shader = r#"
+            @group(0) @binding(0) var<storage, read_write> data: array<u32>;
+
+            @compute @workgroup_size(64)
+            fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+                data[id.x] = data[id.x] * 2u;
+            }
+        "#;
+        let double = pollster::block_on(graphics.create_compute_material("Test Double".to_owned(), shader.to_owned(), "main"))
+            .expect("failed to create test compute material");
+        graphics.dispatch_compute(double, glam::UVec3::ONE, &[buffer]);
+
+        let expected: Vec<u32> = written.iter().map(|v| v * 2).collect();
+        assert_eq!(graphics.read_storage_buffer(buffer).as_deref(), Some(bytemuck::cast_slice(&expected)));
+
+        // 调度排队到帧末与绘制一起提交；缺少绑定的调度被跳过，不影响整帧的提交
+        render(&mut graphics, 1, move |_, graphics| {
+            graphics.dispatch_compute(double, glam::UVec3::ONE, &[buffer]);
+            graphics.dispatch_compute(double, glam::UVec3::ONE, &[]);
+        });
+        let expected: Vec<u32> = expected.iter().map(|v| v * 2).collect();
+        assert_eq!(graphics.read_storage_buffer(buffer).as_deref(), Some(bytemuck::cast_slice(&expected)));
+
+        assert!(graphics.create_storage_buffer("Test Empty", &[]).is_none());
+    }
+
+    #[test]
+    fn msaa_toggle_rebuilds_targets() {
+        let Some(mut graphics) = headless_state() else {
+            return;
+        };
+        let expected = Msaa::Sample4.clamp_to(&graphics.supported_sample_counts());
+        let opaque = create_basic_material(&mut graphics, "Test Opaque", MaterialDescriptor::opaque());
+
+        // 第 1 帧请求 Sample4，第 2 帧以新的采样数绘制后关闭，第 3 帧关闭 MSAA 后绘制
+        let mut frame = 0;
+        let mut seen = Vec::new();
+        let pixels = render(&mut graphics, 3, |game_settings, graphics| {
+            frame += 1;
+            seen.push(game_settings.get_msaa());
+            match frame {
+                1 => game_settings.set_msaa(Msaa::Sample4),
+                2 => game_settings.set_msaa(Msaa::Off),
+                _ => {}
+            }
+            if frame > 1 {
+                graphics.draw_mesh(&full_screen_quad(0.0, wgpu::Color::RED), &RECT_INDICES, opaque, 0);
+            }
+        });
+        assert_eq!(seen, [Msaa::Off, expected, Msaa::Off]);
+        assert_eq!(pixel(&pixels, SIZE.x / 2, SIZE.y / 2), [255, 0, 0, 255]);
+    }
+
+    // 记录 run_frames 调用了哪些回调的测试游戏，`quit_after`/`fail_after` 帧后请求退出或返回错误
+    #[derive(Default)]
+    struct Lifecycle {
+        starts: u32,
+        updates: u32,
+        exits: u32,
+        quit_after: Option<u32>,
+        fail_after: Option<u32>,
+        clear_color: Option<wgpu::Color>,
+    }
+
+    #[async_trait]
+    impl GameLoop for Lifecycle {
+        async fn start(
+            &mut self,
+            game_settings: &mut GameSettings,
+            _graphics: &mut WgpuState,
+            _sfx_manager: &mut SfxManager,
+            _assets: &mut Assets,
+        ) -> anyhow::Result<()> {
+            self.starts += 1;
+            game_settings.set_msaa(Msaa::Off);
+            if let Some(color) = self.clear_color {
+                game_settings.set_clear_color(color);
+            }
+            Ok(())
+        }
+
+        async fn update(
+            &mut self,
+            game_settings: &mut GameSettings,
+            _graphics: &mut WgpuState,
+            _time_manager: &TimeManager,
+            _sfx_manager: &mut SfxManager,
+            _assets: &mut Assets,
+            _mouse_input: &MouseInput,
+            _touch_input: &TouchInput,
+            _key_input: &mut KeyInput,
+        ) -> anyhow::Result<()> {
+            self.updates += 1;
+            if self.fail_after == Some(self.updates) {
+                anyhow::bail!("test failure");
+            }
+            if self.quit_after == Some(self.updates) {
+                game_settings.quit();
+            }
+            Ok(())
+        }
+
+        async fn on_exit(&mut self) {
+            self.exits += 1;
+        }
+    }
+
+    #[test]
+    fn run_frames_drives_the_game_loop() {
+        let Some(mut graphics) = headless_state() else {
+            return;
+        };
+        let mut game = Lifecycle::default();
+        pollster::block_on(run_frames(&mut game, &mut graphics, 3)).expect("run_frames failed");
+        assert_eq!((game.starts, game.updates, game.exits), (1, 3, 1));
+
+        // 0 帧时只调用 start 和 on_exit
+        let mut game = Lifecycle::default();
+        pollster::block_on(run_frames(&mut game, &mut graphics, 0)).expect("run_frames failed");
+        assert_eq!((game.starts, game.updates, game.exits), (1, 0, 1));
+    }
+
+    #[test]
+    fn run_frames_stops_on_quit_and_error() {
+        let Some(mut graphics) = headless_state() else {
+            return;
+        };
+        let mut game = Lifecycle { quit_after: Some(2), ..Default::default() };
+        pollster::block_on(run_frames(&mut game, &mut graphics, 10)).expect("run_frames failed");
+        assert_eq!((game.updates, game.exits), (2, 1));
+
+        // 默认的 on_error 返回 false：错误传给调用者，on_exit 仍然调用
+        let mut game = Lifecycle { fail_after: Some(2), ..Default::default() };
+        let result = pollster::block_on(run_frames(&mut game, &mut graphics, 10));
+        assert!(result.is_err());
+        assert_eq!((game.updates, game.exits), (2, 1));
+    }
+
+    #[test]
+    fn run_frames_renders_the_clear_color() {
+        let Some(mut graphics) = headless_state() else {
+            return;
+        };
+        let mut game = Lifecycle { clear_color: Some(wgpu::Color::GREEN), ..Default::default() };
+        pollster::block_on(run_frames(&mut game, &mut graphics, 1)).expect("run_frames failed");
+        let pixels = graphics
+            .read_render_target(graphics.get_default_render_target())
+            .expect("failed to read back the default render target");
+        assert_eq!(pixels.len(), (SIZE.x * SIZE.y * 4) as usize);
+        assert!(pixels.chunks_exact(4).all(|p| p == [0, 255, 0, 255]));
+    }
+
+    // 矩形展开成两个三角形的 6 个顶点，用于不使用索引的绘制
+    fn quad_triangles(dest: Rect, color: wgpu::Color) -> Vec<Vertex> {
+        let corners = rect_vertices_uv(dest, vec2(0.0, 0.0), vec2(1.0, 1.0), color);
+        RECT_INDICES.iter().map(|&i| corners[i as usize]).collect()
+    }
+
+    #[test]
+    fn unindexed_draws_render_alongside_indexed() {
+        let Some(mut graphics) = headless_state() else {
+            return;
+        };
+        let half = SIZE.as_vec2() / 2.0;
+        let left = Rect { x: -half.x, y: -half.y, w: half.x, h: SIZE.y as f32 };
+        let right = Rect { x: 0.0, y: -half.y, w: half.x, h: SIZE.y as f32 };
+
+        let pixels = render(&mut graphics, 1, move |_, graphics| {
+            graphics.draw_triangle_list(&quad_triangles(left, wgpu::Color::RED), 0);
+            graphics.draw_rectangle(right, wgpu::Color::BLUE, wgpu::Color::WHITE, 0);
+            graphics.draw_triangle_list(&quad_triangles(right, wgpu::Color::GREEN), 1);
+        });
+        assert_eq!(pixel(&pixels, SIZE.x / 4, SIZE.y / 2), [255, 0, 0, 255]);
+        assert_eq!(pixel(&pixels, SIZE.x * 3 / 4, SIZE.y / 2), [0, 255, 0, 255]);
+    }
+}
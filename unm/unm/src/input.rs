@@ -4,6 +4,7 @@ use std::sync::{
     atomic::{AtomicBool, Ordering},
     Arc,
 };
+use std::time::{Duration, Instant};
 use winit::event::MouseButton;
 
 /// 定义鼠标按钮状态，用于表示某个按钮当前是否被按下。
@@ -43,7 +44,12 @@ pub struct Touch {
     pub x: f32,
     pub y: f32,
     pub phase: TouchPhase,
-    // Add more fields if needed, e.g., tapCount
+    /// 按压力度，范围 [0, 1]，设备不支持时为 `None`
+    pub force: Option<f32>,
+    /// 连续点击次数：在上一次点击附近、间隔足够短内开始的触控会累加，1 表示单击
+    pub tap_count: u32,
+    /// 触控开始的时间
+    pub start_time: Instant,
 
     // 上一帧的位置，用于计算delta或判断Stationary
     pub prev_x: f32,
@@ -55,7 +61,7 @@ pub struct Touch {
 }
 
 /// 定义需要从主线程发送到渲染线程的鼠标和触控事件。
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub enum InputEvent {
     // 将MouseEvent更名为InputEvent，包含更多类型
     /// 鼠标按钮被按下或释放
@@ -63,6 +69,12 @@ pub enum InputEvent {
         button: MouseButton,
         state: MouseButtonState,
     },
+    /// 鼠标的原始位移（光标被锁定时由 `DeviceEvent::MouseMotion` 产生）
+    MouseMotion { dx: f64, dy: f64 },
+    /// 键盘输入产生的文字（已按键盘布局转换，包含退格 `\u{8}`、回车 `\r` 等控制字符）
+    Text(String),
+    /// 输入法事件（预编辑、提交等）
+    Ime(winit::event::Ime),
     /// 触控事件 (类似 winit::event::Touch)
     Touch(winit::event::Touch), // 鼠标移动事件（可选，如果需要）
                                 // CursorMoved {
@@ -71,21 +83,44 @@ pub enum InputEvent {
                                 // },
 }
 
+/// 双击判定的默认最大间隔（两次按下之间）
+pub const DEFAULT_DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+// 单个鼠标按钮的状态
+#[derive(Debug, Default, Clone, Copy)]
+struct ButtonState {
+    // 当前是否按下
+    current: bool,
+    // 本帧内是否发生过按下/抬起。一帧内可能先按下再抬起（或反过来），两个边沿都要保留
+    pressed_this_frame: bool,
+    released_this_frame: bool,
+    // 当前这次按下的时间、上一次可参与双击的按下时间、本帧是否双击
+    press_time: Option<Instant>,
+    last_press: Option<Instant>,
+    double_clicked: bool,
+}
+
 /// 渲染线程中用于查询鼠标按键状态的结构体。
-#[derive(Debug, Default)]
+/// 支持 winit 报告的所有按钮，包括侧键 `Back`/`Forward` 和 `Other(n)`。
+#[derive(Debug)]
 pub struct MouseInput {
-    // ... 保持不变
-    // 当前帧的鼠标按钮状态
-    left_button_current: bool,
-    right_button_current: bool,
-    middle_button_current: bool,
-    // ... 其他按钮
-
-    // 上一帧的鼠标按钮状态
-    left_button_previous: bool,
-    right_button_previous: bool,
-    middle_button_previous: bool,
-    // ... 其他按钮
+    // 出现过的按钮的状态
+    buttons: HashMap<MouseButton, ButtonState>,
+
+    // 本帧累积的鼠标原始位移，只在光标被锁定时更新
+    motion_delta: (f32, f32),
+
+    double_click_interval: Duration,
+}
+
+impl Default for MouseInput {
+    fn default() -> Self {
+        Self {
+            buttons: HashMap::new(),
+            motion_delta: (0.0, 0.0),
+            double_click_interval: DEFAULT_DOUBLE_CLICK_INTERVAL,
+        }
+    }
 }
 
 impl MouseInput {
@@ -93,61 +128,205 @@ impl MouseInput {
         MouseInput::default()
     }
 
-    /// 在每一帧开始时调用，更新 `previous` 状态。
+    /// 设置双击判定的最大间隔
+    pub fn set_double_click_interval(&mut self, interval: Duration) {
+        self.double_click_interval = interval;
+    }
+
+    /// 在每一帧开始时调用，清除上一帧的按下/抬起边沿。
     /// 必须在处理新的 `InputEvent` 之前调用。
     pub fn begin_frame(&mut self) {
-        self.left_button_previous = self.left_button_current;
-        self.right_button_previous = self.right_button_current;
-        self.middle_button_previous = self.middle_button_current;
+        for state in self.buttons.values_mut() {
+            state.pressed_this_frame = false;
+            state.released_this_frame = false;
+            state.double_clicked = false;
+        }
+        self.motion_delta = (0.0, 0.0);
     }
 
-    /// 检查鼠标左键是否当前被按下 (类似 GetMouseButton)。
+    /// 本帧鼠标的原始位移（未经加速的设备单位），用于第一人称视角控制。
+    /// 只在 `GameSettings::set_cursor_grab(true)` 生效后才有数据。
+    pub fn get_mouse_delta(&self) -> (f32, f32) {
+        self.motion_delta
+    }
+
+    /// 内部方法，累加一次原始鼠标位移。
+    pub fn add_motion(&mut self, dx: f64, dy: f64) {
+        self.motion_delta.0 += dx as f32;
+        self.motion_delta.1 += dy as f32;
+    }
+
+    fn button(&self, button: MouseButton) -> ButtonState {
+        self.buttons.get(&button).copied().unwrap_or_default()
+    }
+
+    /// 检查鼠标按钮是否当前被按下 (类似 GetMouseButton)。
     pub fn get_mouse_button(&self, button: MouseButton) -> bool {
-        match button {
-            MouseButton::Left => self.left_button_current,
-            MouseButton::Right => self.right_button_current,
-            MouseButton::Middle => self.middle_button_current,
-            _ => false, // 暂时不支持其他按钮
-        }
+        self.button(button).current
     }
 
-    /// 检查鼠标左键是否在当前帧被按下 (类似 GetMouseButtonDown)。
+    /// 检查鼠标按钮是否在当前帧被按下 (类似 GetMouseButtonDown)。
+    /// 同一帧内按下又抬起时，`get_mouse_button_down` 和 `get_mouse_button_up` 都返回 true
     pub fn get_mouse_button_down(&self, button: MouseButton) -> bool {
-        match button {
-            MouseButton::Left => self.left_button_current && !self.left_button_previous,
-            MouseButton::Right => self.right_button_current && !self.right_button_previous,
-            MouseButton::Middle => self.middle_button_current && !self.middle_button_previous,
-            _ => false,
-        }
+        self.button(button).pressed_this_frame
     }
 
-    /// 检查鼠标左键是否在当前帧被释放 (类似 GetMouseButtonUp)。
+    /// 检查鼠标按钮是否在当前帧被释放 (类似 GetMouseButtonUp)。
     pub fn get_mouse_button_up(&self, button: MouseButton) -> bool {
-        match button {
-            MouseButton::Left => !self.left_button_current && self.left_button_previous,
-            MouseButton::Right => !self.right_button_current && self.right_button_previous,
-            MouseButton::Middle => !self.middle_button_current && self.middle_button_previous,
-            _ => false,
-        }
+        self.button(button).released_this_frame
+    }
+
+    /// 按钮已经按住的时长，未按下时返回 `None`
+    pub fn get_button_hold_duration(&self, button: MouseButton) -> Option<Duration> {
+        self.button(button).press_time.map(|time| time.elapsed())
+    }
+
+    /// 按钮是否在当前帧完成双击（与上一次按下的间隔不超过 `set_double_click_interval`）。
+    /// 第三次按下不会再次触发，需要重新开始计数
+    pub fn get_mouse_button_double_clicked(&self, button: MouseButton) -> bool {
+        self.button(button).double_clicked
     }
 
     /// 内部方法，根据接收到的事件更新鼠标状态。
-    /// 这个方法只更新 `_current` 状态。
+    /// 一帧内的多次状态变化都会记录为边沿，不会互相覆盖。
     pub fn update_button_state(&mut self, button: MouseButton, state: MouseButtonState) {
-        match button {
-            MouseButton::Left => self.left_button_current = state == MouseButtonState::Pressed,
-            MouseButton::Right => self.right_button_current = state == MouseButtonState::Pressed,
-            MouseButton::Middle => self.middle_button_current = state == MouseButtonState::Pressed,
-            _ => {}
+        let double_click_interval = self.double_click_interval;
+        let button_state = self.buttons.entry(button).or_default();
+        match state {
+            // 重复的按下事件不重新计时
+            MouseButtonState::Pressed if button_state.press_time.is_none() => {
+                button_state.pressed_this_frame = true;
+                let now = Instant::now();
+                match button_state.last_press {
+                    Some(last) if now.duration_since(last) <= double_click_interval => {
+                        button_state.double_clicked = true;
+                        button_state.last_press = None;
+                    }
+                    _ => button_state.last_press = Some(now),
+                }
+                button_state.press_time = Some(now);
+            }
+            MouseButtonState::Pressed => {}
+            MouseButtonState::Released => {
+                button_state.released_this_frame |= button_state.current;
+                button_state.press_time = None;
+            }
+        }
+        button_state.current = state == MouseButtonState::Pressed;
+    }
+
+    /// 内部方法，释放所有按钮（例如窗口失去焦点时收不到抬起事件），本帧会产生对应的 `get_mouse_button_up`。
+    pub fn release_all(&mut self) {
+        for state in self.buttons.values_mut() {
+            state.released_this_frame |= state.current;
+            state.current = false;
+            state.press_time = None;
+            state.last_press = None;
         }
     }
 }
 
-/// 渲染线程中用于查询触控事件的结构体。
+/// 渲染线程中用于获取文字输入的结构体，适合名字输入框等需要实际字符而不是按键的场景。
 #[derive(Debug, Default)]
+pub struct KeyInput {
+    // 本帧输入的文字，包括输入法提交的文字
+    typed_text: String,
+    // 输入法正在组字的内容及光标范围（字节偏移）
+    ime_preedit: String,
+    ime_cursor: Option<(usize, usize)>,
+    ime_enabled: bool,
+}
+
+impl KeyInput {
+    pub fn new() -> Self {
+        KeyInput::default()
+    }
+
+    /// 在每一帧开始时调用，丢弃上一帧没有被读取的文字。
+    pub fn begin_frame(&mut self) {
+        self.typed_text.clear();
+    }
+
+    /// 取出本帧输入的文字，读取后清空。
+    pub fn take_typed_text(&mut self) -> String {
+        std::mem::take(&mut self.typed_text)
+    }
+
+    /// 输入法正在组字（尚未提交）的内容，输入框应在光标处显示它。
+    pub fn get_ime_preedit(&self) -> &str {
+        &self.ime_preedit
+    }
+
+    /// 组字内容中的光标/选区范围（字节偏移），`None` 表示隐藏光标。
+    pub fn get_ime_cursor(&self) -> Option<(usize, usize)> {
+        self.ime_cursor
+    }
+
+    /// 输入法是否处于激活状态。
+    pub fn is_ime_enabled(&self) -> bool {
+        self.ime_enabled
+    }
+
+    /// 内部方法，追加键盘输入的文字。
+    pub fn push_text(&mut self, text: &str) {
+        self.typed_text.push_str(text);
+    }
+
+    /// 内部方法，根据输入法事件更新组字状态。
+    pub fn update_ime(&mut self, ime: winit::event::Ime) {
+        match ime {
+            winit::event::Ime::Enabled => self.ime_enabled = true,
+            winit::event::Ime::Preedit(text, cursor) => {
+                self.ime_preedit = text;
+                self.ime_cursor = cursor;
+            }
+            winit::event::Ime::Commit(text) => {
+                self.typed_text.push_str(&text);
+                self.ime_preedit.clear();
+                self.ime_cursor = None;
+            }
+            winit::event::Ime::Disabled => {
+                self.ime_enabled = false;
+                self.ime_preedit.clear();
+                self.ime_cursor = None;
+            }
+        }
+    }
+}
+
+/// 连续点击判定的默认最大距离（像素）
+pub const DEFAULT_TAP_DISTANCE: f32 = 40.0;
+/// 连续点击判定的默认最大间隔
+pub const DEFAULT_TAP_INTERVAL: Duration = Duration::from_millis(300);
+
+// 上一次结束的触控，用于连续点击判定
+#[derive(Debug, Clone, Copy)]
+struct LastTap {
+    x: f32,
+    y: f32,
+    time: Instant,
+    tap_count: u32,
+}
+
+/// 渲染线程中用于查询触控事件的结构体。
+#[derive(Debug)]
 pub struct TouchInput {
     // 存储所有当前活跃的触控点，key是touch id
     active_touches: HashMap<u64, Touch>,
+    last_tap: Option<LastTap>,
+    tap_distance: f32,
+    tap_interval: Duration,
+}
+
+impl Default for TouchInput {
+    fn default() -> Self {
+        Self {
+            active_touches: HashMap::new(),
+            last_tap: None,
+            tap_distance: DEFAULT_TAP_DISTANCE,
+            tap_interval: DEFAULT_TAP_INTERVAL,
+        }
+    }
 }
 
 impl TouchInput {
@@ -155,6 +334,29 @@ impl TouchInput {
         TouchInput::default()
     }
 
+    /// 设置连续点击判定的最大距离（像素）
+    pub fn set_tap_distance(&mut self, distance: f32) {
+        self.tap_distance = distance.max(0.0);
+    }
+
+    /// 设置连续点击判定的最大间隔（上一次触控结束到这一次开始）
+    pub fn set_tap_interval(&mut self, interval: Duration) {
+        self.tap_interval = interval;
+    }
+
+    // 新的触控开始时，根据上一次结束的触控计算连续点击次数
+    fn next_tap_count(&self, x: f32, y: f32, now: Instant) -> u32 {
+        match self.last_tap {
+            Some(last)
+                if now.duration_since(last.time) <= self.tap_interval
+                    && (x - last.x).hypot(y - last.y) <= self.tap_distance =>
+            {
+                last.tap_count + 1
+            }
+            _ => 1,
+        }
+    }
+
     pub fn begin_frame(&mut self) {
         // 1. 移除上一帧已经完成寿命的点
         self.active_touches.retain(|_id, touch| {
@@ -185,8 +387,11 @@ impl TouchInput {
         let x = winit_touch.location.x as f32;
         let y = winit_touch.location.y as f32;
         let phase = winit_touch.phase;
+        let force = winit_touch.force.map(|force| force.normalized() as f32);
+        let now = Instant::now();
 
         if !self.active_touches.contains_key(&id) {
+            let tap_count = self.next_tap_count(x, y, now);
             self.active_touches.insert(
                 id,
                 Touch {
@@ -194,6 +399,9 @@ impl TouchInput {
                     x,
                     y,
                     phase: TouchPhase::Began, // 初始必为 Began
+                    force,
+                    tap_count,
+                    start_time: now,
                     prev_x: x,
                     prev_y: y,
                     pending_phase: None,
@@ -206,6 +414,16 @@ impl TouchInput {
             // 更新最新坐标
             touch.x = x;
             touch.y = y;
+            touch.force = force;
+
+            // 正常抬起的触控作为下一次连续点击的参照，被系统取消的触控打断连续点击
+            match phase {
+                winit::event::TouchPhase::Ended => {
+                    self.last_tap = Some(LastTap { x, y, time: now, tap_count: touch.tap_count });
+                }
+                winit::event::TouchPhase::Cancelled => self.last_tap = None,
+                _ => {}
+            }
 
             let new_phase = match phase {
                 winit::event::TouchPhase::Started => TouchPhase::Began,
@@ -227,6 +445,18 @@ impl TouchInput {
         }
     }
 
+    /// 内部方法，把所有活跃的触控点标记为 `Cancelled`，下一帧移除。用于窗口失去焦点时。
+    pub fn cancel_all(&mut self) {
+        for touch in self.active_touches.values_mut() {
+            if touch.phase == TouchPhase::Began {
+                touch.pending_phase = Some(TouchPhase::Cancelled);
+            } else {
+                touch.phase = TouchPhase::Cancelled;
+            }
+        }
+        self.last_tap = None;
+    }
+
     /// 获取当前所有活跃的触控点。类似于Unity的 Input.touches。
     pub fn get_touches(&self) -> Vec<&Touch> {
         self.active_touches.values().collect() // 返回所有活跃触控点的引用
@@ -238,6 +468,11 @@ impl TouchInput {
         self.active_touches.get(&id)
     }
 
+    /// 触控点已经按住的时长，可用于长按弹出菜单等；触控点不存在时返回 `None`
+    pub fn get_touch_hold_duration(&self, id: u64) -> Option<Duration> {
+        self.active_touches.get(&id).map(|touch| touch.start_time.elapsed())
+    }
+
     /// 获取当前帧的触控点数量。
     pub fn get_touch_count(&self) -> usize {
         self.active_touches.len()
@@ -22,21 +22,30 @@ mod draw_call;
 mod texture;
 mod render_command;
 mod input;
+mod frame_stats;
+mod gpu_timer;
+mod blit;
+mod text;
+mod compute;
+mod monitor;
+mod tilemap;
+mod particle;
+mod gesture;
+mod debug_draw;
+mod headless;
+mod assets;
+mod plugin;
+#[cfg(feature = "scene")]
+mod scene;
 
-use crate::{ graphics::*, my_game::MyGame, render_context::RenderContext };
+use crate::{ graphics::*, my_game::MyGame };
 
-static mut CONTEXT: Option<WgpuState> = None;
-
-pub(crate) fn get_quad_context() -> &'static mut WgpuState {
-    unsafe { CONTEXT.as_mut().unwrap_or_else(|| panic!()) }
-}
-
-pub(crate) fn get_context() -> &'static mut RenderContext {
-    unsafe {
-        assert!(CONTEXT.is_some());
-    }
-
-    unsafe { &mut CONTEXT.as_mut().unwrap().context }
+/// 旧的全局渲染状态访问方式，保留一个版本，之后删除。
+///
+/// 不再有全局状态，直接返回传入的 `GameLoop` 回调参数 `graphics`
+#[deprecated(note = "使用 GameLoop::start/update 参数中的 &mut WgpuState")]
+pub fn get_quad_context(graphics: &mut WgpuState) -> &mut WgpuState {
+    graphics
 }
 
 // ======================= Android specific =======================
@@ -53,7 +62,10 @@ fn android_main(android_app: winit::platform::android::activity::AndroidApp) {
 // ================================================================
 
 pub fn main() {
-    app::App::new(MyGame::default())
+    let config = app::AppConfig::new()
+        .with_size(resolution::Resolution::Physical(1280, 720))
+        .with_msaa(msaa::Msaa::Sample4);
+    app::App::new(MyGame::default(), config)
         .set_logger_max_level(LevelFilter::Info)
         .run();
 }
\ No newline at end of file
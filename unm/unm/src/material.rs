@@ -1,13 +1,13 @@
 use log::error;
-use unm_tools::id_map::IdMapKey;
+use unm_tools::id_map::{GetError, IdMapKey};
 
-use std::{collections::HashMap, num::NonZeroU64};
+use std::{collections::{HashMap, HashSet}, num::NonZeroU64};
 
 use wgpu::{
-    BindGroupLayout, BindingType, BlendComponent, BlendFactor, BlendOperation, BlendState, BufferBindingType, ColorWrites, CompareFunction, DepthBiasState, DepthStencilState, Face, PipelineCompilationOptions, PipelineLayout, PolygonMode, PrimitiveTopology, RenderPipeline, ShaderModule, ShaderStages, StencilState, TextureFormat, naga::{self, Module, valid::ModuleInfo}
+    BindGroupLayout, BindingType, BlendComponent, BlendFactor, BlendOperation, BlendState, BufferBindingType, ColorWrites, CompareFunction, DepthBiasState, DepthStencilState, Face, PipelineCompilationOptions, PipelineLayout, PolygonMode, PrimitiveTopology, RenderPipeline, ShaderModule, ShaderStages, StencilFaceState, StencilOperation, StencilState, TextureFormat, TextureFormatFeatureFlags, naga::{self, Module, valid::ModuleInfo}
 };
 
-use crate::{get_quad_context, msaa::Msaa, render_context::RenderContext, texture::Texture2DHandle, uniform::*, vertex::Vertex};
+use crate::{graphics::WgpuState, msaa::Msaa, render_context::RenderContext, render_target::DEPTH_STENCIL_FORMAT, texture::Texture2DHandle, uniform::*, vertex::Vertex};
 
 #[derive(Default, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 pub struct MaterialHandle(u64);
@@ -22,59 +22,75 @@ impl IdMapKey for MaterialHandle {
 }
 
 impl MaterialHandle {
-    pub fn is_depth_enabled(&self) -> bool {
-        let ctx = get_quad_context();
-        if let Some(mat) = ctx.materials.get_mut(*self) {
-            mat.material_descriptor.is_depth_enabled()
-        } else {
-            false
+    // 查找材质，句柄指向已经移除的材质时记录错误，避免下面的查询静默返回默认值
+    fn material<'a>(&self, ctx: &'a WgpuState) -> Option<&'a Material> {
+        match ctx.materials.try_get(*self) {
+            Ok(mat) => Some(mat),
+            Err(err @ (GetError::Freed | GetError::WrongGeneration)) => {
+                error!("Stale material handle {:?}: {}.", self, err);
+                None
+            }
+            Err(_) => None,
         }
     }
 
-    pub fn is_stencil_enabled(&self) -> bool {
-        let ctx = get_quad_context();
-        if let Some(mat) = ctx.materials.get_mut(*self) {
-            mat.material_descriptor.is_stencil_enabled()
-        } else {
-            false
-        }
+    pub fn is_depth_enabled(&self, ctx: &WgpuState) -> bool {
+        self.material(ctx)
+            .is_some_and(|mat| mat.material_descriptor.is_depth_enabled())
     }
 
-    pub fn should_render_as_transparent(&self) -> bool {
-        let ctx = get_quad_context();
-        if let Some(mat) = ctx.materials.get_mut(*self) {
-            mat.material_descriptor.should_render_as_transparent()
-        } else {
-            // 根据handle未获取到材质
-            // 视为错误
-            false
-        }
+    pub fn is_stencil_enabled(&self, ctx: &WgpuState) -> bool {
+        self.material(ctx)
+            .is_some_and(|mat| mat.material_descriptor.is_stencil_enabled())
+    }
+
+    pub fn should_render_as_transparent(&self, ctx: &WgpuState) -> bool {
+        // 根据handle未获取到材质视为错误，按不透明处理
+        self.material(ctx)
+            .is_some_and(|mat| mat.material_descriptor.should_render_as_transparent())
     }
 
-    pub(crate) fn get_all_uniform(&self) -> Option<HashMap<String, Uniform>>
+    /// 材质当前的用户 Uniform 值（名称 -> 值），句柄无效或材质没有用户 Uniform 时为空。
+    /// 只包含通过 `uniform_defs` 默认值或 `set_uniform` 设置的字段，`set_uniform_block` 写入的数据不会反映在这里
+    pub fn uniforms(&self, ctx: &WgpuState) -> HashMap<String, Uniform> {
+        ctx.materials
+            .get(*self)
+            .map(|mat| mat.current_uniform_values.clone())
+            .unwrap_or_default()
+    }
+
+    /// 单个用户 Uniform 的当前值，说明同 `uniforms`
+    pub fn get_uniform(&self, ctx: &WgpuState, name: &str) -> Option<Uniform> {
+        ctx.materials.get(*self)?.current_uniform_values.get(name).cloned()
+    }
+
+    pub fn set_uniform<T>(&self, ctx: &mut WgpuState, name: &str, value: T)
+    where
+        T: Into<Uniform>,
     {
-        let ctx = get_quad_context();
         if let Some(mat) = ctx.materials.get_mut(*self) {
-            Some(mat.current_uniform_values.clone())
-        } else {
-            None
+            ctx.break_batching = true;
+            mat.set_uniform(name, value);
         }
     }
 
-    pub fn set_uniform<T>(&self, name: &str, value: T)
+    /// 整块写入用户 Uniform，`T` 的内存布局需要与着色器中的 Uniform 结构体一致
+    /// （注意 WGSL 的对齐规则，例如 vec3 按 16 字节对齐）。
+    ///
+    /// 适合字段较多或每帧整体更新的情况，省去按名称逐个查找；
+    /// 材质仍需通过 `uniform_defs` 声明 Uniform，以便创建 UBO。之后用 `set_uniform` 修改单个字段也会生效。
+    pub fn set_uniform_block<T>(&self, ctx: &mut WgpuState, value: &T)
     where
-        T: Into<Uniform>,
+        T: bytemuck::Pod,
     {
-        let ctx = get_quad_context();
         if let Some(mat) = ctx.materials.get_mut(*self) {
             ctx.break_batching = true;
-            mat.set_uniform(name, value);
+            mat.set_uniform_block(bytemuck::bytes_of(value));
         }
     }
 
-    pub fn set_texture<T>(&self, name: &str, texture: Texture2DHandle)
+    pub fn set_texture<T>(&self, ctx: &mut WgpuState, name: &str, texture: Texture2DHandle)
     {
-        let ctx = get_quad_context();
         if let Some(mat) = ctx.materials.get_mut(*self) {
             ctx.break_batching = true;
             // mat.set_uniform(name, value);
@@ -85,8 +101,20 @@ impl MaterialHandle {
 // ====================================================================
 // 修改 Material 结构体以存储 UBO 相关信息
 // = ==================================================================
+/// 材质的用途，决定管线的顶点输入与第 0 组绑定
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum MaterialKind {
+    /// 普通网格材质：顶点来自全局顶点缓冲区，第 0 组为相机 Uniform
+    Mesh,
+    /// 带纹理的网格材质：在 `Mesh` 的基础上，第 1 组为纹理与采样器
+    TexturedMesh,
+    /// 后处理材质：全屏三角形由 `vertex_index` 生成，第 0 组为源纹理与采样器
+    PostProcess,
+}
+
 pub(crate) struct Material {
     pub(crate) name: String,
+    pub(crate) kind: MaterialKind,
     pub(crate) pipeline: RenderPipeline,
     pub(crate) shader: ShaderModule, // 公开方便外部访问
     pub(crate) material_descriptor: MaterialDescriptor, // 公开方便外部访问
@@ -102,12 +130,32 @@ pub(crate) struct Material {
     pub(crate) user_uniform_bind_group: Option<wgpu::BindGroup>, // 存储用户 Uniform 的 BindGroup
     pub(crate) user_uniform_bind_group_layout: Option<wgpu::BindGroupLayout>, // 存储用户 Uniform 的 BindGroupLayout
     pub(crate) total_ubo_size: usize, // 整个 UBO 的总大小
+    // 通过 set_uniform_block 整块设置的 UBO 数据，存在时优先于 current_uniform_values 上传
+    pub(crate) uniform_block: Option<Vec<u8>>,
+    // 用户 Uniform 所在的绑定组序号，排在材质种类固定的绑定组之后
+    pub(crate) user_uniform_group: u32,
+
+    // 管线布局只依赖绑定组布局，与 MSAA/格式无关，创建一次即可
+    pub(crate) pipeline_layout: PipelineLayout,
+    // 创建材质时生成的管线 pipeline 对应的键，其他组合的管线保存在 pipeline_cache 中
+    pub(crate) pipeline_key: PipelineKey,
+    // 之前创建过的其他管线，来回切换 MSAA 或渲染到其他格式的目标时直接复用
+    pub(crate) pipeline_cache: HashMap<PipelineKey, RenderPipeline>,
+    // 无法创建管线的组合，只报告一次错误
+    pub(crate) pipeline_errors: HashSet<PipelineKey>,
 }
 
+/// 管线缓存的键：(MSAA 采样数, 颜色目标格式, 渲染目标是否带深度/模板附件)
+pub(crate) type PipelineKey = (Msaa, TextureFormat, Option<TextureFormat>);
+
 impl Material {
+    /// `shared_layouts` 为用户 Uniform 之前的绑定组布局：网格材质传相机布局，
+    /// 带纹理的网格材质再加上纹理布局，后处理材质传源纹理布局。
+    #[allow(clippy::too_many_arguments)]
     pub(crate) async fn new(
         context: &RenderContext,
-        camera_bind_group_layout: &BindGroupLayout,
+        kind: MaterialKind,
+        shared_layouts: &[&BindGroupLayout],
         sample_count: Msaa,
         name: String,
         shader_str: String,
@@ -123,23 +171,48 @@ impl Material {
 
         let mut current_uniform_values = HashMap::new(); // 初始化为空
 
-        // 首次构建管线
+        // 创建用户 Uniform 的 UBO 与绑定组（只创建一次，重建管线时不再重复创建）
         let (
-            pipeline,
             user_uniform_ubo,
             uniform_layout,
             user_uniform_bind_group,
             user_uniform_bind_group_layout,
             total_ubo_size,
-        ) = Self::create_render_pipeline(
+        ) = Self::create_user_uniform_resources(
             context,
-            camera_bind_group_layout,
+            &name,
+            &uniform_defs,
+            &mut current_uniform_values, // `create_user_uniform_resources` 会用默认值填充它
+        );
+
+        let user_uniform_group = shared_layouts.len() as u32;
+        let mut bind_group_layouts_for_pipeline = shared_layouts.to_vec();
+        if let Some(user_layout) = user_uniform_bind_group_layout.as_ref() {
+            bind_group_layouts_for_pipeline.push(user_layout);
+        }
+
+        let pipeline_layout = context
+            .device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some(&format!("{0} Pipeline Layout", name)),
+                bind_group_layouts: &bind_group_layouts_for_pipeline, // 动态绑定布局
+                ..Default::default()
+            });
+
+        // 首次构建管线
+        // 后处理 Pass 不带深度附件
+        let pipeline_key = (
             sample_count,
+            context.surface_view_format(),
+            (kind != MaterialKind::PostProcess).then_some(DEPTH_STENCIL_FORMAT),
+        );
+        let pipeline = Self::create_render_pipeline(
+            context,
+            kind,
+            &pipeline_layout,
+            pipeline_key,
             &name,
             &shader,
             &material_descriptor,
-            &uniform_defs, // 仍然传递 uniform_defs 以便初始化 UBO
-            &mut current_uniform_values, // 传递可变引用，`create_render_pipeline` 会用默认值填充它
         );
 
         if let Some(err) = error_scope.pop().await {
@@ -147,33 +220,34 @@ impl Material {
         } else {
             Ok(Material {
                 name,
+                kind,
                 pipeline,
                 shader,
                 material_descriptor,
-                uniform_defs, // 仍然存储 uniform_defs，以便 rebuild_pipeline 或未来其他用途
+                uniform_defs, // 仍然存储 uniform_defs，以便未来其他用途
                 current_uniform_values, // *** 存储初始化后的值 ***
                 user_uniform_ubo,
                 uniform_layout,
                 user_uniform_bind_group,
                 user_uniform_bind_group_layout,
                 total_ubo_size,
+                uniform_block: None,
+                user_uniform_group,
+                pipeline_layout,
+                pipeline_key,
+                pipeline_cache: HashMap::new(),
+                pipeline_errors: HashSet::new(),
             })
         }
     }
 
-    // 辅助函数，用于根据给定的参数创建渲染管线
-    // 返回值也需要修改以返回 UBO 相关信息
-    fn create_render_pipeline(
+    // 辅助函数，根据 uniform_defs 创建用户 Uniform 的 UBO、BindGroupLayout 和 BindGroup
+    fn create_user_uniform_resources(
         context: &RenderContext,
-        camera_bind_group_layout_fixed: &BindGroupLayout, // 重命名，以示区分
-        sample_count: Msaa,
         name: &str,
-        shader: &wgpu::ShaderModule,
-        material_descriptor: &MaterialDescriptor,
         uniform_defs: &Option<HashMap<String, UniformDef>>, // 用于获取默认值
-        current_uniform_values: &mut HashMap<String, Uniform>, // 新增参数：用于填充 Material 自身的 current_uniform_values
+        current_uniform_values: &mut HashMap<String, Uniform>, // 用于填充 Material 自身的 current_uniform_values
     ) -> (
-        wgpu::RenderPipeline,
         Option<wgpu::Buffer>,
         Option<UniformLayout>,
         Option<wgpu::BindGroup>,
@@ -186,8 +260,6 @@ impl Material {
         let mut user_uniform_bind_group_layout: Option<wgpu::BindGroupLayout> = None;
         let mut total_ubo_size: usize = 0;
 
-        let mut bind_group_layouts_for_pipeline = vec![camera_bind_group_layout_fixed];
-
         if let Some(uniform_defs_map) = uniform_defs {
             let (calculated_layout, calculated_total_size) =
                 // 暂时使用 clone()，或者可以考虑让 calculate_uniform_offsets_and_total_size 接受引用
@@ -248,16 +320,10 @@ impl Material {
                     },
                 );
 
-                // 1. 将创建的 BindGroupLayout 的所有权赋给 Material 自身的字段 (或 Option)
-                user_uniform_bind_group_layout = Some(created_user_layout);
-
-                // 获取对已存储的 BindGroupLayout 的引用，用于创建 BindGroup
-                let bind_group_layout_ref = user_uniform_bind_group_layout.as_ref().unwrap();
-
                 // 创建用户自定义 Uniform 的 BindGroup
                 let bind_group = context.device.create_bind_group(&wgpu::BindGroupDescriptor {
                     label: Some(&format!("{}_UserUniformBindGroup", name)),
-                    layout: bind_group_layout_ref, // 使用引用
+                    layout: &created_user_layout,
                     entries: &[
                         wgpu::BindGroupEntry {
                             binding: 0, // 修改为 binding 0，因为 Camera 绑定 0 也需要注意
@@ -266,35 +332,52 @@ impl Material {
                     ],
                 });
                 user_uniform_bind_group = Some(bind_group);
-
-                // 2. 为了将其添加到渲染管线的布局中，需要一个独立的 BindGroupLayout 实例的所有权，所以克隆
-                bind_group_layouts_for_pipeline.push(bind_group_layout_ref);
+                user_uniform_bind_group_layout = Some(created_user_layout);
             }
         } // end of if let Some(uniform_defs_map) = uniform_defs
         // 确保即使 uniform_defs 为 None，total_ubo_size 和 uniform_layout 也能被正确初始化（例如为None/0）
 
-        let render_pipeline_layout = context
-            .device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some(&format!("{0} Pipeline Layout", name)),
-                bind_group_layouts: &bind_group_layouts_for_pipeline, // 动态绑定布局
-                ..Default::default()
-            });
+        (
+            user_uniform_ubo,
+            uniform_layout,
+            user_uniform_bind_group,
+            user_uniform_bind_group_layout,
+            total_ubo_size,
+        )
+    }
 
-        let pipeline = context.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+    // 辅助函数，用于根据给定的参数创建渲染管线
+    fn create_render_pipeline(
+        context: &RenderContext,
+        kind: MaterialKind,
+        pipeline_layout: &PipelineLayout,
+        (sample_count, format, depth_format): PipelineKey,
+        name: &str,
+        shader: &wgpu::ShaderModule,
+        material_descriptor: &MaterialDescriptor,
+    ) -> wgpu::RenderPipeline {
+        // 后处理材质不使用顶点缓冲区
+        let vertex_buffers = match kind {
+            MaterialKind::Mesh | MaterialKind::TexturedMesh => vec![Vertex::desc()],
+            MaterialKind::PostProcess => vec![],
+        };
+
+        context.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some(&format!("{0} Pipeline", name)),
-            layout: Some(&render_pipeline_layout),
+            layout: Some(pipeline_layout),
             vertex: wgpu::VertexState {
                 module: shader,
                 entry_point: Some("vs_main"), // 假设顶点着色器入口点是 vs_main
-                buffers: &[Vertex::desc()],
+                buffers: &vertex_buffers,
                 compilation_options: Default::default(),
             },
             fragment: Some(wgpu::FragmentState {
                 module: shader,
                 entry_point: Some("fs_main"), // 假设片元着色器入口点是 fs_main
                 targets: &[Some(wgpu::ColorTargetState {
-                    format: context.config.format,
-                    blend: Some(BlendState {
+                    format,
+                    // 不混合时不设置 BlendState，不支持混合的格式（如 R32Float）也能作为目标
+                    blend: material_descriptor.should_render_as_transparent().then_some(BlendState {
                         color: material_descriptor.color_blend,
                         alpha: material_descriptor.alpha_blend,
                     }),
@@ -305,73 +388,92 @@ impl Material {
             primitive: wgpu::PrimitiveState {
                 topology: material_descriptor.primitive_type.into(),
                 polygon_mode: material_descriptor.primitive_type.into(),
-                cull_mode: Some(material_descriptor.cull_mode),
+                // 全屏三角形是顺时针的，后处理不做背面剔除
+                cull_mode: match kind {
+                    MaterialKind::Mesh | MaterialKind::TexturedMesh => Some(material_descriptor.cull_mode),
+                    MaterialKind::PostProcess => None,
+                },
                 front_face: wgpu::FrontFace::Ccw,
                 strip_index_format: None,
                 unclipped_depth: false,
                 conservative: false,
             },
-            depth_stencil: Some(material_descriptor.depth_stencil.clone()), // 假设没有深度或模板缓冲区
+            // 深度写入由材质是否透明（或显式覆盖）决定
+            // 渲染目标没有深度附件时管线也不能带深度/模板状态，有则使用渲染目标的深度格式
+            depth_stencil: depth_format.map(|format| DepthStencilState {
+                format,
+                ..material_descriptor.effective_depth_stencil()
+            }),
             multisample: wgpu::MultisampleState {
                 count: sample_count.into(),
                 mask: !0,
-                alpha_to_coverage_enabled: false,
+                // alpha-to-coverage 只有在多重采样下才有意义
+                alpha_to_coverage_enabled: material_descriptor.alpha_to_coverage && sample_count != Msaa::Off,
             },
-            cache: None,
+            // 支持 PIPELINE_CACHE 的后端（如 Vulkan）会复用驱动层的编译结果
+            cache: context.pipeline_cache.as_ref(),
             multiview_mask: None,
-        });
-
-        (
-            pipeline,
-            user_uniform_ubo,
-            uniform_layout,
-            user_uniform_bind_group,
-            user_uniform_bind_group_layout,
-            total_ubo_size,
-        )
+        })
     }
 
-    /// 使用 Material 自身的数据重建渲染管线。
-    ///
-    /// 当 `wgpu::SurfaceConfiguration` 发生变化时，例如窗口大小改变，
-    /// 通常需要重建管线。
+    /// 确保存在与渲染目标匹配（采样数、颜色格式、深度格式）的管线，不存在时创建并缓存。
     ///
-    /// # 参数
-    /// - `context`: WGPU 上下文。
-    /// - `camera_bind_group_layout_fixed`: 传入的相机 BindGroupLayout。
-    /// - `sample_count`: MSAA 采样数。
-    pub(crate) fn rebuild_pipeline(
-        &mut self,
-        context: &RenderContext,
-        camera_bind_group_layout_fixed: &BindGroupLayout, // 注意这里也是固定的相机布局
-        sample_count: Msaa,
-    ) {
-        // 重建管线时，仍然需要当前的 uniform_values 来初始化 UBO，
-        // 同时在创建过程中会再次用到 uniform_defs 来推断布局和默认值。
-        let (
-            pipeline,
-            user_uniform_ubo,
-            uniform_layout,
-            user_uniform_bind_group,
-            user_uniform_bind_group_layout,
-            total_ubo_size,
-        ) = Self::create_render_pipeline(
+    /// 目标格式与材质不兼容时（不可混合的格式配合透明材质、深度格式不带模板却开启了模板测试、
+    /// 没有深度附件却显式开启了深度写入）返回 `false`，错误只在第一次遇到时打印。
+    pub(crate) fn ensure_pipeline(&mut self, context: &RenderContext, key: PipelineKey) -> bool {
+        if key == self.pipeline_key || self.pipeline_cache.contains_key(&key) {
+            return true;
+        }
+        if self.pipeline_errors.contains(&key) {
+            return false;
+        }
+
+        let (_, format, depth_format) = key;
+        let format_features = context.adapter.get_texture_format_features(format);
+        let error = if self.material_descriptor.should_render_as_transparent()
+            && !format_features.flags.contains(TextureFormatFeatureFlags::BLENDABLE)
+        {
+            Some(format!("blending is enabled but {:?} is not blendable", format))
+        } else if self.material_descriptor.is_stencil_enabled()
+            && !depth_format.is_some_and(|depth| depth.has_stencil_aspect())
+        {
+            Some(format!("stencil test requires a depth format with stencil, got {:?}", depth_format))
+        } else if depth_format.is_none() && self.material_descriptor.depth_write == Some(true) {
+            // 默认的深度测试在没有深度附件时直接省略；显式要求写入深度则视为配置错误
+            Some("depth write requires a render target with depth/stencil".to_owned())
+        } else {
+            None
+        };
+
+        if let Some(error) = error {
+            error!(
+                "Material '{}' cannot render into {:?} target: {}",
+                self.name, format, error
+            );
+            self.pipeline_errors.insert(key);
+            return false;
+        }
+
+        let pipeline = Self::create_render_pipeline(
             context,
-            camera_bind_group_layout_fixed,
-            sample_count,
+            self.kind,
+            &self.pipeline_layout,
+            key,
             &self.name,
             &self.shader,
             &self.material_descriptor,
-            &self.uniform_defs,
-            &mut self.current_uniform_values, // 传入自身可变引用
         );
+        self.pipeline_cache.insert(key, pipeline);
+        true
+    }
 
-        self.pipeline = pipeline;
-        self.user_uniform_ubo = user_uniform_ubo;
-        self.uniform_layout = uniform_layout;
-        self.user_uniform_bind_group = user_uniform_bind_group;
-        self.user_uniform_bind_group_layout = user_uniform_bind_group_layout;
-        self.total_ubo_size = total_ubo_size;
+    /// 获取与渲染目标匹配的管线，需要先调用 `ensure_pipeline`。
+    pub(crate) fn pipeline_for(&self, key: &PipelineKey) -> Option<&RenderPipeline> {
+        if *key == self.pipeline_key {
+            Some(&self.pipeline)
+        } else {
+            self.pipeline_cache.get(key)
+        }
     }
 
     // ====================================================================
@@ -398,7 +500,39 @@ impl Material {
             return;
         }
 
-        self.current_uniform_values.insert(name.to_string(), value.into());
+        let value = value.into();
+        // 已经整块设置过时，把这个字段同步写进块数据
+        if let (Some(block), Some((offset, size))) = (
+            self.uniform_block.as_mut(),
+            self.uniform_layout.as_ref().and_then(|layout| layout.get(name)),
+        ) {
+            let bytes = uniform_to_bytes(&value);
+            if bytes.len() == *size && offset + size <= block.len() {
+                block[*offset..*offset + *size].copy_from_slice(&bytes);
+            }
+        }
+        self.current_uniform_values.insert(name.to_string(), value);
+    }
+
+    /// 整块设置 UBO 数据，不做字段名查找。数据长度不能超过 UBO 大小，不足部分补零。
+    pub(crate) fn set_uniform_block(&mut self, bytes: &[u8]) {
+        if self.user_uniform_ubo.is_none() {
+            error!("Material '{}' does not support user uniforms.", self.name);
+            return;
+        }
+        if bytes.len() > self.total_ubo_size {
+            error!(
+                "Uniform block of material '{}' is {} bytes, but the UBO is only {} bytes.",
+                self.name,
+                bytes.len(),
+                self.total_ubo_size
+            );
+            return;
+        }
+
+        let mut block = vec![0u8; self.total_ubo_size];
+        block[..bytes.len()].copy_from_slice(bytes);
+        self.uniform_block = Some(block);
     }
 
 
@@ -418,6 +552,12 @@ impl Material {
         let ubo_buffer = self.user_uniform_ubo.as_ref().unwrap();
         let uniform_layout = self.uniform_layout.as_ref().unwrap();
 
+        // 整块设置的数据直接上传，跳过逐个字段的序列化
+        if let Some(block) = self.uniform_block.as_ref() {
+            context.queue.write_buffer(ubo_buffer, 0, block);
+            return Ok(());
+        }
+
         // 创建一个临时的 UBO 数据缓冲区，用于一次性写入
         let mut ubo_data = vec![0u8; self.total_ubo_size];
 
@@ -453,6 +593,17 @@ pub enum PrimitiveType {
     Points,
 }
 
+impl PrimitiveType {
+    /// 每个图元占用的索引数
+    pub fn vertices_per_primitive(&self) -> usize {
+        match self {
+            PrimitiveType::Triangles => 3,
+            PrimitiveType::Lines => 2,
+            PrimitiveType::Points => 1,
+        }
+    }
+}
+
 impl From<PrimitiveType> for PrimitiveTopology {
     fn from(primitive_type: PrimitiveType) -> Self {
         match primitive_type {
@@ -473,37 +624,97 @@ impl From<PrimitiveType> for PolygonMode {
     }
 }
 
+/// 常用的混合模式预设，避免手动配置 `BlendComponent` 的因子
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BlendMode {
+    /// One/Zero，关闭混合，写入深度
+    Opaque,
+    /// SrcAlpha/OneMinusSrcAlpha，默认模式
+    AlphaBlend,
+    /// SrcAlpha/One，用于粒子、发光等效果
+    Additive,
+    /// Dst/Zero，将颜色与目标相乘
+    Multiply,
+}
+
+impl BlendMode {
+    /// 返回 (color_blend, alpha_blend)
+    pub fn blend_components(&self) -> (BlendComponent, BlendComponent) {
+        match self {
+            BlendMode::Opaque => (BlendComponent::REPLACE, BlendComponent::REPLACE),
+            BlendMode::AlphaBlend => (
+                BlendComponent {
+                    src_factor: BlendFactor::SrcAlpha,
+                    dst_factor: BlendFactor::OneMinusSrcAlpha,
+                    operation: BlendOperation::Add,
+                },
+                BlendComponent::OVER,
+            ),
+            BlendMode::Additive => (
+                BlendComponent {
+                    src_factor: BlendFactor::SrcAlpha,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+                BlendComponent {
+                    src_factor: BlendFactor::One,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+            ),
+            BlendMode::Multiply => (
+                BlendComponent {
+                    src_factor: BlendFactor::Dst,
+                    dst_factor: BlendFactor::Zero,
+                    operation: BlendOperation::Add,
+                },
+                BlendComponent::OVER,
+            ),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct MaterialDescriptor {
     pub color_blend: BlendComponent,
     pub alpha_blend: BlendComponent,
     pub color_write: ColorWrites,
 
+    /// 其中的 `depth_write_enabled` 不起作用，是否写入深度只由 `depth_write` 决定
     pub depth_stencil: DepthStencilState,
+    /// 显式指定是否写入深度。`None` 表示自动：写入颜色的不透明材质写入，透明或不写颜色的材质不写入。
+    /// 会覆盖 `depth_stencil.depth_write_enabled`，要改变是否写入深度请设置这里
+    pub depth_write: Option<bool>,
+    /// 模板测试的参考值，仅在开启模板时生效
+    pub stencil_reference: u32,
 
     pub primitive_type: PrimitiveType,
     pub cull_mode: Face,
+
+    /// 根据片元 alpha 生成覆盖掩码，让镂空贴图（树叶、栅栏等）的边缘获得抗锯齿。
+    /// 仅在 MSAA 开启（`sample_count > 1`）时生效，切换 MSAA 时随管线一起重建。
+    pub alpha_to_coverage: bool,
 }
 
 impl Default for MaterialDescriptor {
     fn default() -> Self {
+        let (color_blend, alpha_blend) = BlendMode::AlphaBlend.blend_components();
         Self {
-            color_blend: BlendComponent {
-                src_factor: BlendFactor::SrcAlpha,
-                dst_factor: BlendFactor::OneMinusSrcAlpha,
-                operation: BlendOperation::Add,
-            },
-            alpha_blend: BlendComponent::OVER,
+            color_blend,
+            alpha_blend,
             color_write: ColorWrites::ALL,
             depth_stencil: DepthStencilState {
-                format: TextureFormat::Depth32Float,
+                format: DEPTH_STENCIL_FORMAT,
                 depth_write_enabled: false,
                 depth_compare: CompareFunction::Less,
                 stencil: StencilState::default(),
                 bias: DepthBiasState::default(),
             },
+            depth_write: None,
+            stencil_reference: 0,
             primitive_type: PrimitiveType::Triangles,
-            cull_mode: Face::Back
+            cull_mode: Face::Back,
+            alpha_to_coverage: false,
         }
     }
 }
@@ -524,12 +735,97 @@ impl MaterialDescriptor {
     }
 
     pub fn should_render_as_transparent(&self) -> bool {
+        // 不写颜色的材质（深度预渲染、模板遮罩）与混合无关，按不透明处理
+        if self.color_write.is_empty() {
+            return false;
+        }
+
         let color_blending = is_blending_active(&self.color_blend);
         let alpha_blending = is_blending_active(&self.alpha_blend);
 
         color_blending || alpha_blending
     }
 
+    /// 是否写入深度。未显式指定时，不透明材质写入深度，透明材质不写入，
+    /// 与 `sort_render_commands` 中不透明从近到远、透明从远到近的排序保持一致。
+    ///
+    /// 不写颜色的材质（模板遮罩等）默认也不写入深度，否则会遮挡之后的绘制；
+    /// 深度预渲染需要像 `depth_only()` 一样显式开启
+    pub fn is_depth_write_enabled(&self) -> bool {
+        self.depth_write
+            .unwrap_or_else(|| !self.color_write.is_empty() && !self.should_render_as_transparent())
+    }
+
+    /// 显式开启或关闭深度写入，覆盖根据透明度自动推断的结果。
+    pub fn with_depth_write(mut self, enabled: bool) -> Self {
+        self.depth_write = Some(enabled);
+        self
+    }
+
+    /// 开启或关闭 alpha-to-coverage，仅在 MSAA 开启时生效。
+    pub fn with_alpha_to_coverage(mut self, enabled: bool) -> Self {
+        self.alpha_to_coverage = enabled;
+        self
+    }
+
+    /// 配置模板测试，正反面使用相同的比较函数与通过后的操作。
+    pub fn with_stencil(
+        mut self,
+        compare: CompareFunction,
+        pass_op: StencilOperation,
+        read_mask: u32,
+        write_mask: u32,
+    ) -> Self {
+        let face = StencilFaceState {
+            compare,
+            fail_op: StencilOperation::Keep,
+            depth_fail_op: StencilOperation::Keep,
+            pass_op,
+        };
+        self.depth_stencil.stencil = StencilState {
+            front: face,
+            back: face,
+            read_mask,
+            write_mask,
+        };
+        self
+    }
+
+    /// 设置模板参考值
+    pub fn with_stencil_reference(mut self, reference: u32) -> Self {
+        self.stencil_reference = reference;
+        self
+    }
+
+    /// 遮罩写入：绘制到的像素将模板值写为 `reference`
+    pub fn with_stencil_write(self, reference: u32) -> Self {
+        self.with_stencil(CompareFunction::Always, StencilOperation::Replace, 0xff, 0xff)
+            .with_stencil_reference(reference)
+    }
+
+    /// 遮罩测试：只在模板值等于 `reference` 的像素上绘制
+    pub fn with_stencil_test(self, reference: u32) -> Self {
+        self.with_stencil(CompareFunction::Equal, StencilOperation::Keep, 0xff, 0x00)
+            .with_stencil_reference(reference)
+    }
+
+    /// 构建管线时实际使用的深度模板状态。
+    ///
+    /// 自动推断写入深度、且仍使用默认比较函数 `Less` 时改用 `LessEqual`：
+    /// 2D 精灵都位于同一深度，后绘制（z_order 更大）的不透明精灵需要通过深度测试覆盖之前的精灵
+    pub(crate) fn effective_depth_stencil(&self) -> DepthStencilState {
+        let depth_write_enabled = self.is_depth_write_enabled();
+        let depth_compare = match self.depth_stencil.depth_compare {
+            CompareFunction::Less if depth_write_enabled && self.depth_write.is_none() => CompareFunction::LessEqual,
+            compare => compare,
+        };
+        DepthStencilState {
+            depth_write_enabled,
+            depth_compare,
+            ..self.depth_stencil.clone()
+        }
+    }
+
     pub fn triangle() -> Self {
         Self {
             primitive_type: PrimitiveType::Triangles,
@@ -550,4 +846,55 @@ impl MaterialDescriptor {
             ..Default::default()
         }
     }
+
+    pub fn opaque() -> Self {
+        Self::default().with_blend_mode(BlendMode::Opaque)
+    }
+
+    pub fn alpha_blend() -> Self {
+        Self::default().with_blend_mode(BlendMode::AlphaBlend)
+    }
+
+    pub fn additive() -> Self {
+        Self::default().with_blend_mode(BlendMode::Additive)
+    }
+
+    pub fn multiply() -> Self {
+        Self::default().with_blend_mode(BlendMode::Multiply)
+    }
+
+    /// 深度预渲染：只写深度、不写颜色，按不透明物体排序（从近到远）。
+    ///
+    /// 先用它绘制复杂的不透明几何体，再用 `with_depth_compare(CompareFunction::LessEqual)`
+    /// 的材质绘制同样的几何体，被遮挡的片元不会执行片元着色器，减少 overdraw。
+    pub fn depth_only() -> Self {
+        Self::opaque()
+            .with_color_write(ColorWrites::empty())
+            .with_depth_write(true)
+    }
+
+    /// 替换颜色写入掩码，例如只写 RGB、不写 alpha
+    pub fn with_color_write(mut self, color_write: ColorWrites) -> Self {
+        self.color_write = color_write;
+        self
+    }
+
+    /// 替换深度比较函数
+    pub fn with_depth_compare(mut self, depth_compare: CompareFunction) -> Self {
+        self.depth_stencil.depth_compare = depth_compare;
+        self
+    }
+
+    /// 替换混合模式，可与 `triangle()`/`lines()` 等组合使用，
+    /// 例如 `MaterialDescriptor::lines().with_blend_mode(BlendMode::Additive)`
+    pub fn with_blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        (self.color_blend, self.alpha_blend) = blend_mode.blend_components();
+        self
+    }
+
+    /// 替换图元类型，例如 `MaterialDescriptor::additive().with_primitive_type(PrimitiveType::Lines)`
+    pub fn with_primitive_type(mut self, primitive_type: PrimitiveType) -> Self {
+        self.primitive_type = primitive_type;
+        self
+    }
 }
\ No newline at end of file
@@ -0,0 +1,105 @@
+use log::warn;
+use winit::{
+    dpi::PhysicalSize,
+    event_loop::ActiveEventLoop,
+    monitor::{MonitorHandle, VideoModeHandle},
+    window::{Fullscreen, Window},
+};
+
+/// 窗口的全屏模式，显示器和显示模式的序号与 `GameSettings::get_monitors` 返回的顺序一致。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FullscreenMode {
+    /// 窗口模式
+    Off,
+    /// 无边框全屏，`None` 表示窗口当前所在的显示器
+    Borderless(Option<usize>),
+    /// 独占全屏，(显示器序号, 显示模式序号)
+    Exclusive(usize, usize),
+}
+
+/// 显示器支持的一种显示模式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VideoModeInfo {
+    pub size: PhysicalSize<u32>,
+    pub bit_depth: u16,
+    /// 刷新率（Hz）
+    pub refresh_rate: f32,
+}
+
+/// 显示器信息
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonitorInfo {
+    pub name: Option<String>,
+    pub size: PhysicalSize<u32>,
+    /// 当前刷新率（Hz），平台无法获取时为 `None`
+    pub refresh_rate: Option<f32>,
+    pub video_modes: Vec<VideoModeInfo>,
+}
+
+impl From<&VideoModeHandle> for VideoModeInfo {
+    fn from(mode: &VideoModeHandle) -> Self {
+        Self {
+            size: mode.size(),
+            bit_depth: mode.bit_depth(),
+            refresh_rate: mode.refresh_rate_millihertz() as f32 / 1000.0,
+        }
+    }
+}
+
+impl From<&MonitorHandle> for MonitorInfo {
+    fn from(monitor: &MonitorHandle) -> Self {
+        Self {
+            name: monitor.name(),
+            size: monitor.size(),
+            refresh_rate: monitor.refresh_rate_millihertz().map(|mhz| mhz as f32 / 1000.0),
+            video_modes: monitor.video_modes().map(|mode| VideoModeInfo::from(&mode)).collect(),
+        }
+    }
+}
+
+/// 枚举所有显示器，只能在 winit 线程上调用
+pub(crate) fn collect_monitors(event_loop: &ActiveEventLoop) -> Vec<MonitorInfo> {
+    event_loop
+        .available_monitors()
+        .map(|monitor| MonitorInfo::from(&monitor))
+        .collect()
+}
+
+/// 把 `FullscreenMode` 解析为 winit 的全屏设置，序号无效时返回 `Err`
+pub(crate) fn resolve_fullscreen(
+    event_loop: &ActiveEventLoop,
+    mode: FullscreenMode,
+) -> Result<Option<Fullscreen>, ()> {
+    let monitor = |index: usize| {
+        let monitor = event_loop.available_monitors().nth(index);
+        if monitor.is_none() {
+            warn!("Monitor {} not found.", index);
+        }
+        monitor
+    };
+
+    match mode {
+        FullscreenMode::Off => Ok(None),
+        FullscreenMode::Borderless(None) => Ok(Some(Fullscreen::Borderless(None))),
+        FullscreenMode::Borderless(Some(index)) => {
+            let monitor = monitor(index).ok_or(())?;
+            Ok(Some(Fullscreen::Borderless(Some(monitor))))
+        }
+        FullscreenMode::Exclusive(index, mode_index) => {
+            let monitor = monitor(index).ok_or(())?;
+            let Some(video_mode) = monitor.video_modes().nth(mode_index) else {
+                warn!("Video mode {} not found on monitor {}.", mode_index, index);
+                return Err(());
+            };
+            Ok(Some(Fullscreen::Exclusive(video_mode)))
+        }
+    }
+}
+
+/// 窗口当前的刷新率（mHz）：独占全屏时为所选显示模式的刷新率，否则为窗口所在显示器的刷新率
+pub(crate) fn window_refresh_rate_millihertz(window: &Window) -> Option<u32> {
+    if let Some(Fullscreen::Exclusive(mode)) = window.fullscreen() {
+        return Some(mode.refresh_rate_millihertz());
+    }
+    window.current_monitor().and_then(|m| m.refresh_rate_millihertz())
+}
@@ -1,4 +1,4 @@
-#[derive(Default, Debug, Clone, Copy, PartialEq)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Msaa {
     Off = 1,
     Sample2 = 2,
@@ -7,6 +7,21 @@ pub enum Msaa {
     Sample8 = 8,
 }
 
+impl Msaa {
+    /// 所有采样数，从低到高
+    pub const ALL: [Msaa; 4] = [Msaa::Off, Msaa::Sample2, Msaa::Sample4, Msaa::Sample8];
+
+    /// `supported` 中不超过当前采样数的最大值，都不满足时为 `Msaa::Off`（所有设备都支持）
+    pub fn clamp_to(self, supported: &[Msaa]) -> Msaa {
+        supported
+            .iter()
+            .copied()
+            .filter(|&msaa| msaa as u32 <= self as u32)
+            .max_by_key(|&msaa| msaa as u32)
+            .unwrap_or(Msaa::Off)
+    }
+}
+
 // 实现 From Trait，使其返回对应的 u32 值
 impl From<Msaa> for u32 {
     fn from(msaa: Msaa) -> Self {
@@ -1,7 +1,6 @@
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::get_quad_context;
-use crate::input::{MouseInput, TouchInput, TouchPhase};
+use crate::input::{KeyInput, MouseInput, TouchInput, TouchPhase};
 use async_trait::async_trait;
 use glam::{uvec2, vec2, vec3, Vec3};
 use log::info;
@@ -12,14 +11,13 @@ use unm_sfx::player::SfxManager;
 use winit::event::MouseButton;
 // 这里非常重要！
 use crate::{
+    assets::Assets,
     camera::{self, BaseCamera, Camera2D, Camera3D},
     game_loop::GameLoop,
     game_settings::GameSettings,
     graphics::WgpuState,
     material::{MaterialDescriptor, MaterialHandle},
-    msaa::Msaa,
     render_target::RenderTargetHandle,
-    resolution::Resolution,
     tools::TimeManager,
 };
 
@@ -40,16 +38,14 @@ impl Default for MyGame {
 
 #[async_trait]
 impl GameLoop for MyGame {
-    async fn start(&mut self, game_settings: &mut GameSettings, sfx_manager: &mut SfxManager) {
-        game_settings.set_msaa(Msaa::Sample4);
-        game_settings.set_resolution(Resolution::Physical(1280, 720));
-        // game_settings.set_target_fps(120);
+    async fn start(&mut self, _game_settings: &mut GameSettings, graphics: &mut WgpuState, sfx_manager: &mut SfxManager, _assets: &mut Assets) -> anyhow::Result<()> {
+        // game_settings.set_target_fps(FrameRate::Cap(120));
 
         let cam = Camera2D::new(
             BaseCamera::new(vec3(0., 0., -100.0), -1000.0, 1000.0),
             uvec2(1280, 720),
         );
-        get_quad_context().set_camera(Some(cam));
+        graphics.set_camera(Some(cam));
 
         // let file_path = "D:/HitSong0.wav";
         // println!("正在加载文件: {}", file_path);
@@ -63,23 +59,26 @@ impl GameLoop for MyGame {
 
         let buffer = include_bytes!("assets/HitSong0.wav");
 
-        if let Some(handles) = sfx_manager.init_load_sound(vec![buffer.to_vec()]) {
-            self.handle = handles[0];
-        }
+        let handles = sfx_manager
+            .init_load_sound(vec![buffer.to_vec()])
+            .ok_or_else(|| anyhow::anyhow!("Failed to load HitSong0.wav"))?;
+        self.handle = handles[0];
+        Ok(())
     }
 
     async fn update(
         &mut self,
         game_settings: &mut GameSettings,
+        render: &mut WgpuState,
         time_manager: &TimeManager,
         sfx_manager: &mut SfxManager,
+        _assets: &mut Assets,
         mouse_input: &MouseInput,
         touch_input: &TouchInput,
-    ) {
+        _key_input: &mut KeyInput,
+    ) -> anyhow::Result<()> {
         self.r += time_manager.get_delta_time() * 10.0;
 
-        let render = get_quad_context();
-
         render.draw_rectangle_rotated(
             -50.0,
             0.0,
@@ -133,5 +132,6 @@ impl GameLoop for MyGame {
                 vec2(0.5, 0.0),
             );
         }
+        Ok(())
     }
 }
@@ -0,0 +1,275 @@
+use std::f32::consts::TAU;
+
+use glam::{vec2, Vec2};
+
+use crate::{
+    camera::Rect,
+    graphics::{rect_vertices_uv, WgpuState, RECT_INDICES},
+    material::MaterialHandle,
+    texture::Texture2DHandle,
+    vertex::Vertex,
+};
+
+/// 粒子发射参数，修改后对之后发射的粒子生效（颜色、尺寸和重力对已有粒子也立即生效）
+#[derive(Debug, Clone)]
+pub struct ParticleSettings {
+    /// 每秒发射的粒子数，0 表示只通过 `burst` 发射
+    pub rate: f32,
+    /// 发射方向（弧度，0 为 +x）
+    pub direction: f32,
+    /// 以发射方向为中心的散布角（弧度），TAU 表示向所有方向发射
+    pub spread: f32,
+    /// 初速度范围（世界单位/秒）
+    pub speed: (f32, f32),
+    /// 寿命范围（秒）
+    pub lifetime: (f32, f32),
+    /// 每秒施加在所有粒子上的加速度
+    pub gravity: Vec2,
+    /// 出生和消亡时的颜色，按寿命线性插值
+    pub start_color: wgpu::Color,
+    pub end_color: wgpu::Color,
+    /// 出生和消亡时的边长，按寿命线性插值
+    pub start_size: f32,
+    pub end_size: f32,
+    /// 同时存在的粒子上限，达到上限时不再发射
+    pub max_particles: usize,
+}
+
+impl Default for ParticleSettings {
+    fn default() -> Self {
+        Self {
+            rate: 50.0,
+            direction: TAU / 4.0,
+            spread: TAU / 8.0,
+            speed: (50.0, 100.0),
+            lifetime: (0.5, 1.0),
+            gravity: Vec2::ZERO,
+            start_color: wgpu::Color::WHITE,
+            end_color: wgpu::Color::TRANSPARENT,
+            start_size: 8.0,
+            end_size: 0.0,
+            max_particles: 1000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Particle {
+    position: Vec2,
+    velocity: Vec2,
+    age: f32,
+    lifetime: f32,
+}
+
+/// CPU 更新的粒子系统。
+///
+/// 粒子池中的所有粒子在 `draw` 时生成四边形，作为一个绘制命令提交；
+/// 默认使用叠加混合的材质，颜色写入顶点颜色，可以通过 `set_texture` 指定粒子的纹理
+#[derive(Debug, Clone)]
+pub struct ParticleSystem {
+    settings: ParticleSettings,
+    particles: Vec<Particle>,
+    position: Vec2,
+    emitting: bool,
+    // 不足一个粒子的发射量，累积到下一次 update
+    emit_accumulator: f32,
+    texture: Option<Texture2DHandle>,
+    material: Option<MaterialHandle>,
+    rng_state: u32,
+    // draw 时复用的顶点和索引缓冲
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+}
+
+impl Default for ParticleSystem {
+    fn default() -> Self {
+        Self::new(ParticleSettings::default())
+    }
+}
+
+impl ParticleSystem {
+    pub fn new(settings: ParticleSettings) -> Self {
+        Self {
+            particles: Vec::with_capacity(settings.max_particles),
+            settings,
+            position: Vec2::ZERO,
+            emitting: true,
+            emit_accumulator: 0.0,
+            texture: None,
+            material: None,
+            rng_state: 0x9E37_79B9,
+            vertices: Vec::new(),
+            indices: Vec::new(),
+        }
+    }
+
+    pub fn settings(&self) -> &ParticleSettings {
+        &self.settings
+    }
+
+    pub fn settings_mut(&mut self) -> &mut ParticleSettings {
+        &mut self.settings
+    }
+
+    /// 发射点在世界空间中的位置，已经发射的粒子不随之移动
+    pub fn set_position(&mut self, position: Vec2) {
+        self.position = position;
+    }
+
+    pub fn get_position(&self) -> Vec2 {
+        self.position
+    }
+
+    /// 是否按 `rate` 持续发射，已有的粒子不受影响
+    pub fn set_emitting(&mut self, emitting: bool) {
+        self.emitting = emitting;
+        if !emitting {
+            self.emit_accumulator = 0.0;
+        }
+    }
+
+    pub fn is_emitting(&self) -> bool {
+        self.emitting
+    }
+
+    /// 粒子使用的纹理，`None` 时为纯色方块
+    pub fn set_texture(&mut self, texture: Option<Texture2DHandle>) {
+        self.texture = texture;
+    }
+
+    /// 替换默认的叠加混合材质，材质需要与 `create_textured_material` 的绑定布局一致，`None` 恢复默认
+    pub fn set_material(&mut self, material: Option<MaterialHandle>) {
+        self.material = material;
+    }
+
+    /// 随机数种子，相同的种子和相同的调用序列产生相同的粒子
+    pub fn set_seed(&mut self, seed: u32) {
+        // xorshift 的状态不能为 0
+        self.rng_state = seed.max(1);
+    }
+
+    /// 立即在发射点发射 `count` 个粒子（不超过 `max_particles`）
+    pub fn burst(&mut self, count: usize) {
+        let count = count.min(self.settings.max_particles.saturating_sub(self.particles.len()));
+        for _ in 0..count {
+            self.spawn();
+        }
+    }
+
+    /// 移除所有粒子
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.emit_accumulator = 0.0;
+    }
+
+    /// 存活的粒子数
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    /// 推进 `dt` 秒：移动粒子、移除寿命结束的粒子，并按 `rate` 发射新粒子
+    pub fn update(&mut self, dt: f32) {
+        let dt = dt.max(0.0);
+        let gravity = self.settings.gravity;
+
+        // 顺序不影响叠加混合的结果，用 swap_remove 移除死亡粒子
+        let mut i = 0;
+        while i < self.particles.len() {
+            let particle = &mut self.particles[i];
+            particle.age += dt;
+            if particle.age >= particle.lifetime {
+                self.particles.swap_remove(i);
+                continue;
+            }
+            particle.velocity += gravity * dt;
+            particle.position += particle.velocity * dt;
+            i += 1;
+        }
+
+        if self.emitting && self.settings.rate > 0.0 {
+            self.emit_accumulator += self.settings.rate * dt;
+            let count = self.emit_accumulator.floor();
+            self.emit_accumulator -= count;
+            self.burst(count as usize);
+        }
+    }
+
+    /// 把所有粒子作为一个绘制命令提交
+    pub fn draw(&mut self, ctx: &mut WgpuState, z_order: u32) {
+        if self.particles.is_empty() {
+            return;
+        }
+
+        let settings = &self.settings;
+        self.vertices.clear();
+        self.indices.clear();
+        for particle in &self.particles {
+            let t = (particle.age / particle.lifetime).clamp(0.0, 1.0);
+            let size = settings.start_size + (settings.end_size - settings.start_size) * t;
+            if size <= 0.0 {
+                continue;
+            }
+            let color = lerp_color(settings.start_color, settings.end_color, t);
+            let dest = Rect {
+                x: particle.position.x - size * 0.5,
+                y: particle.position.y - size * 0.5,
+                w: size,
+                h: size,
+            };
+
+            let base = self.vertices.len() as u32;
+            self.vertices.extend_from_slice(&rect_vertices_uv(dest, Vec2::ZERO, Vec2::ONE, color));
+            self.indices.extend(RECT_INDICES.map(|i| base + i));
+        }
+
+        if self.indices.is_empty() {
+            return;
+        }
+        let material = self.material.unwrap_or(ctx.additive_mat);
+        ctx.record_draw_command_ex(&self.vertices, &self.indices, z_order, material, self.texture);
+    }
+
+    fn spawn(&mut self) {
+        let settings = &self.settings;
+        let (direction, spread) = (settings.direction, settings.spread);
+        let speed = settings.speed;
+        let lifetime = settings.lifetime;
+
+        let angle = direction + (self.next_f32() - 0.5) * spread;
+        let speed = speed.0 + (speed.1 - speed.0) * self.next_f32();
+        let lifetime = lifetime.0 + (lifetime.1 - lifetime.0) * self.next_f32();
+        if lifetime <= 0.0 {
+            return;
+        }
+        self.particles.push(Particle {
+            position: self.position,
+            velocity: vec2(angle.cos(), angle.sin()) * speed,
+            age: 0.0,
+            lifetime,
+        });
+    }
+
+    // xorshift32，返回 [0, 1)
+    fn next_f32(&mut self) -> f32 {
+        let mut x = self.rng_state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.rng_state = x;
+        (x >> 8) as f32 / (1u32 << 24) as f32
+    }
+}
+
+fn lerp_color(from: wgpu::Color, to: wgpu::Color, t: f32) -> wgpu::Color {
+    let t = t as f64;
+    wgpu::Color {
+        r: from.r + (to.r - from.r) * t,
+        g: from.g + (to.g - from.g) * t,
+        b: from.b + (to.b - from.b) * t,
+        a: from.a + (to.a - from.a) * t,
+    }
+}
@@ -0,0 +1,29 @@
+use crate::{
+    game_settings::GameSettings,
+    graphics::WgpuState,
+    input::{KeyInput, MouseInput, TouchInput},
+    tools::TimeManager,
+};
+
+/// 独立于 `GameLoop` 的扩展，例如调试面板、性能统计 HUD，通过 `App::add_plugin` 注册。
+///
+/// 每帧 `update` 在游戏的 `update` 之前调用，`draw` 在游戏的 `update` 之后、提交绘制之前调用，
+/// 因此插件绘制的内容在相同 z_order 下位于游戏内容之上。多个插件按注册顺序依次调用。
+///
+/// 不要在回调之外保存 `graphics` 的引用
+pub trait Plugin: Send {
+    /// 每帧在游戏的 `update` 之前调用
+    fn update(
+        &mut self,
+        _game_settings: &mut GameSettings,
+        _graphics: &mut WgpuState,
+        _time_manager: &TimeManager,
+        _mouse_input: &MouseInput,
+        _touch_input: &TouchInput,
+        _key_input: &KeyInput,
+    ) {
+    }
+
+    /// 每帧在游戏的 `update` 之后调用，用于记录覆盖在游戏画面之上的绘制命令
+    fn draw(&mut self, _graphics: &mut WgpuState) {}
+}
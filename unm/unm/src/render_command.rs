@@ -1,21 +1,28 @@
 use std::collections::HashMap;
 
-use crate::{material::MaterialHandle, render_target::RenderTargetHandle, uniform::Uniform, vertex::Vertex};
+use crate::{camera::Rect, material::MaterialHandle, render_target::RenderTargetHandle, texture::Texture2DHandle, uniform::Uniform, vertex::Vertex};
 
 pub(crate) struct RenderCommand {
     pub(crate) id: u32,
     pub(crate) vertices: Vec<Vertex>,
     pub(crate) indices: Vec<u32>,
+    // 为 false 时不使用索引，顶点按顺序组成图元，indices 为空
+    pub(crate) indexed: bool,
 
     pub(crate) mat_handle: MaterialHandle,
     pub(crate) uniforms: Option<HashMap<String, Uniform>>,
+    // 带纹理的材质使用的纹理，为 None 时绑定白色占位纹理
+    pub(crate) texture: Option<Texture2DHandle>,
 
     pub(crate) render_target: RenderTargetHandle,
     pub(crate) render_queue: u32,
     pub(crate) depth: f32,
+    // XY 平面上的包围盒，仅在开启剔除时计算
+    pub(crate) bounds: Option<Rect>,
 }
 
 impl RenderCommand {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         id: u32,
         vertices: &[Vertex],
@@ -23,16 +30,20 @@ impl RenderCommand {
         mat_handle: MaterialHandle,
         render_target: RenderTargetHandle,
         z_order: u32,
-        depth: f32
+        depth: f32,
+        uniforms: Option<HashMap<String, Uniform>>,
     ) -> Self {
         Self {
             id,
             render_queue: z_order,
             vertices: vertices.to_vec(),
             indices: indices.to_vec(),
-            uniforms: mat_handle.get_all_uniform(),
+            indexed: true,
+            uniforms,
+            texture: None,
 
             depth,
+            bounds: None,
             mat_handle,
             render_target,
         }
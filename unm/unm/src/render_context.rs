@@ -1,10 +1,15 @@
 use anyhow::{Context, Ok};
-use image::GenericImageView;
-use log::info;
-use wgpu::{Adapter, Backends, Device, Extent3d, Instance, InstanceDescriptor, Limits, Origin3d, Queue, RequestAdapterOptions, SamplerDescriptor, Surface, SurfaceConfiguration, TexelCopyTextureInfo, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureViewDescriptor};
+use log::{error, info, warn};
+use wgpu::{Adapter, Backends, Device, Extent3d, Instance, InstanceDescriptor, Limits, Origin3d, PipelineCache, PipelineCacheDescriptor, PresentMode, Queue, RequestAdapterOptions, SamplerDescriptor, Surface, SurfaceConfiguration, TexelCopyTextureInfo, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureViewDescriptor};
 use winit::{dpi::PhysicalSize, window::Window};
 
-use crate::texture::Texture2D;
+use crate::{game_settings::DEFAULT_MAX_FRAME_LATENCY, texture::Texture2D};
+
+/// 期望的二维纹理最大边长下限。
+///
+/// 请求设备时会直接使用适配器支持的最大值；适配器达不到该值时仍以适配器的值创建设备并打印警告。
+/// 实际值可通过 `WgpuState::device_limits` 查询。
+pub const REQUIRED_MAX_TEXTURE_DIMENSION_2D: u32 = 4096;
 
 pub(crate) struct RenderContext {
     pub(crate) instance: Instance,
@@ -13,9 +18,23 @@ pub(crate) struct RenderContext {
     pub(crate) device: Device,
     pub(crate) queue: Queue,
     pub(crate) config: SurfaceConfiguration,
+    // 仅在设备支持 PIPELINE_CACHE 时创建
+    pub(crate) pipeline_cache: Option<PipelineCache>,
 }
 
 impl RenderContext {
+    // 在 downlevel 默认限制的基础上，按适配器的实际能力放宽纹理尺寸相关的限制
+    fn negotiate_limits(adapter_limits: &Limits) -> Limits {
+        if adapter_limits.max_texture_dimension_2d < REQUIRED_MAX_TEXTURE_DIMENSION_2D {
+            warn!(
+                "Adapter max_texture_dimension_2d ({}) is below the expected {}.",
+                adapter_limits.max_texture_dimension_2d, REQUIRED_MAX_TEXTURE_DIMENSION_2D
+            );
+        }
+
+        Limits::downlevel_defaults().using_resolution(adapter_limits.clone())
+    }
+
     pub(crate) async fn new(
         window: &'static Window,
         size: PhysicalSize<u32>
@@ -34,40 +53,7 @@ impl RenderContext {
             .context("Failed to create WGPU surface from window")?; // 使用 .context() 添加上下文
         info!("WGPU Surface created.");
 
-        // 3. 请求 Adapter
-        // request_adapter 返回 Option<Adapter>
-        let adapter = instance
-            .request_adapter(&RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::HighPerformance,
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .context("Failed to find an appropriate WGPU adapter")?; // 使用 .context() 适用于 Option
-        info!("WGPU Adapter requested: {:?}", adapter.get_info());
-
-        // 4. 请求 Device 和 Queue
-        // request_device 返回 Result<(Device, Queue), RequestDeviceError>
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    label: Some("Primary WGPU Device"),
-                    memory_hints: wgpu::MemoryHints::default(),
-                    required_features: wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
-                    // 注意：required_limits 可能需要与适配器的实际限制进行协商。
-                    // 理想情况下，您应该检查这些限制是否得到支持，或者使用 Limits::default()。
-                    // 如果您的应用程序特定需求，并且确定这些限制会被支持，可以保留。
-                    required_limits: wgpu::Limits {
-                        max_texture_dimension_2d: 4096,
-                        ..Limits::downlevel_defaults()
-                    },
-                    ..Default::default()
-                }
-            )
-            .await
-            .context("Failed to create WGPU device and queue")?; // 使用 .context() 添加上下文
-
-        info!("WGPU Device and Queue created.");
+        let (adapter, device, queue, pipeline_cache) = Self::request_device(&instance, Some(&surface)).await?;
 
         // 5. 配置 Surface
         let surface_caps = surface.get_capabilities(&adapter);
@@ -90,22 +76,25 @@ impl RenderContext {
             }
         }
         info!("Selected surface format: {:?}", surface_format);
+        if !surface_format.is_srgb() {
+            info!("Surface format {:?} is not sRGB, rendering through its sRGB view format.", surface_format);
+        }
 
         let alpha_mode = *surface_caps.alpha_modes
             .first()
             .context("No supported alpha modes found for surface")?;
 
-        let view_formats = if !surface_format.is_srgb() {
-            vec![surface_format.add_srgb_suffix()]
-        } else {
-            vec![]
-        };
+        // 默认帧率为 Vsync，创建时直接使用所有平台都保证可用的 Fifo
+        let present_mode = Self::choose_present_mode(&surface_caps.present_modes, PresentMode::Fifo);
+        info!("Selected present mode: {:?}", present_mode);
+
+        let view_formats = srgb_view_formats(surface_format);
 
         // 确保 width 和 height 至少为 1，以防窗口大小为 0 导致 WGPU 错误
         let config = SurfaceConfiguration {
             usage: TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_DST,
-            present_mode: wgpu::PresentMode::Mailbox,
-            desired_maximum_frame_latency: 2,
+            present_mode,
+            desired_maximum_frame_latency: DEFAULT_MAX_FRAME_LATENCY,
             width: size.width.max(1),
             height: size.height.max(1),
 
@@ -124,6 +113,96 @@ impl RenderContext {
             queue,
             config,
             surface: Some(surface),
+            pipeline_cache,
+        })
+    }
+
+    // 请求 Adapter、Device 和 Queue。无窗口模式下 compatible_surface 为 None
+    async fn request_device(
+        instance: &Instance,
+        compatible_surface: Option<&Surface<'static>>,
+    ) -> anyhow::Result<(Adapter, Device, Queue, Option<PipelineCache>)> {
+        // 3. 请求 Adapter
+        // request_adapter 返回 Option<Adapter>
+        let adapter = instance
+            .request_adapter(&RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                compatible_surface,
+                force_fallback_adapter: false,
+            })
+            .await
+            .context("Failed to find an appropriate WGPU adapter")?; // 使用 .context() 适用于 Option
+        info!("WGPU Adapter requested: {:?}", adapter.get_info());
+
+        // 4. 请求 Device 和 Queue
+        let required_limits = Self::negotiate_limits(&adapter.limits());
+
+        // request_device 返回 Result<(Device, Queue), RequestDeviceError>
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: Some("Primary WGPU Device"),
+                    memory_hints: wgpu::MemoryHints::default(),
+                    // 适配器支持时开启 TIMESTAMP_QUERY，用于 GPU 计时
+                    required_features: wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+                        | (adapter.features() & wgpu::Features::TIMESTAMP_QUERY)
+                        | (adapter.features() & wgpu::Features::PIPELINE_CACHE),
+                    required_limits,
+                    ..Default::default()
+                }
+            )
+            .await
+            .context("Failed to create WGPU device and queue")?; // 使用 .context() 添加上下文
+
+        info!("WGPU Device and Queue created.");
+
+        let pipeline_cache = if device.features().contains(wgpu::Features::PIPELINE_CACHE) {
+            // SAFETY: 不传入任何外部缓存数据，因此不存在数据来源不可信的问题
+            Some(unsafe {
+                device.create_pipeline_cache(&PipelineCacheDescriptor {
+                    label: Some("Pipeline Cache"),
+                    data: None,
+                    fallback: true,
+                })
+            })
+        } else {
+            None
+        };
+
+        Ok((adapter, device, queue, pipeline_cache))
+    }
+
+    /// 不创建 Surface 的上下文，用于无窗口渲染（CI 测试等）。
+    /// `config` 只用于提供默认渲染目标的尺寸和格式，不会配置到任何 Surface 上
+    pub(crate) async fn new_headless(size: PhysicalSize<u32>) -> anyhow::Result<Self> {
+        let instance = Instance::new(&InstanceDescriptor {
+            backends: Backends::all(),
+            ..Default::default()
+        });
+        info!("WGPU Instance created (headless).");
+
+        let (adapter, device, queue, pipeline_cache) = Self::request_device(&instance, None).await?;
+
+        let config = SurfaceConfiguration {
+            usage: TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_DST,
+            present_mode: PresentMode::Fifo,
+            desired_maximum_frame_latency: DEFAULT_MAX_FRAME_LATENCY,
+            width: size.width.max(1),
+            height: size.height.max(1),
+
+            format: TextureFormat::Rgba8UnormSrgb,
+            view_formats: vec![],
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+        };
+
+        Ok(Self {
+            instance,
+            adapter,
+            device,
+            queue,
+            config,
+            surface: None,
+            pipeline_cache,
         })
     }
 
@@ -135,25 +214,41 @@ impl RenderContext {
         }
     }
 
-    pub fn resume(&mut self, window: &'static Window) -> PhysicalSize<u32> {
+    /// 从窗口重新创建 Surface 并按当前配置重新配置，Device/Queue 及其上的资源保持不变。
+    /// Android 恢复后原生窗口是新的，Surface 支持的格式和呈现模式可能改变，不支持时改用可用的值。
+    pub fn resume(&mut self, window: &'static Window) -> Option<PhysicalSize<u32>> {
         // Window size is only actually valid after we enter the event loop.
         let window_size = window.inner_size();
-        let width = window_size.width.max(1);
-        let height = window_size.height.max(1);
+        let size = PhysicalSize::new(window_size.width.max(1), window_size.height.max(1));
 
         info!("Surface resume {window_size:?}");
 
-        let surface = self.instance.create_surface(window).unwrap();
-
-        let config = &mut self.config;
+        let surface = match self.instance.create_surface(window) {
+            std::result::Result::Ok(surface) => surface,
+            Err(e) => {
+                error!("Failed to recreate WGPU surface: {}", e);
+                return None;
+            }
+        };
 
-        config.width = width;
-        config.height = height;
+        let caps = surface.get_capabilities(&self.adapter);
+        if !caps.formats.contains(&self.config.format) {
+            let Some(&format) = caps.formats.first() else {
+                error!("No supported surface formats found after resume.");
+                return None;
+            };
+            warn!("Surface format {:?} is no longer supported, switching to {:?}.", self.config.format, format);
+            self.config.format = format;
+            self.config.view_formats = srgb_view_formats(format);
+        }
+        self.config.present_mode = Self::choose_present_mode(&caps.present_modes, self.config.present_mode);
 
-        surface.configure(&self.device, &config);
+        self.config.width = size.width;
+        self.config.height = size.height;
+        surface.configure(&self.device, &self.config);
 
         self.surface = Some(surface);
-        window_size
+        Some(size)
     }
 
     // 窗口大小改变时调用
@@ -166,12 +261,62 @@ impl RenderContext {
         }
     }
 
+    /// 渲染目标和管线默认使用的颜色格式。Surface 不是 sRGB 格式时为它的 sRGB 视图格式，
+    /// 这样着色器输出的线性颜色由 GPU 完成 gamma 编码，呈现时以该格式的视图写入 Surface
+    pub(crate) fn surface_view_format(&self) -> TextureFormat {
+        self.config.view_formats.first().copied().unwrap_or(self.config.format)
+    }
+
+    // 请求的呈现模式不受支持时退回 Fifo
+    fn choose_present_mode(supported: &[PresentMode], requested: PresentMode) -> PresentMode {
+        // Auto 模式按优先级选择第一个受支持的模式，Fifo 总是受支持
+        let candidates: &[PresentMode] = match requested {
+            PresentMode::AutoVsync => &[PresentMode::FifoRelaxed],
+            PresentMode::AutoNoVsync => &[PresentMode::Immediate, PresentMode::Mailbox],
+            _ => &[requested],
+        };
+        candidates
+            .iter()
+            .copied()
+            .find(|mode| supported.contains(mode))
+            .unwrap_or(PresentMode::Fifo)
+    }
+
+    /// 修改 Surface 的呈现模式并重新配置，返回实际使用的模式。
+    /// Surface 不存在时（应用在后台）不做修改，返回 `None`。
+    pub(crate) fn set_present_mode(&mut self, requested: PresentMode) -> Option<PresentMode> {
+        let surface = self.surface.as_ref()?;
+
+        let caps = surface.get_capabilities(&self.adapter);
+        let present_mode = Self::choose_present_mode(&caps.present_modes, requested);
+        if present_mode != requested && !matches!(requested, PresentMode::AutoVsync | PresentMode::AutoNoVsync) {
+            info!("Present mode {:?} not supported, falling back to {:?}", requested, present_mode);
+        }
+
+        if self.config.present_mode != present_mode {
+            self.config.present_mode = present_mode;
+            surface.configure(&self.device, &self.config);
+        }
+
+        Some(present_mode)
+    }
+
+    // 修改 Surface 的最大帧延迟并重新配置
+    pub(crate) fn set_max_frame_latency(&mut self, latency: u32) {
+        self.config.desired_maximum_frame_latency = latency;
+
+        if let Some(surface) = &mut self.surface {
+            surface.configure(&self.device, &self.config);
+        }
+    }
+
     // 辅助函数，负责将图像文件加载为 wgpu::Texture
     pub(crate) async fn load_texture(
-        &mut self,
+        &self,
         file_path: &str,
         label: Option<&str>,
         address_mode: wgpu::AddressMode,
+        layout: &wgpu::BindGroupLayout,
     ) -> anyhow::Result<Texture2D> {
         // 1. 异步加载图像文件 (使用 tokio::fs)
         // 如果你不是在tokio环境下运行 main 函数，或者不想异步加载，
@@ -183,7 +328,19 @@ impl RenderContext {
         // 这里我们假设图像是RGBA8，如果不是，`to_rgba8()` 会进行转换
         // wgpu 通常希望纹理是预乘 alpha 的，但这里只是简单地读取。
         let rgba_image = img.to_rgba8();
-        let dimensions = img.dimensions(); // 获取图像的宽度和高度
+
+        Ok(self.create_texture_from_rgba(&rgba_image, label, address_mode, layout))
+    }
+
+    // 用已解码的 RGBA8 图像创建纹理，解码可以在其他线程完成，这里只负责上传
+    pub(crate) fn create_texture_from_rgba(
+        &self,
+        rgba_image: &image::RgbaImage,
+        label: Option<&str>,
+        address_mode: wgpu::AddressMode,
+        layout: &wgpu::BindGroupLayout,
+    ) -> Texture2D {
+        let dimensions = rgba_image.dimensions(); // 获取图像的宽度和高度
 
         // 3. 定义纹理大小
         let texture_size = Extent3d {
@@ -213,7 +370,7 @@ impl RenderContext {
                 origin: Origin3d::ZERO, // 从纹理的 (0,0,0) 开始复制
                 aspect: wgpu::TextureAspect::All,
             },
-            &rgba_image, // 图像的原始字节数据
+            rgba_image, // 图像的原始字节数据
             wgpu::TexelCopyBufferLayout {
                 offset: 0,
                 // 像素行字节长度，必须是 WGPU_COPY_BYTES_PER_ROW_ALIGNMENT 的倍数 (256 字节)
@@ -247,7 +404,7 @@ impl RenderContext {
             border_color: None,
         });
 
-        Ok(Texture2D::new(texture, texture_view, sampler))
+        Texture2D::new(&self.device, layout, texture, texture_view, sampler)
     }
 }
 
@@ -256,4 +413,10 @@ pub async fn load_texture(
     label: Option<&str>
 ) {
 
-}
\ No newline at end of file
+}
+
+// Surface 不是 sRGB 格式时额外允许以 sRGB 格式创建视图（只有 sRGB 后缀不同，不需要复制数据）
+fn srgb_view_formats(format: TextureFormat) -> Vec<TextureFormat> {
+    let srgb = format.add_srgb_suffix();
+    if srgb != format { vec![srgb] } else { vec![] }
+}
@@ -4,7 +4,50 @@ use glam::UVec2;
 use unm_tools::id_map::IdMapKey;
 use wgpu::{Extent3d, TextureDescriptor, TextureDimension, TextureUsages, TextureViewDescriptor, TextureFormat};
 
-use crate::{msaa::Msaa, render_context::RenderContext};
+use crate::{graphics::PassAction, msaa::Msaa, render_context::RenderContext};
+
+/// 深度/模板纹理格式。带 8 位模板，用于遮罩类 UI（圆形头像、裁剪区域等）
+pub(crate) const DEPTH_STENCIL_FORMAT: TextureFormat = TextureFormat::Depth24PlusStencil8;
+
+/// 离屏渲染目标的创建参数，见 `WgpuState::create_render_target_with`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct RenderTargetDescriptor {
+    pub size: UVec2,
+    /// 颜色格式，`None` 为 Surface 格式（Surface 不是 sRGB 格式时为它的 sRGB 视图格式）
+    pub format: Option<TextureFormat>,
+    /// 固定的 MSAA 采样数，`None` 表示跟随全局 MSAA 设置
+    pub msaa: Option<Msaa>,
+    /// 深度/模板格式，`None` 表示不创建深度附件（纯 2D / UI 目标可以省下这部分显存）。
+    /// 模板测试需要带模板的格式，例如 `Depth24PlusStencil8`
+    pub depth: Option<TextureFormat>,
+}
+
+impl RenderTargetDescriptor {
+    /// Surface 颜色格式、跟随全局 MSAA、带 `Depth24PlusStencil8` 深度/模板附件
+    pub fn new(size: UVec2) -> Self {
+        Self {
+            size,
+            format: None,
+            msaa: None,
+            depth: Some(DEPTH_STENCIL_FORMAT),
+        }
+    }
+
+    pub fn with_format(mut self, format: TextureFormat) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    pub fn with_msaa(mut self, msaa: Msaa) -> Self {
+        self.msaa = Some(msaa);
+        self
+    }
+
+    pub fn with_depth(mut self, depth: Option<TextureFormat>) -> Self {
+        self.depth = depth;
+        self
+    }
+}
 
 #[derive(Default, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
 pub struct RenderTargetHandle(u64);
@@ -39,6 +82,14 @@ pub(crate) struct RenderTarget {
 
     pub(crate) size: Extent3d,
     pub(crate) format: TextureFormat,
+    // 深度/模板附件的格式，None 表示不带深度附件
+    pub(crate) depth_format: Option<TextureFormat>,
+    // 当前的采样数
+    pub(crate) sample_count: Msaa,
+    // 创建时固定的采样数，None 表示跟随全局 MSAA 设置
+    pub(crate) fixed_msaa: Option<Msaa>,
+    // 每帧第一个 Pass 的颜色加载方式：Clear 每帧清除为指定颜色，Load 保留上一帧的内容
+    pub(crate) clear_action: PassAction,
 }
 
 impl RenderTarget {
@@ -46,13 +97,24 @@ impl RenderTarget {
         context: &RenderContext,
         size: UVec2,
         sample_count: Msaa,
+    ) -> Self {
+        Self::new_ex(context, size, sample_count, context.surface_view_format(), Some(DEPTH_STENCIL_FORMAT))
+    }
+
+    /// 使用指定颜色格式创建渲染目标，`depth_format` 为 `None` 时不创建深度/模板纹理。
+    /// 调用方需要先确认格式可用（见 `validate_format` 和 `validate_depth_format`）。
+    pub(crate) fn new_ex(
+        context: &RenderContext,
+        size: UVec2,
+        sample_count: Msaa,
+        format: TextureFormat,
+        depth_format: Option<TextureFormat>,
     ) -> Self {
         let size_extent = Extent3d {
             width: size.x,
             height: size.y,
             depth_or_array_layers: 1,
         };
-        let format = context.config.format;
 
         // 1. 创建 Resolve 纹理 (单采样) - 只在 new 的时候创建一次
         let resolve_texture_descriptor = TextureDescriptor {
@@ -72,7 +134,7 @@ impl RenderTarget {
 
         // 2. 创建 MSAA 和 Depth 纹理 (可能需要多采样)
         let (msaa_texture, msaa_texture_view, depth_texture, depth_texture_view) =
-            Self::create_msaa_and_depth_textures(context, size_extent, format, sample_count);
+            Self::create_msaa_and_depth_textures(context, size_extent, format, sample_count, depth_format);
 
         Self {
             resolve_texture,
@@ -83,15 +145,79 @@ impl RenderTarget {
             depth_texture_view,
             size: size_extent,
             format,
+            depth_format,
+            sample_count,
+            fixed_msaa: None,
+            clear_action: PassAction::Load,
         }
     }
 
+    /// 按描述创建渲染目标，`global_msaa` 为描述没有固定采样数时使用的全局设置。
+    pub(crate) fn from_descriptor(
+        context: &RenderContext,
+        descriptor: &RenderTargetDescriptor,
+        global_msaa: Msaa,
+    ) -> Self {
+        let format = descriptor.format.unwrap_or(context.surface_view_format());
+        let sample_count = descriptor.msaa.unwrap_or(global_msaa);
+        let mut render_target =
+            Self::new_ex(context, descriptor.size, sample_count, format, descriptor.depth);
+        render_target.fixed_msaa = descriptor.msaa;
+        render_target
+    }
+
+    /// 检查颜色格式能否作为渲染目标使用（可渲染、可采样，并支持当前 MSAA 采样数）。
+    pub(crate) fn validate_format(
+        context: &RenderContext,
+        format: TextureFormat,
+        sample_count: Msaa,
+    ) -> Result<(), String> {
+        let features = context.adapter.get_texture_format_features(format);
+        let required = TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING;
+        if !features.allowed_usages.contains(required) {
+            return Err(format!("{:?} cannot be used as a render target on this adapter", format));
+        }
+        if !features.flags.sample_count_supported(sample_count.into()) {
+            return Err(format!("{:?} does not support {:?}", format, sample_count));
+        }
+        Ok(())
+    }
+
+    /// 检查深度/模板格式能否作为深度附件使用，并支持指定的 MSAA 采样数。
+    pub(crate) fn validate_depth_format(
+        context: &RenderContext,
+        format: TextureFormat,
+        sample_count: Msaa,
+    ) -> Result<(), String> {
+        if !format.has_depth_aspect() {
+            return Err(format!("{:?} is not a depth format", format));
+        }
+        let features = context.adapter.get_texture_format_features(format);
+        if !features.allowed_usages.contains(TextureUsages::RENDER_ATTACHMENT) {
+            return Err(format!("{:?} cannot be used as a depth attachment on this adapter", format));
+        }
+        if !features.flags.sample_count_supported(sample_count.into()) {
+            return Err(format!("{:?} does not support {:?}", format, sample_count));
+        }
+        Ok(())
+    }
+
+    /// 检查尺寸是否在设备的 `max_texture_dimension_2d` 限制之内
+    pub(crate) fn validate_size(context: &RenderContext, size: UVec2) -> Result<(), String> {
+        let max = context.device.limits().max_texture_dimension_2d;
+        if size.x > max || size.y > max {
+            return Err(format!("{}x{} exceeds the device max_texture_dimension_2d ({})", size.x, size.y, max));
+        }
+        Ok(())
+    }
+
     // 辅助函数：专门用于创建 MSAA 纹理和深度纹理
     fn create_msaa_and_depth_textures(
         context: &RenderContext,
         size: Extent3d,
         format: TextureFormat,
         sample_count: Msaa,
+        depth_format: Option<TextureFormat>,
     ) -> (Option<wgpu::Texture>, Option<wgpu::TextureView>, Option<wgpu::Texture>, Option<wgpu::TextureView>) {
         let mut msaa_texture: Option<wgpu::Texture> = None;
         let mut msaa_texture_view: Option<wgpu::TextureView> = None;
@@ -114,13 +240,17 @@ impl RenderTarget {
             msaa_texture_view = Some(d_texture_view);
         }
 
+        let Some(depth_format) = depth_format else {
+            return (msaa_texture, msaa_texture_view, None, None);
+        };
+
         let depth_texture_descriptor = wgpu::TextureDescriptor {
             label: Some("Depth Texture"),
             size,
             mip_level_count: 1,
             sample_count: sample_count.into(),
             dimension: TextureDimension::D2,
-            format: wgpu::TextureFormat::Depth32Float,
+            format: depth_format,
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
             view_formats: &[],
         };
@@ -131,14 +261,19 @@ impl RenderTarget {
     }
 
     /// 重建 RenderTarget 的纹理，通常是当 MSAA 设置改变时调用。
-    /// resolut_texture 不会被重建，因为它总是单采样。
+    /// resolut_texture 不会被重建，因为它总是单采样；固定了采样数的渲染目标不受影响。
     pub(crate) fn re_create(
         &mut self,
         context: &RenderContext,
         new_msaa: Msaa,
     ) {
+        if self.fixed_msaa.is_some() {
+            return;
+        }
+
         let (new_msaa_texture, new_msaa_texture_view, new_depth_texture, new_depth_texture_view) =
-        Self::create_msaa_and_depth_textures(context, self.size, self.format, new_msaa);
+        Self::create_msaa_and_depth_textures(context, self.size, self.format, new_msaa, self.depth_format);
+        self.sample_count = new_msaa;
 
         // 替换字段
         self.msaa_texture = new_msaa_texture;
@@ -147,14 +282,30 @@ impl RenderTarget {
         self.depth_texture_view = new_depth_texture_view;
     }
 
-    // 如果您也需要一个同时处理尺寸变化的 rebuild 方法，可以这样实现
+    /// 立即释放 GPU 上的 resolve、MSAA 和深度纹理，调用后该渲染目标不可再使用。
+    pub(crate) fn destroy(&self) {
+        self.resolve_texture.destroy();
+        if let Some(texture) = &self.msaa_texture {
+            texture.destroy();
+        }
+        if let Some(texture) = &self.depth_texture {
+            texture.destroy();
+        }
+    }
+
+    /// 按新的尺寸和采样数重建纹理。尺寸不变时只在采样数改变时重建 MSAA 和深度纹理，
+    /// 保证 Pass 的附件与按全局 MSAA 选择的管线采样数一致。
     pub(crate) fn rebuild_with_size_and_msaa(
         &mut self,
         context: &RenderContext,
         new_size: UVec2,
         new_msaa: Msaa,
     ) {
+        let new_msaa = self.fixed_msaa.unwrap_or(new_msaa);
         if self.size.width == new_size.x && self.size.height == new_size.y {
+            if self.sample_count != new_msaa {
+                self.re_create(context, new_msaa);
+            }
             return;
         }
 
@@ -180,12 +331,13 @@ impl RenderTarget {
 
         // 创建新的 MSAA 和 Depth 纹理
         let (new_msaa_texture, new_msaa_texture_view, new_depth_texture, new_depth_texture_view) =
-            Self::create_msaa_and_depth_textures(context, new_size_extent, self.format, new_msaa);
+            Self::create_msaa_and_depth_textures(context, new_size_extent, self.format, new_msaa, self.depth_format);
 
         self.msaa_texture = new_msaa_texture;
         self.msaa_texture_view = new_msaa_texture_view;
         self.depth_texture = new_depth_texture;
         self.depth_texture_view = new_depth_texture_view;
         self.size = new_size_extent;
+        self.sample_count = new_msaa;
     }
 }
@@ -1,6 +1,6 @@
 use winit::dpi::{LogicalSize, PhysicalSize, Size};
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Resolution {
     Physical(u32, u32),
     Logical(u32, u32),
@@ -37,6 +37,52 @@ impl Resolution {
     }
 }
 
+impl Resolution {
+    /// 宽高比（宽 / 高），高为 0 时返回 0
+    pub fn aspect_ratio(&self) -> f32 {
+        if self.height() == 0 {
+            return 0.0;
+        }
+        self.width() as f32 / self.height() as f32
+    }
+
+    /// 按比例缩放，保持 Physical/Logical 类型不变。结果四舍五入，且每边至少为 1
+    pub fn scaled(&self, factor: f32) -> Resolution {
+        let scale = |v: u32| ((v as f32 * factor).round() as u32).max(1);
+        self.with_size(scale(self.width()), scale(self.height()))
+    }
+
+    /// 保持宽高比缩小到 `max` 之内，本身已经放得下时原样返回。
+    /// 只比较数值，`max` 的 Physical/Logical 类型不参与换算
+    pub fn fit_within(&self, max: Resolution) -> Resolution {
+        if self.width() <= max.width() && self.height() <= max.height() {
+            return *self;
+        }
+
+        let factor = (max.width() as f32 / self.width() as f32)
+            .min(max.height() as f32 / self.height() as f32);
+        // 四舍五入可能超出 1 像素，再截断到 max 之内
+        let fitted = self.scaled(factor);
+        self.with_size(fitted.width().min(max.width().max(1)), fitted.height().min(max.height().max(1)))
+    }
+
+    // 保持类型不变，替换宽高
+    fn with_size(&self, width: u32, height: u32) -> Resolution {
+        match self {
+            Resolution::Physical(..) => Resolution::Physical(width, height),
+            Resolution::Logical(..) => Resolution::Logical(width, height),
+        }
+    }
+
+    /// 按缩放系数换算为物理像素尺寸
+    pub fn to_physical(self, scale_factor: f64) -> PhysicalSize<u32> {
+        match self {
+            Resolution::Physical(w, h) => PhysicalSize::new(w, h),
+            Resolution::Logical(w, h) => LogicalSize::new(w as f64, h as f64).to_physical(scale_factor),
+        }
+    }
+}
+
 impl From<Resolution> for Size {
     fn from(res: Resolution) -> Self {
         match res {
@@ -44,4 +90,87 @@ impl From<Resolution> for Size {
             Resolution::Logical(w, h) => Size::Logical(LogicalSize::new(w as f64, h as f64)),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scaled_rounds_half_away_from_zero() {
+        assert_eq!(Resolution::Physical(3, 5).scaled(0.5), Resolution::Physical(2, 3));
+        assert_eq!(Resolution::Physical(1, 3).scaled(1.5), Resolution::Physical(2, 5));
+        assert_eq!(Resolution::Physical(1280, 720).scaled(1.0), Resolution::Physical(1280, 720));
+    }
+
+    #[test]
+    fn scaled_keeps_variant() {
+        assert_eq!(Resolution::Logical(800, 600).scaled(2.0), Resolution::Logical(1600, 1200));
+        assert_eq!(Resolution::Physical(800, 600).scaled(2.0), Resolution::Physical(1600, 1200));
+    }
+
+    #[test]
+    fn scaled_is_at_least_one_pixel() {
+        assert_eq!(Resolution::Physical(0, 0).scaled(2.0), Resolution::Physical(1, 1));
+        assert_eq!(Resolution::Physical(1920, 1080).scaled(0.0), Resolution::Physical(1, 1));
+        assert_eq!(Resolution::Physical(1, 1).scaled(0.4), Resolution::Physical(1, 1));
+        assert_eq!(Resolution::Physical(100, 100).scaled(-1.0), Resolution::Physical(1, 1));
+        assert_eq!(Resolution::Physical(100, 100).scaled(f32::NAN), Resolution::Physical(1, 1));
+    }
+
+    #[test]
+    fn scaled_saturates_huge_factors() {
+        assert_eq!(Resolution::Physical(1920, 1080).scaled(1e10), Resolution::Physical(u32::MAX, u32::MAX));
+        assert_eq!(Resolution::Logical(2, 2).scaled(f32::INFINITY), Resolution::Logical(u32::MAX, u32::MAX));
+    }
+
+    #[test]
+    fn fit_within_keeps_aspect_ratio() {
+        assert_eq!(
+            Resolution::Physical(1920, 1080).fit_within(Resolution::Physical(1280, 1280)),
+            Resolution::Physical(1280, 720)
+        );
+        assert_eq!(
+            Resolution::Logical(1080, 1920).fit_within(Resolution::Physical(1000, 960)),
+            Resolution::Logical(540, 960)
+        );
+    }
+
+    #[test]
+    fn fit_within_returns_self_when_it_fits() {
+        let res = Resolution::Physical(640, 480);
+        assert_eq!(res.fit_within(Resolution::Physical(640, 480)), res);
+        assert_eq!(res.fit_within(Resolution::Physical(4000, 4000)), res);
+    }
+
+    #[test]
+    fn fit_within_never_exceeds_max() {
+        // 缩放后四舍五入可能多出 1 像素，结果仍要在 max 之内
+        for (w, h, max_w, max_h) in [(3, 2, 2, 2), (5, 3, 2, 1), (7, 3, 3, 3), (1921, 1081, 1280, 720), (999, 1, 10, 10)] {
+            let fitted = Resolution::Physical(w, h).fit_within(Resolution::Physical(max_w, max_h));
+            assert!(fitted.width() <= max_w && fitted.height() <= max_h, "{fitted:?} exceeds {max_w}x{max_h}");
+            assert!(fitted.width() >= 1 && fitted.height() >= 1);
+        }
+    }
+
+    #[test]
+    fn fit_within_zero_max_is_one_pixel() {
+        assert_eq!(
+            Resolution::Physical(1920, 1080).fit_within(Resolution::Physical(0, 0)),
+            Resolution::Physical(1, 1)
+        );
+    }
+
+    #[test]
+    fn aspect_ratio_of_zero_height_is_zero() {
+        assert_eq!(Resolution::Physical(1920, 0).aspect_ratio(), 0.0);
+        assert_eq!(Resolution::Physical(1920, 1080).aspect_ratio(), 16.0 / 9.0);
+    }
+
+    #[test]
+    fn to_physical_rounds_logical_sizes() {
+        assert_eq!(Resolution::Physical(101, 51).to_physical(2.0), PhysicalSize::new(101, 51));
+        assert_eq!(Resolution::Logical(100, 50).to_physical(1.5), PhysicalSize::new(150, 75));
+        assert_eq!(Resolution::Logical(3, 1).to_physical(1.5), PhysicalSize::new(5, 2));
+    }
+}
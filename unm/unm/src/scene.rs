@@ -0,0 +1,201 @@
+use async_trait::async_trait;
+use log::error;
+use unm_sfx::player::SfxManager;
+
+use crate::{
+    assets::Assets,
+    game_loop::GameLoop,
+    game_settings::GameSettings,
+    graphics::WgpuState,
+    input::{KeyInput, MouseInput, TouchInput},
+    tools::TimeManager,
+};
+
+/// `Scene::update` 返回的场景切换指令，在本帧 `update` 结束后执行
+pub enum SceneCommand {
+    /// 保持当前场景
+    None,
+    /// 把新场景压入栈顶，当前场景暂停更新
+    Push(Box<dyn Scene>),
+    /// 弹出当前场景，栈空时退出程序
+    Pop,
+    /// 用新场景替换当前场景：新场景进入成功后旧场景才出栈，进入失败时保留当前场景
+    Replace(Box<dyn Scene>),
+}
+
+/// `SceneStack` 中的一个场景，例如菜单、游戏、暂停界面。
+///
+/// 只有栈顶场景会收到 `update` 和 `fixed_update`；`draw` 在每帧 `update` 之后按从下到上的顺序调用，
+/// 栈顶场景的 `draw_under_previous` 返回 true 时，它下面的场景也会继续绘制（依次向下判断）
+#[async_trait]
+pub trait Scene: Send {
+    /// 场景入栈时调用，相当于 `GameLoop::start`。返回错误时场景不会入栈
+    async fn on_enter(
+        &mut self,
+        game_settings: &mut GameSettings,
+        graphics: &mut WgpuState,
+        sfx_manager: &mut SfxManager,
+        assets: &mut Assets,
+    ) -> anyhow::Result<()>;
+
+    /// 场景位于栈顶时每帧调用，返回的指令在本帧结束前执行
+    #[allow(clippy::too_many_arguments)]
+    async fn update(
+        &mut self,
+        game_settings: &mut GameSettings,
+        graphics: &mut WgpuState,
+        time_manager: &TimeManager,
+        sfx_manager: &mut SfxManager,
+        assets: &mut Assets,
+        mouse_input: &MouseInput,
+        touch_input: &TouchInput,
+        key_input: &mut KeyInput,
+    ) -> anyhow::Result<SceneCommand>;
+
+    /// 场景位于栈顶时以固定频率调用，见 `GameLoop::fixed_update`
+    async fn fixed_update(&mut self, _game_settings: &mut GameSettings, _fixed_dt: f32) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// 场景可见时每帧调用，用于记录绘制命令。被上层场景遮住时不会调用
+    fn draw(&mut self, _graphics: &mut WgpuState) {}
+
+    /// 场景出栈（`Pop`、`Replace`）或程序退出时调用
+    async fn on_exit(&mut self) {}
+
+    /// 位于本场景下面的场景是否继续绘制，暂停界面等半透明覆盖层应返回 true
+    fn draw_under_previous(&self) -> bool {
+        false
+    }
+}
+
+/// 以场景栈驱动游戏的 `GameLoop`，不需要多个场景的游戏可以直接实现 `GameLoop`
+pub struct SceneStack {
+    scenes: Vec<Box<dyn Scene>>,
+    // start 之前还未入栈的初始场景
+    initial: Option<Box<dyn Scene>>,
+}
+
+impl SceneStack {
+    pub fn new(initial: Box<dyn Scene>) -> Self {
+        Self { scenes: Vec::new(), initial: Some(initial) }
+    }
+
+    /// 栈中的场景数
+    pub fn len(&self) -> usize {
+        self.scenes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+
+    async fn push(
+        &mut self,
+        mut scene: Box<dyn Scene>,
+        game_settings: &mut GameSettings,
+        graphics: &mut WgpuState,
+        sfx_manager: &mut SfxManager,
+        assets: &mut Assets,
+    ) -> anyhow::Result<()> {
+        scene.on_enter(game_settings, graphics, sfx_manager, assets).await?;
+        self.scenes.push(scene);
+        Ok(())
+    }
+
+    async fn pop(&mut self) {
+        if let Some(mut scene) = self.scenes.pop() {
+            scene.on_exit().await;
+        }
+    }
+
+    // 最下面一个需要绘制的场景的下标
+    fn first_visible(&self) -> usize {
+        let mut first = self.scenes.len().saturating_sub(1);
+        while first > 0 && self.scenes[first].draw_under_previous() {
+            first -= 1;
+        }
+        first
+    }
+}
+
+#[async_trait]
+impl GameLoop for SceneStack {
+    async fn start(
+        &mut self,
+        game_settings: &mut GameSettings,
+        graphics: &mut WgpuState,
+        sfx_manager: &mut SfxManager,
+        assets: &mut Assets,
+    ) -> anyhow::Result<()> {
+        if let Some(initial) = self.initial.take() {
+            self.push(initial, game_settings, graphics, sfx_manager, assets).await?;
+        }
+        Ok(())
+    }
+
+    async fn update(
+        &mut self,
+        game_settings: &mut GameSettings,
+        graphics: &mut WgpuState,
+        time_manager: &TimeManager,
+        sfx_manager: &mut SfxManager,
+        assets: &mut Assets,
+        mouse_input: &MouseInput,
+        touch_input: &TouchInput,
+        key_input: &mut KeyInput,
+    ) -> anyhow::Result<()> {
+        let Some(top) = self.scenes.last_mut() else {
+            game_settings.quit();
+            return Ok(());
+        };
+        let command = top
+            .update(game_settings, graphics, time_manager, sfx_manager, assets, mouse_input, touch_input, key_input)
+            .await?;
+
+        match command {
+            SceneCommand::None => {}
+            SceneCommand::Push(scene) => {
+                // 新场景进入失败时保留当前场景
+                if let Err(e) = self.push(scene, game_settings, graphics, sfx_manager, assets).await {
+                    error!("Scene failed to enter: {:#}", e);
+                }
+            }
+            SceneCommand::Pop => self.pop().await,
+            SceneCommand::Replace(mut scene) => {
+                // 新场景先进入，成功后再让旧场景出栈；失败时与 Push 一样保留当前场景
+                match scene.on_enter(game_settings, graphics, sfx_manager, assets).await {
+                    Ok(()) => {
+                        self.pop().await;
+                        self.scenes.push(scene);
+                    }
+                    Err(e) => error!("Scene failed to enter: {:#}", e),
+                }
+            }
+        }
+
+        if self.scenes.is_empty() {
+            game_settings.quit();
+            return Ok(());
+        }
+
+        let first = self.first_visible();
+        for scene in self.scenes[first..].iter_mut() {
+            scene.draw(graphics);
+        }
+        Ok(())
+    }
+
+    async fn fixed_update(&mut self, game_settings: &mut GameSettings, fixed_dt: f32) -> anyhow::Result<()> {
+        match self.scenes.last_mut() {
+            Some(top) => top.fixed_update(game_settings, fixed_dt).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn on_exit(&mut self) {
+        while !self.scenes.is_empty() {
+            self.pop().await;
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+
+use ab_glyph::{Font as _, FontArc, GlyphId, PxScale, ScaleFont};
+use glam::{uvec2, vec2, vec3, UVec2, Vec2};
+use log::error;
+use unm_tools::id_map::IdMapKey;
+use wgpu::TextureFormat;
+
+use crate::{
+    graphics::WgpuState,
+    render_context::RenderContext,
+    texture::{Texture2D, Texture2DHandle},
+    vertex::Vertex,
+};
+
+/// 字形图集的边长（像素）
+const GLYPH_ATLAS_SIZE: u32 = 1024;
+// 相邻字形之间留出的空隙，避免线性过滤时采样到隔壁字形
+const GLYPH_PADDING: u32 = 1;
+
+#[derive(Default, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub struct FontHandle(u64);
+
+impl IdMapKey for FontHandle {
+    fn from(id: u64) -> Self {
+        FontHandle(id)
+    }
+    fn to(&self) -> u64 {
+        self.0
+    }
+}
+
+#[derive(Default, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub struct GlyphAtlasHandle(u64);
+
+impl IdMapKey for GlyphAtlasHandle {
+    fn from(id: u64) -> Self {
+        GlyphAtlasHandle(id)
+    }
+    fn to(&self) -> u64 {
+        self.0
+    }
+}
+
+pub(crate) struct Font {
+    pub(crate) font: FontArc,
+}
+
+/// 单个字形在图集中的位置
+#[derive(Debug, Clone, Copy)]
+struct GlyphInfo {
+    uv_min: Vec2,
+    uv_max: Vec2,
+    // 字形位图的像素尺寸
+    size: Vec2,
+    // 位图左上角相对于笔位置（基线上）的偏移，y 轴向下
+    offset: Vec2,
+}
+
+/// 某个字体在某个像素大小下的字形图集。
+///
+/// 字形在第一次绘制时光栅化，按行（shelf）依次排入图集纹理，图集纹理存放在 `texture2ds` 中。
+/// 纹理为白色，覆盖率写入 alpha 通道，绘制时与顶点颜色相乘。
+pub(crate) struct GlyphAtlas {
+    pub(crate) texture: Texture2DHandle,
+    px_size: u32,
+    size: UVec2,
+    // None 表示该字形没有轮廓（空格等）或图集已满
+    glyphs: HashMap<GlyphId, Option<GlyphInfo>>,
+    cursor: UVec2,
+    row_height: u32,
+    full_reported: bool,
+}
+
+impl GlyphAtlas {
+    pub(crate) fn new(texture: Texture2DHandle, px_size: u32, size: UVec2) -> Self {
+        Self {
+            texture,
+            px_size,
+            size,
+            glyphs: HashMap::new(),
+            cursor: UVec2::ZERO,
+            row_height: 0,
+            full_reported: false,
+        }
+    }
+
+    /// 创建图集使用的空白纹理
+    pub(crate) fn create_texture(context: &RenderContext, layout: &wgpu::BindGroupLayout) -> Texture2D {
+        Texture2D::new_empty(
+            context,
+            layout,
+            uvec2(GLYPH_ATLAS_SIZE, GLYPH_ATLAS_SIZE),
+            TextureFormat::Rgba8Unorm,
+            Some("Glyph Atlas Texture"),
+        )
+    }
+
+    // 在当前行放不下时换行，图集放满时返回 None
+    fn allocate(&mut self, size: UVec2) -> Option<UVec2> {
+        let padded = size + UVec2::splat(GLYPH_PADDING);
+        if self.cursor.x + padded.x > self.size.x {
+            self.cursor = uvec2(0, self.cursor.y + self.row_height);
+            self.row_height = 0;
+        }
+        if self.cursor.x + padded.x > self.size.x || self.cursor.y + padded.y > self.size.y {
+            return None;
+        }
+
+        let origin = self.cursor;
+        self.cursor.x += padded.x;
+        self.row_height = self.row_height.max(padded.y);
+        Some(origin)
+    }
+
+    // 查找字形，不存在时光栅化并上传到图集纹理
+    fn glyph(
+        &mut self,
+        context: &RenderContext,
+        texture: &Texture2D,
+        font: &FontArc,
+        id: GlyphId,
+    ) -> Option<GlyphInfo> {
+        if let Some(info) = self.glyphs.get(&id) {
+            return *info;
+        }
+
+        let glyph = id.with_scale(PxScale::from(self.px_size as f32));
+        let info = font.outline_glyph(glyph).and_then(|outlined| {
+            let bounds = outlined.px_bounds();
+            let size = uvec2(bounds.width().ceil() as u32, bounds.height().ceil() as u32);
+            if size.x == 0 || size.y == 0 {
+                return None;
+            }
+
+            let Some(origin) = self.allocate(size) else {
+                if !self.full_reported {
+                    error!("Glyph atlas ({} px) is full, remaining glyphs are skipped.", self.px_size);
+                    self.full_reported = true;
+                }
+                return None;
+            };
+
+            let mut pixels = vec![0u8; (size.x * size.y * 4) as usize];
+            outlined.draw(|x, y, coverage| {
+                if x < size.x && y < size.y {
+                    let i = ((y * size.x + x) * 4) as usize;
+                    pixels[i..i + 3].fill(255);
+                    pixels[i + 3] = (coverage.clamp(0.0, 1.0) * 255.0) as u8;
+                }
+            });
+            texture.write(context, origin, size, &pixels);
+
+            let atlas_size = self.size.as_vec2();
+            Some(GlyphInfo {
+                uv_min: origin.as_vec2() / atlas_size,
+                uv_max: (origin + size).as_vec2() / atlas_size,
+                size: size.as_vec2(),
+                offset: vec2(bounds.min.x, bounds.min.y),
+            })
+        });
+
+        self.glyphs.insert(id, info);
+        info
+    }
+
+    /// 生成一段文本的四边形，`position` 为第一行文本的左上角。
+    /// 坐标系 y 轴向上，`\n` 换行，字形之间应用字距调整。
+    pub(crate) fn layout(
+        &mut self,
+        context: &RenderContext,
+        texture: &Texture2D,
+        font: &FontArc,
+        text: &str,
+        position: Vec2,
+        color: wgpu::Color,
+    ) -> (Vec<Vertex>, Vec<u32>) {
+        let scaled = font.as_scaled(PxScale::from(self.px_size as f32));
+        let line_height = scaled.height() + scaled.line_gap();
+
+        let mut vertices = Vec::with_capacity(text.len() * 4);
+        let mut indices = Vec::with_capacity(text.len() * 6);
+
+        let mut pen = vec2(position.x, position.y - scaled.ascent());
+        let mut previous: Option<GlyphId> = None;
+
+        for c in text.chars() {
+            if c == '\n' {
+                pen = vec2(position.x, pen.y - line_height);
+                previous = None;
+                continue;
+            }
+
+            let id = scaled.glyph_id(c);
+            if let Some(prev) = previous {
+                pen.x += scaled.kern(prev, id);
+            }
+            previous = Some(id);
+
+            if let Some(info) = self.glyph(context, texture, font, id) {
+                let left = pen.x + info.offset.x;
+                let right = left + info.size.x;
+                let top = pen.y - info.offset.y;
+                let bottom = top - info.size.y;
+
+                let base = vertices.len() as u32;
+                vertices.extend_from_slice(&[
+                    // 0: Top-left
+                    Vertex::new(vec3(left, top, 0.0), info.uv_min, color),
+                    // 1: Top-right
+                    Vertex::new(vec3(right, top, 0.0), vec2(info.uv_max.x, info.uv_min.y), color),
+                    // 2: Bottom-right
+                    Vertex::new(vec3(right, bottom, 0.0), info.uv_max, color),
+                    // 3: Bottom-left
+                    Vertex::new(vec3(left, bottom, 0.0), vec2(info.uv_min.x, info.uv_max.y), color),
+                ]);
+                indices.extend([3, 2, 0, 0, 2, 1].map(|i| base + i));
+            }
+
+            pen.x += scaled.h_advance(id);
+        }
+
+        (vertices, indices)
+    }
+}
+
+#[deprecated(note = "使用 WgpuState::load_font_from_memory")]
+pub fn load_font_from_memory(graphics: &mut WgpuState, bytes: Vec<u8>) -> Option<FontHandle> {
+    graphics.load_font_from_memory(bytes)
+}
+
+#[deprecated(note = "使用 WgpuState::load_font")]
+pub async fn load_font(graphics: &mut WgpuState, file_path: &str) -> Option<FontHandle> {
+    graphics.load_font(file_path).await
+}
@@ -1,10 +1,16 @@
+use glam::UVec2;
 use log::error;
 use unm_tools::id_map::IdMapKey;
-use wgpu::{Sampler, Texture, TextureView};
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindingResource, Device,
+    Extent3d, Origin3d, Sampler, SamplerDescriptor, TexelCopyTextureInfo, Texture,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureView,
+    TextureViewDescriptor,
+};
 
-use crate::{get_context, get_quad_context};
+use crate::{graphics::WgpuState, render_context::RenderContext};
 
-#[derive(Default, Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Default, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
 pub struct Texture2DHandle(u64);
 
 impl IdMapKey for Texture2DHandle {
@@ -20,27 +26,115 @@ pub struct Texture2D {
     texture: Texture,
     texture_view: TextureView,
     sampler: Sampler,
+    // 纹理 + 采样器的绑定组，布局与后处理材质的源纹理相同
+    pub(crate) bind_group: BindGroup,
+    pub(crate) size: UVec2,
 }
 
 impl Texture2D {
-    pub(crate) fn new(texture: Texture, texture_view: TextureView, sampler: Sampler) -> Self {
+    pub(crate) fn new(
+        device: &Device,
+        layout: &BindGroupLayout,
+        texture: Texture,
+        texture_view: TextureView,
+        sampler: Sampler,
+    ) -> Self {
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Texture2D Bind Group"),
+            layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&texture_view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+        let size = UVec2::new(texture.width(), texture.height());
+
         Self {
             texture,
             texture_view,
             sampler,
+            bind_group,
+            size,
         }
     }
+
+    /// 创建内容为空的纹理，之后通过 `write` 按区域上传像素（字形图集、白色占位纹理等）。
+    pub(crate) fn new_empty(
+        context: &RenderContext,
+        layout: &BindGroupLayout,
+        size: UVec2,
+        format: TextureFormat,
+        label: Option<&str>,
+    ) -> Self {
+        let texture = context.device.create_texture(&TextureDescriptor {
+            label,
+            size: Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let texture_view = texture.create_view(&TextureViewDescriptor::default());
+        let sampler = context.device.create_sampler(&SamplerDescriptor {
+            label: Some("Texture Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self::new(&context.device, layout, texture, texture_view, sampler)
+    }
+
+    /// 将紧密排列的像素数据写入 `origin` 开始、大小为 `size` 的区域。
+    pub(crate) fn write(&self, context: &RenderContext, origin: UVec2, size: UVec2, data: &[u8]) {
+        let bytes_per_pixel = self.texture.format().block_copy_size(None).unwrap_or(4);
+        context.queue.write_texture(
+            TexelCopyTextureInfo {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: Origin3d {
+                    x: origin.x,
+                    y: origin.y,
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            data,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(size.x * bytes_per_pixel),
+                rows_per_image: Some(size.y),
+            },
+            Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
 }
 
 pub(crate) async fn load_texture(
+    ctx: &mut WgpuState,
     file_path: &str,
     label: Option<&str>,
     address_mode: wgpu::AddressMode,
 ) -> Option<Texture2DHandle> {
-    let ctx = get_quad_context();
     match ctx
         .context
-        .load_texture(file_path, label, address_mode)
+        .load_texture(file_path, label, address_mode, ctx.blitter.bind_group_layout())
         .await
     {
         Ok(new_texture2d) => Some(ctx.texture2ds.insert(new_texture2d)),
@@ -0,0 +1,137 @@
+use glam::{vec2, UVec2, Vec2};
+use log::error;
+
+use crate::{
+    camera::Rect,
+    graphics::{rect_vertices_uv, WgpuState, RECT_INDICES},
+    texture::Texture2DHandle,
+};
+
+/// 使用一张图集纹理的瓦片地图。
+///
+/// 瓦片序号按图集从左到右、从上到下编号；地图第 0 行位于最下方（y 轴向上），
+/// `(x, y)` 处的瓦片占据 `origin + (x, y) * tile_size` 开始的一个格子。
+/// 每次 `draw` 只生成可见范围内非空瓦片的四边形，并作为一个绘制命令提交。
+#[derive(Debug, Clone)]
+pub struct TileMap {
+    atlas: Texture2DHandle,
+    // 图集中单个瓦片的像素尺寸
+    atlas_tile_size: UVec2,
+    // 单个瓦片在世界空间中的尺寸
+    tile_size: Vec2,
+    width: u32,
+    height: u32,
+    // 行优先，None 表示空瓦片
+    tiles: Vec<Option<u32>>,
+    origin: Vec2,
+    tint: wgpu::Color,
+}
+
+impl TileMap {
+    /// 创建 `width` x `height` 的空地图
+    pub fn new(atlas: Texture2DHandle, atlas_tile_size: UVec2, tile_size: Vec2, width: u32, height: u32) -> Self {
+        Self {
+            atlas,
+            atlas_tile_size: atlas_tile_size.max(UVec2::ONE),
+            tile_size,
+            width,
+            height,
+            tiles: vec![None; (width * height) as usize],
+            origin: Vec2::ZERO,
+            tint: wgpu::Color::WHITE,
+        }
+    }
+
+    /// 地图左下角在世界空间中的位置
+    pub fn set_origin(&mut self, origin: Vec2) {
+        self.origin = origin;
+    }
+
+    /// 整张地图的颜色，写入顶点颜色
+    pub fn set_tint(&mut self, tint: wgpu::Color) {
+        self.tint = tint;
+    }
+
+    pub fn get_tile(&self, x: u32, y: u32) -> Option<u32> {
+        self.index(x, y).and_then(|i| self.tiles[i])
+    }
+
+    /// 设置 `(x, y)` 处的瓦片，`None` 表示清空。坐标超出地图时返回 false
+    pub fn set_tile(&mut self, x: u32, y: u32, tile: Option<u32>) -> bool {
+        match self.index(x, y) {
+            Some(i) => {
+                self.tiles[i] = tile;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 用行优先的瓦片序号填充整张地图，长度必须等于 `width * height`
+    pub fn set_tiles(&mut self, tiles: Vec<Option<u32>>) -> bool {
+        if tiles.len() != self.tiles.len() {
+            error!("TileMap expects {} tiles, got {}.", self.tiles.len(), tiles.len());
+            return false;
+        }
+        self.tiles = tiles;
+        true
+    }
+
+    fn index(&self, x: u32, y: u32) -> Option<usize> {
+        (x < self.width && y < self.height).then(|| (y * self.width + x) as usize)
+    }
+
+    /// 绘制地图。`cull` 为 true 时只生成当前相机可见范围内的瓦片
+    pub fn draw(&self, ctx: &mut WgpuState, z_order: u32, cull: bool) {
+        let Some(atlas_size) = ctx.texture_size(self.atlas) else {
+            error!("TileMap atlas {:?} not found.", self.atlas);
+            return;
+        };
+        let columns = atlas_size.x / self.atlas_tile_size.x;
+        let rows = atlas_size.y / self.atlas_tile_size.y;
+        if columns == 0 || rows == 0 || self.tile_size.x <= 0.0 || self.tile_size.y <= 0.0 {
+            return;
+        }
+
+        // 可见范围换算为瓦片坐标
+        let (min, max) = if cull {
+            let view = ctx.camera_view_bounds();
+            let min = ((vec2(view.x, view.y) - self.origin) / self.tile_size).floor();
+            let max = ((vec2(view.x + view.w, view.y + view.h) - self.origin) / self.tile_size).ceil();
+            (
+                min.max(Vec2::ZERO).as_uvec2(),
+                max.min(vec2(self.width as f32, self.height as f32)).max(Vec2::ZERO).as_uvec2(),
+            )
+        } else {
+            (UVec2::ZERO, UVec2::new(self.width, self.height))
+        };
+
+        let uv_tile = self.atlas_tile_size.as_vec2() / atlas_size.as_vec2();
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        for y in min.y..max.y {
+            for x in min.x..max.x {
+                let Some(tile) = self.tiles[(y * self.width + x) as usize] else {
+                    continue;
+                };
+                if tile >= columns * rows {
+                    continue;
+                }
+
+                let uv_min = vec2((tile % columns) as f32, (tile / columns) as f32) * uv_tile;
+                let position = self.origin + vec2(x as f32, y as f32) * self.tile_size;
+                let dest = Rect { x: position.x, y: position.y, w: self.tile_size.x, h: self.tile_size.y };
+
+                let base = vertices.len() as u32;
+                vertices.extend_from_slice(&rect_vertices_uv(dest, uv_min, uv_min + uv_tile, self.tint));
+                indices.extend(RECT_INDICES.map(|i| base + i));
+            }
+        }
+
+        if indices.is_empty() {
+            return;
+        }
+        let textured_mat = ctx.textured_mat;
+        ctx.record_draw_command_ex(&vertices, &indices, z_order, textured_mat, Some(self.atlas));
+    }
+}
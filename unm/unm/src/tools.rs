@@ -1,12 +1,17 @@
 pub mod fps_limiter;
 pub mod time_manager;
 pub mod platform_specific;
+pub mod easing;
+pub mod scheduler;
+pub mod profiler;
 
 #[cfg(target_os = "android")]
 pub mod jni_utils;
 
 pub use fps_limiter::*;
 pub use time_manager::*;
+pub use easing::*;
+pub use profiler::*;
 
 #[cfg(target_os = "android")]
 pub use jni_utils::*;
\ No newline at end of file
@@ -20,14 +20,69 @@
 // SOFTWARE.
 
 use std::time::{Duration, Instant};
-use log::info;
+use log::{info, warn};
 use winit::window::Window;
 
-use crate::{game_settings::GameSettings, tools::TimeManager};
+use crate::{game_settings::{FrameRate, GameSettings}, monitor::window_refresh_rate_millihertz, tools::TimeManager};
 
+/// Hybrid 策略默认的忙等阈值
+pub const DEFAULT_SPIN_THRESHOLD: Duration = Duration::from_millis(1);
 
-/// 内部逻辑：根据平台选择获取刷新率的方式
-fn get_platform_refresh_rate(window: &Window) -> f64 {
+/// 限帧时等待到下一帧的方式，见 `GameSettings::set_sleep_strategy`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SleepStrategy {
+    /// 全程忙等，精度最高，但等待期间占满一个 CPU 核心
+    SpinAccurate,
+    /// 先由系统休眠，距离截止时间不足阈值时改为忙等，精度损失通常小于 0.5ms
+    Hybrid(Duration),
+    /// 只使用系统休眠，CPU 占用最低，精度取决于系统的定时器（通常约 1ms）
+    OsSleep,
+}
+
+impl Default for SleepStrategy {
+    fn default() -> Self {
+        Self::Hybrid(DEFAULT_SPIN_THRESHOLD)
+    }
+}
+
+// 忙等到截止时间
+fn spin_until(deadline: Instant) {
+    while Instant::now() < deadline {
+        std::hint::spin_loop();
+    }
+}
+
+fn sleep_with_strategy(duration: Duration, strategy: SleepStrategy) {
+    match strategy {
+        SleepStrategy::SpinAccurate => spin_until(Instant::now() + duration),
+        SleepStrategy::Hybrid(threshold) => {
+            // SpinSleeper 的精度参数不超过 1 秒
+            let threshold_ns = threshold.as_nanos().min(999_999_999) as u32;
+            spin_sleep::SpinSleeper::new(threshold_ns).sleep(duration);
+        }
+        SleepStrategy::OsSleep => spin_sleep::native_sleep(duration),
+    }
+}
+
+async fn sleep_with_strategy_tokio(duration: Duration, strategy: SleepStrategy) {
+    let deadline = Instant::now() + duration;
+    match strategy {
+        SleepStrategy::SpinAccurate => spin_until(deadline),
+        SleepStrategy::Hybrid(threshold) => {
+            tokio::time::sleep(duration.saturating_sub(threshold)).await;
+            spin_until(deadline);
+        }
+        SleepStrategy::OsSleep => tokio::time::sleep(duration).await,
+    }
+}
+
+
+/// 检测到的刷新率在没有窗口移动等事件时的更新间隔
+pub(crate) const REFRESH_RATE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 内部逻辑：根据平台选择获取刷新率的方式。
+/// Android 上每次都要经过 JNI，结果缓存在 `GameSettings` 中，不要每帧调用
+pub(crate) fn query_refresh_rate(window: &Window) -> f64 {
     #[cfg(target_os = "android")]
     {
         use crate::tools::get_refresh_rate;
@@ -38,38 +93,71 @@ fn get_platform_refresh_rate(window: &Window) -> f64 {
 
     #[cfg(not(target_os = "android"))]
     {
-        // 其他平台（Windows, macOS, Linux）使用 winit 标准接口
-        window
-            .current_monitor()
-            .and_then(|m| m.refresh_rate_millihertz())
+        // 其他平台（Windows, macOS, Linux）使用 winit 标准接口，
+        // 独占全屏时使用所选显示模式的刷新率
+        window_refresh_rate_millihertz(window)
             .map(|mhz| mhz as f32 / 1000.0)
             .unwrap_or(120.0) as f64 - 0.5
     }
 }
 
-pub fn detect_frametime(window: &Window) -> Duration {
-    let refresh_rate = get_platform_refresh_rate(window);
-    Duration::from_secs_f64(1.0 / refresh_rate)
+pub fn detect_frametime(game_settings: &GameSettings) -> Duration {
+    Duration::from_secs_f64(1.0 / game_settings.get_refresh_rate())
+}
+
+// 单帧耗时超过 目标帧时间 × 系数 时打印警告，附带帧号和耗时最长的阶段
+fn check_frame_budget(frame_time: Duration, target_frametime: Duration, timer: &TimeManager, game_settings: &GameSettings) {
+    let Some(factor) = game_settings.get_frame_budget_factor() else {
+        return;
+    };
+
+    let budget = target_frametime.mul_f32(factor);
+    if frame_time > budget {
+        let (phase, phase_time) = timer.get_phase_times().longest();
+        warn!(
+            "Frame {} took {:.2}ms, over budget {:.2}ms by {:.2}ms (longest phase: {} {:.2}ms)",
+            timer.get_frame_count(),
+            frame_time.as_secs_f64() * 1000.0,
+            budget.as_secs_f64() * 1000.0,
+            (frame_time - budget).as_secs_f64() * 1000.0,
+            phase,
+            phase_time.as_secs_f64() * 1000.0,
+        );
+    }
+}
+
+// Vsync 由 Fifo 呈现模式等待垂直同步，Unlimited 不限帧率，两者都不在 CPU 上等待，
+// 只更新计时并以显示器刷新间隔检查帧预算
+fn skip_limiter(timer: &mut TimeManager, game_settings: &GameSettings) {
+    let frame_time = timer.sleep_end.elapsed();
+    check_frame_budget(frame_time, detect_frametime(game_settings), timer, game_settings);
+
+    timer.sleep_end = Instant::now();
+    timer.sleep_timer.frametime = frame_time;
+    timer.sleep_timer.oversleep = Duration::ZERO;
 }
 
 #[allow(dead_code)]
 pub fn framerate_limiter(
-    window: &'static Window,
     timer: &mut TimeManager,
     game_settings: &GameSettings
 ) {
-    let target_fps = game_settings.get_target_fps();
-    let limit = if target_fps > 0 {
-        Duration::from_secs_f64(1.0 / target_fps as f64)
-    } else {
-        detect_frametime(window)
+    let target_fps = match game_settings.get_target_fps() {
+        FrameRate::Cap(fps) if fps > 0 => fps,
+        _ => {
+            skip_limiter(timer, game_settings);
+            return;
+        }
     };
+    let limit = Duration::from_secs_f64(1.0 / target_fps as f64);
 
     let frame_time = timer.sleep_end.elapsed();
     let oversleep = timer.sleep_timer.oversleep;
 
+    check_frame_budget(frame_time, limit, timer, game_settings);
+
     let sleep_time = limit.saturating_sub(frame_time + oversleep);
-    spin_sleep::sleep(sleep_time);
+    sleep_with_strategy(sleep_time, game_settings.get_sleep_strategy());
 
     let frame_time_total = timer.sleep_end.elapsed();
     timer.sleep_end = Instant::now();
@@ -78,24 +166,26 @@ pub fn framerate_limiter(
     timer.sleep_timer.oversleep = frame_time_total.saturating_sub(limit);
 }
 
-#[allow(unused_variables)]
 pub async fn framerate_limiter_tokio(
-    window: &'static Window,
     timer: &mut TimeManager,
     game_settings: &GameSettings
 ) {
-    let target_fps = game_settings.get_target_fps();
-    let limit = if target_fps > 0 {
-        Duration::from_secs_f64(1.0 / target_fps as f64)
-    } else {
-        detect_frametime(window)
+    let target_fps = match game_settings.get_target_fps() {
+        FrameRate::Cap(fps) if fps > 0 => fps,
+        _ => {
+            skip_limiter(timer, game_settings);
+            return;
+        }
     };
+    let limit = Duration::from_secs_f64(1.0 / target_fps as f64);
 
     let frame_time = timer.sleep_end.elapsed();
     let oversleep = timer.sleep_timer.oversleep;
 
+    check_frame_budget(frame_time, limit, timer, game_settings);
+
     let sleep_time = limit.saturating_sub(frame_time + oversleep);
-    tokio::time::sleep(sleep_time).await;
+    sleep_with_strategy_tokio(sleep_time, game_settings.get_sleep_strategy()).await;
 
     let frame_time_total = timer.sleep_end.elapsed();
     timer.sleep_end = Instant::now();
@@ -1,42 +1,70 @@
 use crate::ANDROID_APP;
-use jni::{ JavaVM, objects::JObject };
+use crate::game_settings::Orientation;
+use jni::{ JavaVM, objects::{ JObject, JValue, JValueOwned } };
+use log::error;
 
-pub fn get_refresh_rate() -> f32 {
-    let Some(app) = ANDROID_APP.get() else {
-        return 120.0;
-    };
+// 附加当前线程并调用 Activity 上的 Java 方法，用 read 取出返回值，出错时打印日志并返回 None。
+// Java 端抛出的异常不清除会一直挂起，导致这个线程上之后的 JNI 调用全部失败，因此每次调用后都检查并清除
+fn call_activity_method<R>(
+    name: &str,
+    sig: &str,
+    args: &[JValue],
+    read: impl FnOnce(JValueOwned) -> jni::errors::Result<R>,
+) -> Option<R> {
+    let app = ANDROID_APP.get()?;
 
     unsafe {
-        let vm = JavaVM::from_raw(app.vm_as_ptr() as *mut _).expect("Failed to get JVM");
-        let mut env = vm.attach_current_thread().expect("Failed to attach thread");
-        let activity = JObject::from_raw(app.activity_as_ptr() as jni::sys::jobject);
-
-        // 修复：显式调用方法并处理返回值
-        // 获取 Result 后，先得到 JValue，再转成 f32
-        match env.call_method(&activity, "getRefreshRate", "()F", &[]) {
-            Ok(val) => {
-                // val 是 JValueGen 枚举，调用 f() 会尝试转为 f32
-                // 为解决 type annotations needed，我们可以直接匹配或者显式指定
-                val.f().unwrap_or(120.0)
-            },
+        let vm = match JavaVM::from_raw(app.vm_as_ptr() as *mut _) {
+            Ok(vm) => vm,
+            Err(e) => {
+                error!("JNI {name}: failed to get JVM: {:?}", e);
+                return None;
+            }
+        };
+        let mut env = match vm.attach_current_thread() {
+            Ok(env) => env,
             Err(e) => {
-                eprintln!("JNI error: {:?}", e);
-                120.0
+                error!("JNI {name}: failed to attach thread: {:?}", e);
+                return None;
             }
+        };
+        let activity = JObject::from_raw(app.activity_as_ptr() as jni::sys::jobject);
+
+        let result = env.call_method(&activity, name, sig, args).and_then(read);
+        if let Err(e) = &result {
+            error!("JNI {name} failed: {:?}", e);
+        }
+        if env.exception_check().unwrap_or(false) {
+            env.exception_describe().ok();
+            env.exception_clear().ok();
         }
+        result.ok()
     }
 }
 
+pub fn get_refresh_rate() -> f32 {
+    call_activity_method("getRefreshRate", "()F", &[], |val| val.f()).unwrap_or(120.0)
+}
+
 pub fn call_game_ready() {
-    let Some(app) = ANDROID_APP.get() else {
-        return;
+    call_activity_method("GameReady", "()V", &[], |val| val.v());
+}
+
+pub fn show_soft_keyboard(show: bool) {
+    call_activity_method("showSoftKeyboard", "(Z)V", &[show.into()], |val| val.v());
+}
+
+pub fn set_orientation(orientation: Orientation) {
+    // 对应 ActivityInfo.SCREEN_ORIENTATION_UNSPECIFIED / SENSOR_LANDSCAPE / SENSOR_PORTRAIT
+    let value: i32 = match orientation {
+        Orientation::Auto => -1,
+        Orientation::Landscape => 6,
+        Orientation::Portrait => 7,
     };
 
-    unsafe {
-        let vm = JavaVM::from_raw(app.vm_as_ptr() as *mut _).expect("Failed to get JVM");
-        let mut env = vm.attach_current_thread().expect("Failed to attach thread");
-        let activity = JObject::from_raw(app.activity_as_ptr() as jni::sys::jobject);
+    call_activity_method("setOrientation", "(I)V", &[value.into()], |val| val.v());
+}
 
-        env.call_method(&activity, "GameReady", "()V", &[]).ok();
-    }
-}
\ No newline at end of file
+pub fn set_keep_screen_on(keep_on: bool) {
+    call_activity_method("setKeepScreenOn", "(Z)V", &[keep_on.into()], |val| val.v());
+}
@@ -0,0 +1,108 @@
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+// 本帧正在记录的各作用域耗时。渲染任务可能在 tokio 的不同工作线程之间切换，因此使用全局表而不是线程局部变量
+static FRAME_PROFILE: LazyLock<Mutex<HashMap<&'static str, Duration>>> = LazyLock::new(Default::default);
+
+/// 可以暂停、继续的计时器
+#[derive(Debug, Clone, Default)]
+pub struct Stopwatch {
+    // 正在计时时为本段计时的开始时间
+    started_at: Option<Instant>,
+    // 之前各段累计的时间
+    accumulated: Duration,
+}
+
+impl Stopwatch {
+    /// 创建一个未开始的计时器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 创建并立即开始计时
+    pub fn start_new() -> Self {
+        let mut stopwatch = Self::new();
+        stopwatch.start();
+        stopwatch
+    }
+
+    /// 开始或继续计时，已经在计时时无效
+    pub fn start(&mut self) {
+        self.started_at.get_or_insert_with(Instant::now);
+    }
+
+    /// 暂停计时，保留已经累计的时间
+    pub fn stop(&mut self) {
+        if let Some(started_at) = self.started_at.take() {
+            self.accumulated += started_at.elapsed();
+        }
+    }
+
+    /// 清零并停止计时
+    pub fn reset(&mut self) {
+        self.started_at = None;
+        self.accumulated = Duration::ZERO;
+    }
+
+    /// 清零并重新开始计时，返回清零前累计的时间
+    pub fn restart(&mut self) -> Duration {
+        let elapsed = self.elapsed();
+        self.accumulated = Duration::ZERO;
+        self.started_at = Some(Instant::now());
+        elapsed
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.started_at.is_some()
+    }
+
+    /// 累计的时间，包括正在进行的一段
+    pub fn elapsed(&self) -> Duration {
+        self.accumulated + self.started_at.map_or(Duration::ZERO, |started_at| started_at.elapsed())
+    }
+}
+
+/// 命名的性能分析作用域，离开作用域（drop）时把耗时累加到本帧同名的记录中。
+/// 通常通过 `profile_scope!` 使用，本帧的结果在下一帧通过 `TimeManager::get_profile_scopes` 获取
+pub struct ProfileScope {
+    name: &'static str,
+    start: Instant,
+}
+
+impl ProfileScope {
+    pub fn new(name: &'static str) -> Self {
+        Self { name, start: Instant::now() }
+    }
+}
+
+impl Drop for ProfileScope {
+    fn drop(&mut self) {
+        record_scope(self.name, self.start.elapsed());
+    }
+}
+
+/// 把一段耗时累加到本帧名为 `name` 的记录中，同一帧内多次记录会相加
+pub fn record_scope(name: &'static str, elapsed: Duration) {
+    if let Ok(mut profile) = FRAME_PROFILE.lock() {
+        *profile.entry(name).or_default() += elapsed;
+    }
+}
+
+// 帧末由渲染循环调用：把本帧的记录交换到 `finished` 中，并清空旧的记录供下一帧使用，不重新分配
+pub(crate) fn swap_frame_profile(finished: &mut HashMap<&'static str, Duration>) {
+    finished.clear();
+    if let Ok(mut profile) = FRAME_PROFILE.lock() {
+        std::mem::swap(&mut *profile, finished);
+    }
+}
+
+/// 记录当前作用域剩余部分的耗时：`profile_scope!("physics");`
+#[macro_export]
+macro_rules! profile_scope {
+    ($name:expr) => {
+        let _profile_scope = $crate::tools::ProfileScope::new($name);
+    };
+}
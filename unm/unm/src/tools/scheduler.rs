@@ -0,0 +1,212 @@
+use glam::{Vec2, Vec3, Vec4};
+
+use crate::tools::Easing;
+
+// every 的最小间隔，避免间隔为 0 时在一次 update 中无限触发
+const MIN_INTERVAL: f32 = 0.001;
+
+/// `Scheduler` 中任务的句柄，用于取消或暂停任务
+#[derive(Default, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub struct TaskHandle(u64);
+
+/// 可以被 `Scheduler::tween` 插值的类型
+pub trait Lerp: Copy + Send + 'static {
+    fn lerp(self, to: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        self + (to - self) * t
+    }
+}
+
+impl Lerp for Vec2 {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        Vec2::lerp(self, to, t)
+    }
+}
+
+impl Lerp for Vec3 {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        Vec3::lerp(self, to, t)
+    }
+}
+
+impl Lerp for Vec4 {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        Vec4::lerp(self, to, t)
+    }
+}
+
+type OnceCallback<C> = Box<dyn FnOnce(&mut C) + Send>;
+type RepeatCallback<C> = Box<dyn FnMut(&mut C) + Send>;
+// 参数为缓动后的插值系数
+type TweenCallback<C> = Box<dyn FnMut(&mut C, f32) + Send>;
+
+enum TaskKind<C> {
+    After(Option<OnceCallback<C>>),
+    Every { interval: f32, callback: RepeatCallback<C> },
+    Tween { duration: f32, easing: Easing, apply: TweenCallback<C> },
+}
+
+struct Task<C> {
+    handle: TaskHandle,
+    kind: TaskKind<C>,
+    // After/Every 为距离下次触发的剩余时间，Tween 为已经经过的时间
+    time: f32,
+    paused: bool,
+}
+
+/// 延时、定时和补间任务的调度器，由游戏在 `update` 中调用 `update(dt)` 驱动。
+///
+/// 回调收到 `update` 传入的上下文 `&mut C`，通常是游戏状态，这样回调不需要持有游戏结构体的引用。
+/// 传给 `update` 的时间会乘以 `set_time_scale` 设置的倍率，`set_paused(true)` 时所有任务暂停；
+/// 任务按创建顺序执行
+pub struct Scheduler<C = ()> {
+    tasks: Vec<Task<C>>,
+    next_id: u64,
+    time_scale: f32,
+    paused: bool,
+}
+
+impl<C> Default for Scheduler<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C> Scheduler<C> {
+    pub fn new() -> Self {
+        Self { tasks: Vec::new(), next_id: 0, time_scale: 1.0, paused: false }
+    }
+
+    fn push(&mut self, kind: TaskKind<C>, time: f32) -> TaskHandle {
+        let handle = TaskHandle(self.next_id);
+        self.next_id += 1;
+        self.tasks.push(Task { handle, kind, time, paused: false });
+        handle
+    }
+
+    /// `seconds` 秒后调用一次 `callback`
+    pub fn after(&mut self, seconds: f32, callback: impl FnOnce(&mut C) + Send + 'static) -> TaskHandle {
+        self.push(TaskKind::After(Some(Box::new(callback))), seconds.max(0.0))
+    }
+
+    /// 每隔 `seconds` 秒调用一次 `callback`（第一次在 `seconds` 秒后），直到被取消。
+    /// 一次 `update` 跨过多个间隔时会补调相应的次数
+    pub fn every(&mut self, seconds: f32, callback: impl FnMut(&mut C) + Send + 'static) -> TaskHandle {
+        let interval = seconds.max(MIN_INTERVAL);
+        self.push(TaskKind::Every { interval, callback: Box::new(callback) }, interval)
+    }
+
+    /// 在 `duration` 秒内把值从 `from` 按 `easing` 变化到 `to`，每次 `update` 都以当前值调用 `setter`，
+    /// 结束时保证以 `to` 调用一次
+    pub fn tween<T: Lerp>(
+        &mut self,
+        from: T,
+        to: T,
+        duration: f32,
+        easing: Easing,
+        mut setter: impl FnMut(&mut C, T) + Send + 'static,
+    ) -> TaskHandle {
+        let apply: TweenCallback<C> = Box::new(move |ctx: &mut C, t: f32| setter(ctx, from.lerp(to, t)));
+        self.push(TaskKind::Tween { duration: duration.max(0.0), easing, apply }, 0.0)
+    }
+
+    /// 取消任务，任务已经结束或句柄无效时返回 false
+    pub fn cancel(&mut self, handle: TaskHandle) -> bool {
+        let len = self.tasks.len();
+        self.tasks.retain(|task| task.handle != handle);
+        self.tasks.len() != len
+    }
+
+    /// 取消所有任务
+    pub fn clear(&mut self) {
+        self.tasks.clear();
+    }
+
+    /// 任务是否还未结束
+    pub fn is_active(&self, handle: TaskHandle) -> bool {
+        self.tasks.iter().any(|task| task.handle == handle)
+    }
+
+    /// 暂停/恢复单个任务，任务已经结束时返回 false
+    pub fn set_task_paused(&mut self, handle: TaskHandle, paused: bool) -> bool {
+        match self.tasks.iter_mut().find(|task| task.handle == handle) {
+            Some(task) => {
+                task.paused = paused;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 暂停/恢复所有任务，例如打开暂停菜单时
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// 时间倍率（不小于 0），用于慢动作等效果
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale.max(0.0);
+    }
+
+    pub fn get_time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    /// 未结束的任务数
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// 推进 `dt` 秒（通常是 `TimeManager::get_delta_time`），调用到期的回调并移除已经结束的任务
+    pub fn update(&mut self, dt: f32, ctx: &mut C) {
+        if self.paused {
+            return;
+        }
+        let dt = dt.max(0.0) * self.time_scale;
+
+        self.tasks.retain_mut(|task| {
+            if task.paused {
+                return true;
+            }
+            match &mut task.kind {
+                TaskKind::After(callback) => {
+                    task.time -= dt;
+                    if task.time > 0.0 {
+                        return true;
+                    }
+                    if let Some(callback) = callback.take() {
+                        callback(ctx);
+                    }
+                    false
+                }
+                TaskKind::Every { interval, callback } => {
+                    task.time -= dt;
+                    while task.time <= 0.0 {
+                        callback(ctx);
+                        task.time += *interval;
+                    }
+                    true
+                }
+                TaskKind::Tween { duration, easing, apply } => {
+                    task.time += dt;
+                    let progress = if *duration > 0.0 { (task.time / *duration).min(1.0) } else { 1.0 };
+                    // 结束时直接使用 1.0，保证最终值等于 to
+                    let t = if progress >= 1.0 { 1.0 } else { easing.apply(progress) };
+                    apply(ctx, t);
+                    progress < 1.0
+                }
+            }
+        });
+    }
+}
@@ -1,14 +1,62 @@
-use std::{time::{Duration, Instant}};
+use std::{collections::HashMap, time::{Duration, Instant}};
+
+/// 默认保存的帧时间样本数量（约 4 秒 @ 60 FPS）
+pub const DEFAULT_FRAME_HISTORY_LEN: usize = 240;
+/// 默认的最大增量时间，超过时截断
+pub const DEFAULT_MAX_DELTA_TIME: Duration = Duration::from_millis(100);
+
+/// 帧时间分位数（秒），用于发现平均值掩盖掉的卡顿
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct FrameTimePercentiles {
+    pub p50: f32,
+    pub p95: f32,
+    pub p99: f32,
+}
+
+/// 上一帧各阶段在 CPU 上的耗时，由渲染循环记录，用于定位卡顿发生在哪个阶段
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct FramePhaseTimes {
+    /// 固定步、插件和游戏的 update
+    pub update: Duration,
+    /// 插件 draw、合批提交、后处理和调试绘制
+    pub draw: Duration,
+    /// 呈现到 Surface 以及帧末的设置更新
+    pub present: Duration,
+}
+
+impl FramePhaseTimes {
+    /// 耗时最长的阶段名称及其耗时
+    pub fn longest(&self) -> (&'static str, Duration) {
+        [("update", self.update), ("draw", self.draw), ("present", self.present)]
+            .into_iter()
+            .max_by_key(|(_, time)| *time)
+            .unwrap()
+    }
+}
 
 #[derive(Clone)]
 pub struct TimeManager {
     start_time: Instant,
     current_time: Duration,
     delta_time: Duration,
+    // 未截断的增量时间
+    raw_delta_time: Duration,
+    max_delta_time: Duration,
+    // update 被调用的总次数
+    total_frames: u64,
     fps: f32,  // 改为f32保持类型一致
-    frame_times: [f32; 20],  // 帧时间环形缓冲区
+    frame_times: Vec<f32>,  // 帧时间环形缓冲区，长度固定，不在每帧分配
     frame_index: usize,
+    frame_count: usize,     // 环形缓冲区中的有效样本数
+    sorted_scratch: Vec<f32>, // 计算分位数用的临时缓冲区，预先分配
+    percentiles: FrameTimePercentiles,
     last_update: Instant,
+    // 尚未被固定步消耗的时间，以及上一次计算出的插值系数
+    fixed_accumulator: Duration,
+    fixed_alpha: f32,
+    pub(crate) phase_times: FramePhaseTimes,
+    // 上一帧 profile_scope 的记录，帧末由渲染循环交换进来
+    pub(crate) profile_scopes: HashMap<&'static str, Duration>,
     
     pub(crate) sleep_end: Instant,
     pub(crate) sleep_timer: SleepTimer,
@@ -23,15 +71,31 @@ pub(crate) struct SleepTimer {
 #[allow(dead_code)]
 impl TimeManager {
     pub(crate) fn new() -> Self {
+        Self::with_frame_history_len(DEFAULT_FRAME_HISTORY_LEN)
+    }
+
+    /// 使用指定长度（至少为 1）的帧时间环形缓冲区创建
+    pub(crate) fn with_frame_history_len(len: usize) -> Self {
+        let len = len.max(1);
         let start_time = Instant::now();
         Self {
             start_time,
             current_time: Duration::ZERO,
             delta_time: Duration::ZERO,
+            raw_delta_time: Duration::ZERO,
+            max_delta_time: DEFAULT_MAX_DELTA_TIME,
+            total_frames: 0,
             fps: 0.0,
-            frame_times: [0.0; 20],
+            frame_times: vec![0.0; len],
             frame_index: 0,
+            frame_count: 0,
+            sorted_scratch: Vec::with_capacity(len),
+            percentiles: FrameTimePercentiles::default(),
             last_update: start_time,
+            fixed_accumulator: Duration::ZERO,
+            fixed_alpha: 0.0,
+            phase_times: FramePhaseTimes::default(),
+            profile_scopes: HashMap::new(),
             sleep_end: Instant::now(),
             sleep_timer: SleepTimer::default(),
         }
@@ -40,23 +104,91 @@ impl TimeManager {
     pub(crate) fn update(&mut self) {
         let now = Instant::now();
         
-        // 计算增量时间
-        self.delta_time = now.duration_since(self.last_update);
+        // 计算增量时间。断点、拖动窗口或应用切到后台后会得到很长的间隔，截断后再交给游戏逻辑
+        self.raw_delta_time = now.duration_since(self.last_update);
+        self.delta_time = self.raw_delta_time.min(self.max_delta_time);
         self.last_update = now;
         self.current_time = now.duration_since(self.start_time);
+        self.total_frames += 1;
         
-        // 更新帧时间缓冲区
-        let delta_secs = self.delta_time.as_secs_f32();
+        // 更新帧时间缓冲区，统计使用未截断的帧时间
+        let delta_secs = self.raw_delta_time.as_secs_f32();
         self.frame_times[self.frame_index] = delta_secs;
         self.frame_index = (self.frame_index + 1) % self.frame_times.len();
-        
+        self.frame_count = (self.frame_count + 1).min(self.frame_times.len());
+
+        let samples = &self.frame_times[..self.frame_count];
+
         // 计算平均FPS（基于最近N帧）
-        let total_time: f32 = self.frame_times.iter().sum();
+        let total_time: f32 = samples.iter().sum();
         self.fps = if total_time > 0.0 {
-            self.frame_times.len() as f32 / total_time
+            samples.len() as f32 / total_time
         } else {
             0.0
         };
+
+        // 计算分位数：复用预先分配的缓冲区，只做部分排序
+        self.sorted_scratch.clear();
+        self.sorted_scratch.extend_from_slice(samples);
+        self.percentiles = FrameTimePercentiles {
+            p50: select_percentile(&mut self.sorted_scratch, 0.50),
+            p95: select_percentile(&mut self.sorted_scratch, 0.95),
+            p99: select_percentile(&mut self.sorted_scratch, 0.99),
+        };
+    }
+
+    /// 把本帧的增量时间累加到固定步累加器，返回本帧需要执行的固定步数（不超过 `max_steps`）。
+    /// 达到上限时丢弃多余的时间，避免卡顿后追赶不上
+    pub(crate) fn advance_fixed_steps(&mut self, step: Duration, max_steps: u32) -> u32 {
+        if step.is_zero() {
+            return 0;
+        }
+
+        self.fixed_accumulator += self.delta_time;
+        let mut steps = 0;
+        while self.fixed_accumulator >= step && steps < max_steps {
+            self.fixed_accumulator -= step;
+            steps += 1;
+        }
+        if steps == max_steps {
+            self.fixed_accumulator = self.fixed_accumulator.min(step);
+        }
+        self.fixed_alpha = (self.fixed_accumulator.as_secs_f32() / step.as_secs_f32()).min(1.0);
+        steps
+    }
+
+    /// 固定步插值系数 [0, 1]：距离上一个固定步已经过去的时间占一个步长的比例，
+    /// 渲染时可以用 `lerp(previous_state, current_state, alpha)` 在两个固定步的状态之间平滑过渡
+    pub fn get_fixed_alpha(&self) -> f32 {
+        self.fixed_alpha
+    }
+
+    /// 修改保存的帧时间样本数量（至少为 1），已有的样本会被丢弃
+    pub(crate) fn set_frame_history_len(&mut self, len: usize) {
+        let len = len.max(1);
+        if len == self.frame_times.len() {
+            return;
+        }
+
+        self.frame_times = vec![0.0; len];
+        self.sorted_scratch = Vec::with_capacity(len);
+        self.frame_index = 0;
+        self.frame_count = 0;
+    }
+
+    /// 设置 `get_delta_time` 的上限
+    pub(crate) fn set_max_delta_time(&mut self, max_delta_time: Duration) {
+        self.max_delta_time = max_delta_time;
+    }
+
+    // 帧时间环形缓冲区的长度
+    pub fn get_frame_history_len(&self) -> usize {
+        self.frame_times.len()
+    }
+
+    // 获取最近 N 帧帧时间的 p50/p95/p99 (秒)
+    pub fn get_frame_time_percentiles(&self) -> FrameTimePercentiles {
+        self.percentiles
     }
 
     // 获取当前时间 (秒)
@@ -64,11 +196,54 @@ impl TimeManager {
         self.current_time.as_secs_f32()
     }
 
-    // 获取增量时间 (秒)
+    // 获取增量时间 (秒)，不超过 GameSettings::set_max_delta_time 设置的上限
     pub fn get_delta_time(&self) -> f32 {
         self.delta_time.as_secs_f32()
     }
 
+    // 获取未截断的增量时间 (秒)，用于日志等
+    pub fn get_raw_delta_time(&self) -> f32 {
+        self.raw_delta_time.as_secs_f32()
+    }
+
+    // 已经运行的帧数
+    pub fn get_frame_count(&self) -> u64 {
+        self.total_frames
+    }
+
+    /// 最近 N 帧帧时间的任意分位数，`p` 取 [0, 1]，例如 0.99 为 p99。
+    /// 每次调用都会复制并部分排序样本，固定的 p50/p95/p99 请使用 `get_frame_time_percentiles`
+    pub fn frame_time_percentile(&self, p: f32) -> Duration {
+        let mut samples = self.frame_times[..self.frame_count].to_vec();
+        Duration::from_secs_f32(select_percentile(&mut samples, p.clamp(0.0, 1.0)))
+    }
+
+    /// 最近 N 帧中最长的帧时间
+    pub fn worst_frame_time(&self) -> Duration {
+        let worst = self.frame_times[..self.frame_count].iter().copied().fold(0.0, f32::max);
+        Duration::from_secs_f32(worst)
+    }
+
+    /// 上一帧各阶段的耗时，第一帧之前全为 0
+    pub fn get_phase_times(&self) -> FramePhaseTimes {
+        self.phase_times
+    }
+
+    /// 上一帧各 `profile_scope!` 作用域的累计耗时。
+    /// 引擎自身的阶段也会记录在这里：input、update、geometry、buffer upload、encode、present
+    pub fn get_profile_scopes(&self) -> &HashMap<&'static str, Duration> {
+        &self.profile_scopes
+    }
+
+    // 最近 N 帧的平均帧时间 (秒)
+    pub fn get_average_frame_time(&self) -> f32 {
+        let samples = &self.frame_times[..self.frame_count];
+        if samples.is_empty() {
+            return 0.0;
+        }
+        samples.iter().sum::<f32>() / samples.len() as f32
+    }
+
     // 获取平均FPS
     pub fn get_fps(&self) -> u32 {
         self.fps.round() as u32
@@ -82,4 +257,14 @@ impl TimeManager {
             self.current_time.as_secs_f32(),
         );
     }
-}
\ No newline at end of file
+}
+
+// 取第 percentile 分位的值，会打乱 values 的顺序
+fn select_percentile(values: &mut [f32], percentile: f32) -> f32 {
+    if values.is_empty() {
+        return 0.0;
+    }
+
+    let index = ((values.len() - 1) as f32 * percentile).round() as usize;
+    *values.select_nth_unstable_by(index, f32::total_cmp).1
+}
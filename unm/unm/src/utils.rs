@@ -1,4 +1,9 @@
-use wgpu::{Buffer, BufferAddress, BufferDescriptor, BufferUsages, Device, Queue, util::{self, DeviceExt}};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use wgpu::{Buffer, BufferAddress, BufferDescriptor, BufferUsages, Device, MapMode, PollType, Queue, util::{self, DeviceExt}};
 
 pub struct SizedBuffer {
     pub buffer: Buffer,
@@ -26,6 +31,57 @@ impl SizedBuffer {
         }
     }
 
+    /// 创建存储缓冲区并写入初始数据
+    pub fn new_storage(label: &str, device: &Device, data: &[u8]) -> Self {
+        let buffer = device.create_buffer_init(&util::BufferInitDescriptor {
+            label: Some(label),
+            usage: BufferType::Storage.usage(),
+            contents: data,
+        });
+
+        Self {
+            label: label.to_string(),
+            size: data.len(),
+            buffer_type: BufferType::Storage,
+            buffer,
+        }
+    }
+
+    /// 把缓冲区内容拷贝到一块临时的 Read 缓冲区后映射读回，会阻塞直到 GPU 完成之前提交的所有工作。
+    /// 缓冲区需要带 COPY_SRC（例如 Storage 类型），失败时返回 None。
+    pub fn read_back(&self, device: &Device, queue: &Queue) -> Option<Vec<u8>> {
+        // 拷贝长度必须是 COPY_BUFFER_ALIGNMENT 的倍数
+        let copy_size = (self.size as BufferAddress).next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT);
+        if copy_size > self.buffer.size() || !self.buffer.usage().contains(BufferUsages::COPY_SRC) {
+            return None;
+        }
+
+        let staging = SizedBuffer::new(
+            &format!("{} Readback", self.label),
+            device,
+            copy_size as usize,
+            BufferType::Read,
+        );
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Readback Encoder"),
+        });
+        encoder.copy_buffer_to_buffer(&self.buffer, 0, &staging.buffer, 0, copy_size);
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = staging.buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        let _ = device.poll(PollType::wait_indefinitely());
+        receiver.recv().ok()?.ok()?;
+
+        let data = slice.get_mapped_range()[..self.size].to_vec();
+        staging.buffer.unmap();
+        Some(data)
+    }
+
     pub fn ensure_size_and_copy(
         &mut self,
         device: &Device,
@@ -46,6 +102,61 @@ impl SizedBuffer {
     }
 }
 
+/// 按帧轮换的一组 SizedBuffer。
+/// CPU 写入下一帧数据时使用另一块缓冲区，不会和 GPU 正在读取的缓冲区冲突。
+pub struct SizedBufferRing {
+    buffers: Vec<SizedBuffer>,
+    // GPU 是否仍在使用对应的缓冲区，由 on_submitted_work_done 回调清除
+    in_flight: Vec<Arc<AtomicBool>>,
+    current: usize,
+}
+
+impl SizedBufferRing {
+    pub fn new(label: &str, device: &Device, size: usize, buffer_type: BufferType, count: usize) -> Self {
+        let count = count.max(1);
+        let buffers = (0..count)
+            .map(|i| SizedBuffer::new(&format!("{label} #{i}"), device, size, buffer_type))
+            .collect();
+        let in_flight = (0..count).map(|_| Arc::new(AtomicBool::new(false))).collect();
+
+        Self {
+            buffers,
+            in_flight,
+            current: 0,
+        }
+    }
+
+    pub fn current(&self) -> &SizedBuffer {
+        &self.buffers[self.current]
+    }
+
+    pub fn current_mut(&mut self) -> &mut SizedBuffer {
+        &mut self.buffers[self.current]
+    }
+
+    /// 本帧的命令提交完毕后调用，GPU 执行完成时清除当前缓冲区的占用标记
+    pub fn mark_submitted(&self, queue: &Queue) {
+        let flag = self.in_flight[self.current].clone();
+        flag.store(true, Ordering::Release);
+        queue.on_submitted_work_done(move || flag.store(false, Ordering::Release));
+    }
+
+    /// 切换到下一块缓冲区。若它仍被 GPU 占用则阻塞等待，返回是否发生了等待
+    pub fn advance(&mut self, device: &Device) -> bool {
+        self.current = (self.current + 1) % self.buffers.len();
+
+        let flag = &self.in_flight[self.current];
+        if !flag.load(Ordering::Acquire) {
+            return false;
+        }
+
+        let _ = device.poll(PollType::wait_indefinitely());
+        flag.store(false, Ordering::Release);
+        true
+    }
+}
+
+#[derive(Clone, Copy)]
 pub enum BufferType {
     Vertex,
     Index,
@@ -63,9 +174,7 @@ impl BufferType {
             BufferType::Instance => BufferUsages::VERTEX | BufferUsages::COPY_DST,
             BufferType::Uniform => BufferUsages::UNIFORM | BufferUsages::COPY_DST,
             BufferType::Read => BufferUsages::COPY_DST | BufferUsages::MAP_READ,
-            BufferType::Storage => {
-                todo!()
-            }
+            BufferType::Storage => BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
         }
     }
 }
\ No newline at end of file
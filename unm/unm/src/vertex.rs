@@ -9,8 +9,19 @@ pub struct Vertex {
     pub color: [f32; 4],
 }
 
+// 颜色空间约定：
+// 引擎中所有 `wgpu::Color`（顶点颜色、清除颜色、Uniform）都视为线性颜色，
+// 与 wgpu 对 `LoadOp::Clear` 的处理一致。写入 sRGB 格式的渲染目标时由 GPU 完成 gamma 编码，
+// 因此同一个 `wgpu::Color::RED` 无论作为清除颜色还是顶点颜色，显示出来都是同一种红色。
+// 取色器、设计稿中的十六进制颜色是 sRGB 编码的，需要先经过 `srgb_to_linear`（或 `Vertex::new_srgb`）。
 impl Vertex {
+    /// `color` 为线性颜色，等同于 `new_linear`
     pub fn new(pos: Vec3, uv: Vec2, color: Color) -> Self {
+        Self::new_linear(pos, uv, color)
+    }
+
+    /// `color` 为线性颜色，原样写入顶点
+    pub fn new_linear(pos: Vec3, uv: Vec2, color: Color) -> Self {
         Self {
             position: pos.to_array(),
             uv: uv.to_array(),
@@ -22,6 +33,57 @@ impl Vertex {
             ],
         }
     }
+
+    /// `color` 为 sRGB 编码的颜色（例如来自取色器），先转换为线性颜色再写入顶点
+    pub fn new_srgb(pos: Vec3, uv: Vec2, color: Color) -> Self {
+        Self::new_linear(pos, uv, srgb_to_linear(color))
+    }
+
+    /// 顶点颜色逐通道乘以 `tint`（线性颜色），用于整体淡入淡出、染色
+    pub fn tint(&mut self, tint: Color) {
+        let tint = [tint.r as f32, tint.g as f32, tint.b as f32, tint.a as f32];
+        for (c, t) in self.color.iter_mut().zip(tint) {
+            *c *= t;
+        }
+    }
+}
+
+// 单个通道的 sRGB 解码
+fn srgb_channel_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+// 单个通道的 sRGB 编码
+fn linear_channel_to_srgb(c: f64) -> f64 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// 将 sRGB 编码的颜色转换为线性颜色，alpha 不变
+pub fn srgb_to_linear(color: Color) -> Color {
+    Color {
+        r: srgb_channel_to_linear(color.r),
+        g: srgb_channel_to_linear(color.g),
+        b: srgb_channel_to_linear(color.b),
+        a: color.a,
+    }
+}
+
+/// 将线性颜色转换为 sRGB 编码的颜色，alpha 不变
+pub fn linear_to_srgb(color: Color) -> Color {
+    Color {
+        r: linear_channel_to_srgb(color.r),
+        g: linear_channel_to_srgb(color.g),
+        b: linear_channel_to_srgb(color.b),
+        a: color.a,
+    }
 }
 
 impl Vertex {
@@ -52,3 +114,18 @@ pub fn calculate_object_center(vertices: &[Vertex]) -> glam::Vec3 {
     }
     sum_position / (vertices.len() as f32)
 }
+
+/// 顶点在 XY 平面上的包围盒（`(x, y)` 为左下角），没有顶点时返回 `None`
+pub fn calculate_object_bounds(vertices: &[Vertex]) -> Option<crate::camera::Rect> {
+    if vertices.is_empty() {
+        return None;
+    }
+
+    let (mut min, mut max) = (glam::Vec2::splat(f32::MAX), glam::Vec2::splat(f32::MIN));
+    for vertex in vertices {
+        let position = glam::Vec2::new(vertex.position[0], vertex.position[1]);
+        min = min.min(position);
+        max = max.max(position);
+    }
+    Some(crate::camera::Rect { x: min.x, y: min.y, w: max.x - min.x, h: max.y - min.y })
+}